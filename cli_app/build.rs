@@ -0,0 +1,4 @@
+fn main() {
+    // sqlx::migrate! 在编译期嵌入迁移文件，新增迁移时需要重新编译
+    println!("cargo:rerun-if-changed=sqlx/migrations");
+}
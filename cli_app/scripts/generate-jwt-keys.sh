@@ -0,0 +1,21 @@
+#!/bin/sh
+# 生成 config.json 中 jwt.keys 引用的 Ed25519 密钥对，默认写入 keys/jwt.pem 和 keys/jwt.pub.pem
+# 用法: scripts/generate-jwt-keys.sh [目录] [文件名前缀]
+set -eu
+
+dir="${1:-keys}"
+name="${2:-jwt}"
+private_key="$dir/$name.pem"
+public_key="$dir/$name.pub.pem"
+
+if [ -e "$private_key" ]; then
+    echo "$private_key already exists, remove it first to generate a new key" >&2
+    exit 1
+fi
+
+mkdir -p "$dir"
+umask 077
+openssl genpkey -algorithm ed25519 -out "$private_key"
+openssl pkey -in "$private_key" -pubout -out "$public_key"
+chmod 644 "$public_key"
+echo "Wrote $private_key and $public_key"
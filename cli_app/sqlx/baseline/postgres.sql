@@ -0,0 +1,32 @@
+-- Bring a database created from the old sqlx/schema.sql to the shape of
+-- 0001_init so that it can be marked as applied, see `migrate baseline`.
+alter table users alter column id type bigint;
+alter sequence users_id_seq as bigint;
+-- The old schema stored last_login without a time zone, the service always wrote UTC
+alter table users alter column last_login type timestamp with time zone
+    using last_login at time zone 'UTC';
+
+alter table posts alter column id type bigint;
+alter sequence posts_id_seq as bigint;
+alter table posts alter column author_id type bigint;
+
+create unique index if not exists idx_users_username on users(username);
+create unique index if not exists idx_posts_slug on posts(slug);
+
+create or replace function updated_at_column()
+returns trigger as $$
+begin
+    new.updated = current_timestamp;
+    return new;
+end
+$$ language plpgsql;
+
+drop trigger if exists updated_at_column on users;
+create trigger updated_at_column
+before update on users
+for each row execute procedure updated_at_column();
+
+drop trigger if exists updated_at_column on posts;
+create trigger updated_at_column
+before update on posts
+for each row execute procedure updated_at_column();
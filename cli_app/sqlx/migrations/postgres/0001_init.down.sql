@@ -0,0 +1,6 @@
+drop trigger if exists updated_at_column on posts;
+drop trigger if exists updated_at_column on users;
+drop function if exists updated_at_column();
+
+drop table if exists posts;
+drop table if exists users;
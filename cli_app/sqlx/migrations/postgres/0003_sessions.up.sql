@@ -0,0 +1,17 @@
+create table sessions(
+    id varchar(64) primary key,
+    user_id bigint not null,
+    refresh_token_hash varchar(64) not null,
+    expires_at timestamp with time zone not null,
+    revoked_at timestamp with time zone,
+    created timestamp with time zone default current_timestamp,
+    updated timestamp with time zone default current_timestamp,
+    foreign key (user_id) references users(id) on delete cascade
+);
+
+create unique index idx_sessions_refresh_token_hash on sessions(refresh_token_hash);
+create index idx_sessions_user_id on sessions(user_id);
+
+create trigger updated_at_column
+before update on sessions
+for each row execute procedure updated_at_column();
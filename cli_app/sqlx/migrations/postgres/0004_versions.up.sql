@@ -0,0 +1,3 @@
+-- incremented on every write, exposed to clients as the ETag
+alter table users add column version bigint not null default 1;
+alter table posts add column version bigint not null default 1;
@@ -0,0 +1,2 @@
+drop index if exists idx_posts_search_vector;
+alter table posts drop column search_vector;
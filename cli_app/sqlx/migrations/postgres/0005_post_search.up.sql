@@ -0,0 +1,7 @@
+-- titles weigh more than content when ranking search results
+alter table posts add column search_vector tsvector generated always as (
+    setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(content, '')), 'B')
+) stored;
+
+create index idx_posts_search_vector on posts using gin(search_vector);
@@ -0,0 +1,18 @@
+-- full snapshot of a post after each change, `revision` is the post version it produced
+create table post_revisions(
+    post_id bigint not null,
+    revision bigint not null,
+    editor_id bigint,
+    title varchar(255) not null,
+    slug varchar(255) not null,
+    content text not null,
+    status int not null,
+    created timestamp with time zone default current_timestamp,
+    primary key (post_id, revision),
+    foreign key (post_id) references posts(id) on delete cascade,
+    foreign key (editor_id) references users(id) on delete set null
+);
+
+-- existing posts start their history with their current content
+insert into post_revisions(post_id, revision, editor_id, title, slug, content, status, created)
+select id, version, author_id, title, slug, content, status, updated from posts;
@@ -0,0 +1,3 @@
+-- rows with deleted_at set are in the trash and hidden from regular queries
+alter table users add column deleted_at timestamp with time zone;
+alter table posts add column deleted_at timestamp with time zone;
@@ -0,0 +1,4 @@
+alter table posts drop column category_id;
+drop table if exists post_tags;
+drop table if exists tags;
+drop table if exists categories;
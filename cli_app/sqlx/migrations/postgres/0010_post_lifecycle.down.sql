@@ -0,0 +1,8 @@
+drop index if exists idx_posts_publish_at;
+
+-- statuses added by this migration are unknown to older versions, keep those posts as drafts
+update posts set status = 1 where status not in (1, 2);
+update post_revisions set status = 1 where status not in (1, 2);
+
+alter table posts drop column published_at;
+alter table posts drop column publish_at;
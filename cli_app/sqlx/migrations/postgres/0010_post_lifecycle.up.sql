@@ -0,0 +1,7 @@
+-- `publish_at` is when a scheduled post goes live, `published_at` when a post was first published
+alter table posts add column publish_at timestamp with time zone;
+alter table posts add column published_at timestamp with time zone;
+
+update posts set published_at = created where status = 2;
+
+create index idx_posts_publish_at on posts(publish_at) where publish_at is not null;
@@ -0,0 +1,4 @@
+drop table if exists password_resets;
+
+drop index if exists idx_users_email;
+alter table users drop column email;
@@ -0,0 +1,16 @@
+-- address that password reset mails are sent to, unique regardless of case
+alter table users add column email varchar(255);
+
+create unique index idx_users_email on users(lower(email));
+
+-- single-use reset tokens, only the SHA-256 hash of a token is stored
+create table password_resets(
+    token_hash varchar(64) primary key,
+    user_id bigint not null,
+    expires_at timestamp with time zone not null,
+    used_at timestamp with time zone,
+    created timestamp with time zone default current_timestamp,
+    foreign key (user_id) references users(id) on delete cascade
+);
+
+create index idx_password_resets_user_id on password_resets(user_id);
@@ -0,0 +1,22 @@
+-- TOTP secret of a user, encrypted with the key from the settings;
+-- enabled_at is null until the first code has been verified
+create table user_totp(
+    user_id bigint primary key,
+    secret text not null,
+    enabled_at timestamp with time zone,
+    -- last accepted time step, a code cannot be used twice
+    last_step bigint,
+    created timestamp with time zone default current_timestamp,
+    foreign key (user_id) references users(id) on delete cascade
+);
+
+-- single-use recovery codes, only the SHA-256 hash of a code is stored
+create table recovery_codes(
+    code_hash varchar(64) primary key,
+    user_id bigint not null,
+    used_at timestamp with time zone,
+    created timestamp with time zone default current_timestamp,
+    foreign key (user_id) references users(id) on delete cascade
+);
+
+create index idx_recovery_codes_user_id on recovery_codes(user_id);
@@ -0,0 +1,5 @@
+delete from comments where author_id is null;
+alter table comments drop constraint comments_author_id_fkey;
+alter table comments add constraint comments_author_id_fkey
+    foreign key (author_id) references users(id) on delete cascade;
+alter table comments alter column author_id set not null;
@@ -0,0 +1,5 @@
+-- purging a user keeps their comments, the author becomes null and is shown as a deleted user
+alter table comments alter column author_id drop not null;
+alter table comments drop constraint comments_author_id_fkey;
+alter table comments add constraint comments_author_id_fkey
+    foreign key (author_id) references users(id) on delete set null;
@@ -0,0 +1,2 @@
+drop table if exists posts;
+drop table if exists users;
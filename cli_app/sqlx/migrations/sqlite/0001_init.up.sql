@@ -0,0 +1,25 @@
+create table users(
+    id integer primary key autoincrement,
+    username varchar(255) not null,
+    password varchar(255) not null,
+    status integer not null default 1,
+    created timestamp default current_timestamp,
+    updated timestamp default current_timestamp,
+    last_login timestamp
+);
+
+create unique index idx_users_username on users(username);
+
+create table posts(
+    id integer primary key autoincrement,
+    author_id integer not null,
+    slug varchar(255) not null,
+    title varchar(255) not null,
+    content text not null,
+    status integer not null default 1,
+    created timestamp default current_timestamp,
+    updated timestamp default current_timestamp,
+    foreign key (author_id) references users(id)
+);
+
+create unique index idx_posts_slug on posts(slug);
@@ -0,0 +1,5 @@
+-- 1 = admin, 2 = editor, 3 = author, 4 = reader
+alter table users add column role integer not null default 4;
+
+-- existing authors keep the ability to write posts
+update users set role = 3 where id in (select distinct author_id from posts);
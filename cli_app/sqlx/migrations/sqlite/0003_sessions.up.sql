@@ -0,0 +1,13 @@
+create table sessions(
+    id varchar(64) primary key,
+    user_id integer not null,
+    refresh_token_hash varchar(64) not null,
+    expires_at timestamp not null,
+    revoked_at timestamp,
+    created timestamp default current_timestamp,
+    updated timestamp default current_timestamp,
+    foreign key (user_id) references users(id) on delete cascade
+);
+
+create unique index idx_sessions_refresh_token_hash on sessions(refresh_token_hash);
+create index idx_sessions_user_id on sessions(user_id);
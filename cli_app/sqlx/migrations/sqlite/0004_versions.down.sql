@@ -0,0 +1,2 @@
+alter table posts drop column version;
+alter table users drop column version;
@@ -0,0 +1,3 @@
+-- incremented on every write, exposed to clients as the ETag
+alter table users add column version integer not null default 1;
+alter table posts add column version integer not null default 1;
@@ -0,0 +1,16 @@
+create virtual table posts_fts using fts5(title, content, content='posts', content_rowid='id');
+
+insert into posts_fts(rowid, title, content) select id, title, content from posts;
+
+create trigger posts_fts_insert after insert on posts begin
+    insert into posts_fts(rowid, title, content) values (new.id, new.title, new.content);
+end;
+
+create trigger posts_fts_delete after delete on posts begin
+    insert into posts_fts(posts_fts, rowid, title, content) values ('delete', old.id, old.title, old.content);
+end;
+
+create trigger posts_fts_update after update of title, content on posts begin
+    insert into posts_fts(posts_fts, rowid, title, content) values ('delete', old.id, old.title, old.content);
+    insert into posts_fts(rowid, title, content) values (new.id, new.title, new.content);
+end;
@@ -0,0 +1,2 @@
+alter table posts drop column deleted_at;
+alter table users drop column deleted_at;
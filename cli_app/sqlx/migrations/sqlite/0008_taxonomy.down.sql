@@ -0,0 +1,5 @@
+drop index if exists idx_posts_category_id;
+alter table posts drop column category_id;
+drop table if exists post_tags;
+drop table if exists tags;
+drop table if exists categories;
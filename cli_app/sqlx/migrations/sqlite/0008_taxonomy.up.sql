@@ -0,0 +1,34 @@
+-- categories form a tree, a category with subcategories cannot be deleted
+create table categories(
+    id integer primary key autoincrement,
+    parent_id integer,
+    name varchar(255) not null,
+    slug varchar(255) not null,
+    created timestamp default current_timestamp,
+    foreign key (parent_id) references categories(id)
+);
+
+create unique index idx_categories_slug on categories(slug);
+create index idx_categories_parent_id on categories(parent_id);
+
+create table tags(
+    id integer primary key autoincrement,
+    name varchar(64) not null,
+    slug varchar(64) not null,
+    created timestamp default current_timestamp
+);
+
+create unique index idx_tags_slug on tags(slug);
+
+create table post_tags(
+    post_id integer not null,
+    tag_id integer not null,
+    primary key (post_id, tag_id),
+    foreign key (post_id) references posts(id) on delete cascade,
+    foreign key (tag_id) references tags(id) on delete cascade
+);
+
+create index idx_post_tags_tag_id on post_tags(tag_id);
+
+alter table posts add column category_id integer references categories(id) on delete set null;
+create index idx_posts_category_id on posts(category_id);
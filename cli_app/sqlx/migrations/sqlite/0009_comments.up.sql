@@ -0,0 +1,17 @@
+-- comments on posts, replies point to the comment they answer through `parent_id`
+create table comments(
+    id integer primary key autoincrement,
+    post_id integer not null,
+    author_id integer not null,
+    parent_id integer,
+    content text not null,
+    status integer not null default 1,
+    created timestamp default current_timestamp,
+    updated timestamp default current_timestamp,
+    foreign key (post_id) references posts(id) on delete cascade,
+    foreign key (author_id) references users(id) on delete cascade,
+    foreign key (parent_id) references comments(id) on delete set null
+);
+
+create index idx_comments_post_id on comments(post_id);
+create index idx_comments_parent_id on comments(parent_id);
@@ -0,0 +1,25 @@
+-- purging a user keeps their comments, the author becomes null and is shown as a deleted user;
+-- SQLite cannot change a foreign key in place, so the table is rebuilt
+alter table comments rename to comments_old;
+
+create table comments(
+    id integer primary key autoincrement,
+    post_id integer not null,
+    author_id integer,
+    parent_id integer,
+    content text not null,
+    status integer not null default 1,
+    created timestamp default current_timestamp,
+    updated timestamp default current_timestamp,
+    foreign key (post_id) references posts(id) on delete cascade,
+    foreign key (author_id) references users(id) on delete set null,
+    foreign key (parent_id) references comments(id) on delete set null
+);
+
+insert into comments(id,post_id,author_id,parent_id,content,status,created,updated)
+select id,post_id,author_id,parent_id,content,status,created,updated from comments_old;
+
+drop table comments_old;
+
+create index idx_comments_post_id on comments(post_id);
+create index idx_comments_parent_id on comments(parent_id);
@@ -0,0 +1,123 @@
+use std::convert::Infallible;
+
+use axum::{
+    Json,
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::services::error::ServiceError;
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// 资源版本对应的强实体标签
+pub fn entity_tag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// `If-Match` 请求头，写操作只在资源当前版本与之匹配时执行
+pub enum IfMatch {
+    Absent,
+    Any,
+    Tags(Vec<String>),
+}
+
+impl IfMatch {
+    /// 校验资源当前版本，返回写入时需要再次比较的版本，不匹配时返回 412
+    pub fn check(&self, version: i64) -> Result<Option<i64>, ServiceError> {
+        let matched = match self {
+            IfMatch::Absent => return Ok(None),
+            IfMatch::Any => true,
+            //If-Match 使用强比较，弱标签不会匹配
+            IfMatch::Tags(tags) => tags.contains(&entity_tag(version)),
+        };
+        if !matched {
+            return Err(ServiceError::PreconditionFailed(
+                "Resource has been modified".to_string(),
+            ));
+        }
+        Ok(Some(version))
+    }
+}
+
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(match entity_tags(&parts.headers, header::IF_MATCH) {
+            None => IfMatch::Absent,
+            Some(tags) if tags.iter().any(|tag| tag == "*") => IfMatch::Any,
+            Some(tags) => IfMatch::Tags(tags),
+        })
+    }
+}
+
+/// 为 GET 响应处理 `If-None-Match` 和 `If-Modified-Since`，资源未变化时返回 304
+pub fn conditional<T: Serialize>(
+    headers: &HeaderMap,
+    version: i64,
+    updated: DateTime<Utc>,
+    body: T,
+) -> Response {
+    if not_modified(headers, version, updated) {
+        return with_validators(version, updated, StatusCode::NOT_MODIFIED);
+    }
+    with_validators(version, updated, Json(body))
+}
+
+/// 在响应中附加 `ETag` 和 `Last-Modified`
+pub fn with_validators(
+    version: i64,
+    updated: DateTime<Utc>,
+    response: impl IntoResponse,
+) -> Response {
+    let mut response = response.into_response();
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&entity_tag(version)) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&updated.format(HTTP_DATE).to_string()) {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+    response
+}
+
+fn not_modified(headers: &HeaderMap, version: i64, updated: DateTime<Utc>) -> bool {
+    //If-None-Match 使用弱比较，存在时忽略 If-Modified-Since
+    if let Some(tags) = entity_tags(headers, header::IF_NONE_MATCH) {
+        let etag = entity_tag(version);
+        return tags
+            .iter()
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| updated.timestamp() <= since.timestamp())
+}
+
+fn entity_tags(headers: &HeaderMap, name: HeaderName) -> Option<Vec<String>> {
+    let values: Vec<&str> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    Some(
+        values
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+    )
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{FromRequest, FromRequestParts, Request, rejection::JsonRejection},
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::{
+    api::{middleware::auth::verify_bearer, response::TokenClaims},
+    apperr::{AppError, FieldError},
+    state::ApplicationState,
+};
+
+/// 解析 JSON 请求体并按类型上声明的规则校验，失败时返回 422
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|e: JsonRejection| AppError::new(e.status(), e.body_text()))?;
+        value
+            .validate()
+            .map_err(|e| AppError::validation(field_errors(&e)))?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+/// 可选的登录令牌：请求带有 Bearer 令牌时校验并解析，没有时为 `None`，
+/// 用于匿名和登录用户都能访问、但返回内容不同的接口
+pub struct OptionalClaims(pub Option<TokenClaims>);
+
+impl FromRequestParts<Arc<ApplicationState>> for OptionalClaims {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<ApplicationState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(OptionalClaims(verify_bearer(state, &parts.headers).await?))
+    }
+}
+
+fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut result: Vec<FieldError> = errors
+        .errors()
+        .iter()
+        .flat_map(|(field, kind)| match kind {
+            ValidationErrorsKind::Field(errors) => errors
+                .iter()
+                .map(|e| FieldError {
+                    field: field.to_string(),
+                    code: e.code.to_string(),
+                    message: e
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| e.code.to_string()),
+                })
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect();
+    result.sort_by(|a, b| a.field.cmp(&b.field));
+    result
+}
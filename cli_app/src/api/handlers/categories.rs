@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use crate::{
+    api::{
+        extract::ValidatedJson,
+        request::category::{CreateCategoryRequest, UpdateCategoryRequest},
+        response::category::{CategoryNode, CategoryTreeResponse, SingleCategoryResponse},
+    },
+    apperr::AppError,
+    state::ApplicationState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/categories",
+    responses(
+        (status = 200, description = "All categories as a tree", body = CategoryTreeResponse),
+    ),
+    tag = "Categories",
+)]
+pub async fn tree(
+    State(state): State<Arc<ApplicationState>>,
+) -> Result<Json<CategoryTreeResponse>, AppError> {
+    let categories = state.post_service.list_categories().await?;
+    Ok(Json(CategoryTreeResponse {
+        data: CategoryNode::tree(categories),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/categories/{id}",
+    responses(
+        (status = 200, description = "Category found", body = SingleCategoryResponse),
+        (status = 404, description = "Category not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Category ID"),
+    ),
+    tag = "Categories",
+)]
+pub async fn get(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SingleCategoryResponse>, AppError> {
+    let category = state.post_service.get_category(id).await?;
+    Ok(Json(SingleCategoryResponse { data: category }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/categories",
+    request_body = CreateCategoryRequest,
+    responses(
+        (status = 200, description = "Category created successfully", body = SingleCategoryResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Category slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed or parent category not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Categories",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn create(
+    State(state): State<Arc<ApplicationState>>,
+    ValidatedJson(payload): ValidatedJson<CreateCategoryRequest>,
+) -> Result<Json<SingleCategoryResponse>, AppError> {
+    let category = state.post_service.create_category(payload).await?;
+    Ok(Json(SingleCategoryResponse { data: category }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/categories/{id}",
+    request_body = UpdateCategoryRequest,
+    responses(
+        (status = 200, description = "Category updated successfully", body = SingleCategoryResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Category not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Category slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed, parent category not found or would create a cycle", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Category ID"),
+    ),
+    tag = "Categories",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn update(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateCategoryRequest>,
+) -> Result<Json<SingleCategoryResponse>, AppError> {
+    let category = state.post_service.update_category(id, payload).await?;
+    Ok(Json(SingleCategoryResponse { data: category }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/categories/{id}",
+    responses(
+        (status = 200, description = "Category deleted, its posts become uncategorized"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Category not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Category still has subcategories", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Category ID"),
+    ),
+    tag = "Categories",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn delete(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<(), AppError> {
+    state.post_service.delete_category(id).await?;
+    Ok(())
+}
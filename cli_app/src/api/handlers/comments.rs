@@ -0,0 +1,274 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+use crate::{
+    api::{
+        extract::{OptionalClaims, ValidatedJson},
+        request::comment::{CreateCommentRequest, UpdateCommentRequest},
+        response::{
+            TokenClaims,
+            comment::{ListCommentResponse, SingleCommentResponse},
+        },
+    },
+    apperr::AppError,
+    model::{Comment, CommentStatus, PostStatus},
+    services::post::Visibility,
+    state::ApplicationState,
+};
+
+use super::{current_user, post_visibility, posts::authorize_owner};
+
+/// 只有评论作者本人可以修改、删除评论
+async fn authorize_commenter(
+    state: &ApplicationState,
+    claims: &TokenClaims,
+    id: i64,
+    comment_id: i64,
+) -> Result<Comment, AppError> {
+    let user = current_user(state, claims).await?;
+    state
+        .post_service
+        .get_post_by_id(id, Visibility::All)
+        .await?;
+    let comment = state.comment_service.get_comment(id, comment_id).await?;
+    if comment.author_id != Some(user.id) {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Only the author can modify this comment"),
+        )));
+    }
+    Ok(comment)
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/comments",
+    responses(
+        (status = 200, description = "Visible comments of the post, oldest first", body = ListCommentResponse),
+        (status = 401, description = "Invalid or revoked token", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found or not visible to the caller", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+    ),
+    tag = "Comments",
+    security(
+        (),
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn list(
+    OptionalClaims(claims): OptionalClaims,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ListCommentResponse>, AppError> {
+    let visibility = post_visibility(&state, claims.as_ref()).await?;
+    state.post_service.get_post_by_id(id, visibility).await?;
+    let comments = state
+        .comment_service
+        .list_comments(id, Some(CommentStatus::Approved))
+        .await?;
+    Ok(Json(ListCommentResponse { data: comments }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/comments/hidden",
+    responses(
+        (status = 200, description = "Hidden comments of the post, oldest first", body = ListCommentResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn list_hidden(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ListCommentResponse>, AppError> {
+    authorize_owner(&state, &claims, id).await?;
+    let comments = state
+        .comment_service
+        .list_comments(id, Some(CommentStatus::Hidden))
+        .await?;
+    Ok(Json(ListCommentResponse { data: comments }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/comments",
+    request_body = CreateCommentRequest,
+    responses(
+        (status = 200, description = "Comment created successfully", body = SingleCommentResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Post is not published", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed or parent comment not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn create(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<CreateCommentRequest>,
+) -> Result<Json<SingleCommentResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let post = state
+        .post_service
+        .get_post_by_id(id, Visibility::All)
+        .await?;
+    if post.status != PostStatus::Published {
+        return Err(AppError::from((
+            StatusCode::CONFLICT,
+            anyhow::anyhow!("Only published posts accept comments"),
+        )));
+    }
+    let comment = state
+        .comment_service
+        .create_comment(id, user.id, payload)
+        .await?;
+    Ok(Json(SingleCommentResponse { data: comment }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/posts/{id}/comments/{comment_id}",
+    request_body = UpdateCommentRequest,
+    responses(
+        (status = 200, description = "Comment updated successfully", body = SingleCommentResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the comment", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or comment not found", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("comment_id"=i64, Path, description = "Comment ID"),
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn update(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, comment_id)): Path<(i64, i64)>,
+    ValidatedJson(payload): ValidatedJson<UpdateCommentRequest>,
+) -> Result<Json<SingleCommentResponse>, AppError> {
+    authorize_commenter(&state, &claims, id, comment_id).await?;
+    let comment = state
+        .comment_service
+        .update_comment(id, comment_id, payload)
+        .await?;
+    Ok(Json(SingleCommentResponse { data: comment }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/posts/{id}/comments/{comment_id}",
+    responses(
+        (status = 200, description = "Comment deleted, its replies move up one level"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the comment", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or comment not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("comment_id"=i64, Path, description = "Comment ID"),
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn delete(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, comment_id)): Path<(i64, i64)>,
+) -> Result<(), AppError> {
+    authorize_commenter(&state, &claims, id, comment_id).await?;
+    state.comment_service.delete_comment(id, comment_id).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/comments/{comment_id}/hide",
+    responses(
+        (status = 200, description = "Comment hidden from readers", body = SingleCommentResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or comment not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("comment_id"=i64, Path, description = "Comment ID"),
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn hide(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, comment_id)): Path<(i64, i64)>,
+) -> Result<Json<SingleCommentResponse>, AppError> {
+    authorize_owner(&state, &claims, id).await?;
+    let comment = state
+        .comment_service
+        .set_comment_status(id, comment_id, CommentStatus::Hidden)
+        .await?;
+    Ok(Json(SingleCommentResponse { data: comment }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/comments/{comment_id}/approve",
+    responses(
+        (status = 200, description = "Comment visible to readers again", body = SingleCommentResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or comment not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("comment_id"=i64, Path, description = "Comment ID"),
+    ),
+    tag = "Comments",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn approve(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, comment_id)): Path<(i64, i64)>,
+) -> Result<Json<SingleCommentResponse>, AppError> {
+    authorize_owner(&state, &claims, id).await?;
+    let comment = state
+        .comment_service
+        .set_comment_status(id, comment_id, CommentStatus::Approved)
+        .await?;
+    Ok(Json(SingleCommentResponse { data: comment }))
+}
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::state::ApplicationState;
+
+/// 发布验证令牌所用的公钥，包括轮换中仍被接受的旧密钥
+pub async fn jwks(State(state): State<Arc<ApplicationState>>) -> Json<JwkSet> {
+    Json(state.keys.jwks().clone())
+}
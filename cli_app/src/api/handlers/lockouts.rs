@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+use crate::{
+    api::response::login::ListLockoutResponse, apperr::AppError, model::LockoutKind,
+    state::ApplicationState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/lockouts",
+    responses(
+        (status = 200, description = "Usernames and IP addresses with recent failed logins", body = ListLockoutResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Login",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn list(State(state): State<Arc<ApplicationState>>) -> Json<ListLockoutResponse> {
+    let protection = state.settings.load().login_protection.clone();
+    Json(ListLockoutResponse {
+        data: state.login_throttle.lockouts(&protection),
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lockouts/{kind}/{value}",
+    responses(
+        (status = 200, description = "Failed login attempts cleared"),
+        (status = 400, description = "Unknown lockout kind", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "No failed login attempts recorded", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("kind" = LockoutKind, Path, description = "Either username or ip"),
+        ("value" = String, Path, description = "Username or IP address"),
+    ),
+    tag = "Login",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn clear(
+    State(state): State<Arc<ApplicationState>>,
+    Path((kind, value)): Path<(String, String)>,
+) -> Result<(), AppError> {
+    let kind = kind
+        .parse::<LockoutKind>()
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    if !state.login_throttle.clear(kind, &value) {
+        return Err(AppError::from((
+            StatusCode::NOT_FOUND,
+            anyhow::anyhow!("No failed login attempts recorded for {}", value),
+        )));
+    }
+    Ok(())
+}
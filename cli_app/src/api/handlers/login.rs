@@ -1,26 +1,54 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::{Json, extract::State, http::StatusCode};
-use jsonwebtoken::{EncodingKey, Header, encode};
+use axum::{
+    Extension, Json,
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     api::{
-        request::login::LoginRequest,
-        response::{TokenClaims, login::LoginResponse},
+        request::login::{LoginRequest, LoginTwoFactorRequest, RefreshTokenRequest},
+        response::{
+            TokenClaims,
+            login::{LoginResponse, TwoFactorChallengeResponse},
+        },
     },
     apperr::AppError,
-    services::user::UserService,
+    model::{LockoutKind, TwoFactor, User, UserStatus},
+    services::error::ServiceError,
     state::ApplicationState,
 };
 
-use crate::utils::password;
+use crate::utils::token;
+
+use super::two_factor::check_code;
+
+/// 两步验证令牌的 `purpose`
+const TWO_FACTOR_PURPOSE: &str = "2fa";
+
+/// 密码校验通过后签发的两步验证令牌，没有 `jti`，不能作为访问令牌使用
+#[derive(Serialize, Deserialize)]
+struct ChallengeClaims {
+    /// 用户 ID
+    sub: String,
+    iat: usize,
+    exp: usize,
+    purpose: String,
+}
+
 #[utoipa::path(
     post,
     path = "/login",
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
-        (status = 401, description = "Invalid credentials",),
+        (status = 202, description = "Password accepted, complete the login with `/login/2fa`", body = TwoFactorChallengeResponse),
+        (status = 401, description = "Invalid credentials", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "User is blocked", body = AppError, content_type = "application/problem+json"),
+        (status = 429, description = "Too many failed login attempts", body = AppError, content_type = "application/problem+json"),
     ),
     tag = "Login",
     security(
@@ -29,54 +57,306 @@ use crate::utils::password;
 )]
 pub async fn login(
     State(state): State<Arc<ApplicationState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>, AppError> {
-    //查询用户
+) -> Result<Response, AppError> {
+    let protection = state.settings.load().login_protection.clone();
+    let keys = [
+        (LockoutKind::Username, payload.username.clone()),
+        (LockoutKind::Ip, addr.ip().to_string()),
+    ];
+
+    //失败次数过多时拒绝尝试
+    if let Some(retry_after) = state.login_throttle.check(&keys, &protection) {
+        let seconds = (retry_after - chrono::Utc::now()).num_seconds().max(1);
+        return Err(AppError::from((
+            StatusCode::TOO_MANY_REQUESTS,
+            anyhow::anyhow!(
+                "Too many failed login attempts, retry in {} seconds",
+                seconds
+            ),
+        )));
+    }
+
+    //查询用户并校验密码
     let user = match state
         .user_service
         .get_user_by_username(&payload.username)
         .await
     {
-        Ok(user) => user,
-        Err(_) => {
+        Ok(user)
+            if state
+                .user_service
+                .verify_password(&user, &payload.password)
+                .await? =>
+        {
+            user
+        }
+        //数据库错误或无法识别的状态、角色不能当作密码错误
+        Err(err) if !matches!(err, ServiceError::NotFound(_)) => return Err(err.into()),
+        _ => {
+            state.login_throttle.record_failure(&keys, &protection);
             return Err(AppError::from((
                 StatusCode::UNAUTHORIZED,
                 anyhow::anyhow!("Invalid username or password"),
             )));
         }
     };
-    //校验密码
-    let password = payload.password;
-    password::validate_password(&password, &user.password)?;
+    if user.status == UserStatus::Blocked {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("User is blocked"),
+        )));
+    }
+
+    //启用两步验证时先签发验证令牌，动态码校验通过后才创建会话
+    if state
+        .two_factor_service
+        .get_two_factor(user.id)
+        .await?
+        .is_some_and(|two_factor| two_factor.is_enabled())
+    {
+        let response = challenge(&state, &user)?;
+        return Ok((StatusCode::ACCEPTED, Json(response)).into_response());
+    }
+
+    state.login_throttle.record_success(&user.username);
+    Ok(Json(complete_login(&state, &user).await?).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    request_body = LoginTwoFactorRequest,
+    responses(
+        (status = 200, description = "Login successful", body = LoginResponse),
+        (status = 401, description = "Invalid or expired challenge token, or invalid code", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "User is blocked", body = AppError, content_type = "application/problem+json"),
+        (status = 429, description = "Too many failed login attempts", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Login",
+)]
+pub async fn login_2fa(
+    State(state): State<Arc<ApplicationState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(payload): Json<LoginTwoFactorRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    let invalid = || {
+        AppError::from((
+            StatusCode::UNAUTHORIZED,
+            anyhow::anyhow!("Invalid or expired challenge token"),
+        ))
+    };
+    let claims = state
+        .keys
+        .decode::<ChallengeClaims>(&payload.challenge_token)
+        .map_err(|_| invalid())?;
+    if claims.purpose != TWO_FACTOR_PURPOSE {
+        return Err(invalid());
+    }
+    let id = claims.sub.parse::<i64>().map_err(|_| invalid())?;
+    let user = state
+        .user_service
+        .get_user_by_id(id)
+        .await
+        .map_err(|_| invalid())?;
+
+    //动态码的错误次数与密码共用登录失败保护
+    let protection = state.settings.load().login_protection.clone();
+    let keys = [
+        (LockoutKind::Username, user.username.clone()),
+        (LockoutKind::Ip, addr.ip().to_string()),
+    ];
+    if let Some(retry_after) = state.login_throttle.check(&keys, &protection) {
+        let seconds = (retry_after - chrono::Utc::now()).num_seconds().max(1);
+        return Err(AppError::from((
+            StatusCode::TOO_MANY_REQUESTS,
+            anyhow::anyhow!(
+                "Too many failed login attempts, retry in {} seconds",
+                seconds
+            ),
+        )));
+    }
+
+    if user.status == UserStatus::Blocked {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("User is blocked"),
+        )));
+    }
+    let two_factor = state
+        .two_factor_service
+        .get_two_factor(user.id)
+        .await?
+        .filter(TwoFactor::is_enabled)
+        .ok_or_else(invalid)?;
+    if !check_code(&state, &two_factor, &payload.code).await? {
+        state.login_throttle.record_failure(&keys, &protection);
+        return Err(AppError::from((
+            StatusCode::UNAUTHORIZED,
+            anyhow::anyhow!("Invalid two-factor code"),
+        )));
+    }
+
+    state.login_throttle.record_success(&user.username);
+    Ok(Json(complete_login(&state, &user).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/token/refresh",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "Tokens refreshed", body = LoginResponse),
+        (status = 401, description = "Invalid, expired or revoked refresh token", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "User is blocked", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Login",
+)]
+pub async fn refresh(
+    State(state): State<Arc<ApplicationState>>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    let invalid = || {
+        AppError::from((
+            StatusCode::UNAUTHORIZED,
+            anyhow::anyhow!("Invalid refresh token"),
+        ))
+    };
 
+    let current_hash = token::hash_token(&payload.refresh_token);
+    let session = state
+        .session_service
+        .get_session_by_refresh_token(&current_hash)
+        .await
+        .map_err(|err| match err {
+            ServiceError::NotFound(_) => invalid(),
+            err => err.into(),
+        })?;
+    if !session.is_active() {
+        return Err(invalid());
+    }
+    let user = state
+        .user_service
+        .get_user_by_id(session.user_id)
+        .await
+        .map_err(|_| invalid())?;
+    if user.status == UserStatus::Blocked {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("User is blocked"),
+        )));
+    }
+
+    //轮换刷新令牌，旧的刷新令牌随即失效
+    let refresh_token = token::generate_token(32);
+    let session = state
+        .session_service
+        .rotate_refresh_token(
+            &session.id,
+            &current_hash,
+            &token::hash_token(&refresh_token),
+            refresh_token_expiry(&state),
+        )
+        .await
+        .map_err(|err| match err {
+            //刷新令牌已被并发的请求使用或会话已被撤销
+            ServiceError::NotFound(_) | ServiceError::Conflict(_) => invalid(),
+            err => err.into(),
+        })?;
+
+    let response = LoginResponse {
+        status: "success".to_string(),
+        token: access_token(&state, &user, &session.id)?,
+        refresh_token,
+    };
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Login",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn logout(
+    State(state): State<Arc<ApplicationState>>,
+    Extension(claims): Extension<TokenClaims>,
+) -> Result<(), AppError> {
+    state.session_service.revoke_session(&claims.jti).await?;
+    Ok(())
+}
+
+/// 记录登录时间，创建会话并签发令牌
+async fn complete_login(state: &ApplicationState, user: &User) -> Result<LoginResponse, AppError> {
+    state.user_service.record_login(user.id).await?;
+
+    let refresh_token = token::generate_token(32);
+    let session = state
+        .session_service
+        .create_session(
+            user.id,
+            &token::hash_token(&refresh_token),
+            refresh_token_expiry(state),
+        )
+        .await?;
+
+    Ok(LoginResponse {
+        status: "success".to_string(),
+        token: access_token(state, user, &session.id)?,
+        refresh_token,
+    })
+}
+
+/// 签发两步验证令牌
+fn challenge(state: &ApplicationState, user: &User) -> anyhow::Result<TwoFactorChallengeResponse> {
+    let timeout = state.settings.load().two_factor.challenge_timeout_seconds;
+
+    let now = chrono::Utc::now();
+    let claims = ChallengeClaims {
+        sub: user.id.to_string(),
+        iat: now.timestamp() as usize,
+        exp: (now + chrono::Duration::seconds(timeout)).timestamp() as usize,
+        purpose: TWO_FACTOR_PURPOSE.to_string(),
+    };
+
+    Ok(TwoFactorChallengeResponse {
+        status: "2fa_required".to_string(),
+        challenge_token: state.keys.encode(&claims)?,
+        expires_in: timeout,
+    })
+}
+
+fn refresh_token_expiry(state: &ApplicationState) -> chrono::DateTime<chrono::Utc> {
+    let timeout = state
+        .settings
+        .load()
+        .refresh_token_timeout_seconds
+        .unwrap_or(30 * 24 * 3600);
+    chrono::Utc::now() + chrono::Duration::seconds(timeout)
+}
+
+/// 为会话签发访问令牌，`jti` 记录会话 ID
+fn access_token(state: &ApplicationState, user: &User, session_id: &str) -> anyhow::Result<String> {
     let timeout = state.settings.load().token_timeout_seconds.unwrap_or(3600);
 
     let now = chrono::Utc::now();
     let iat = now.timestamp() as usize;
     let exp = (now + chrono::Duration::seconds(timeout)).timestamp() as usize;
     let claims = TokenClaims {
-        sub: payload.username.clone(),
+        sub: user.id.to_string(),
         exp,
         iat,
+        jti: session_id.to_string(),
+        role: user.role,
     };
 
-    let secret = state
-        .settings
-        .load()
-        .token_secret
-        .clone()
-        .unwrap_or("secret".to_string());
-
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
-    .unwrap();
-
-    let response = LoginResponse {
-        status: "success".to_string(),
-        token,
-    };
-    Ok(Json(response))
+    let token = state.keys.encode(&claims)?;
+    Ok(token)
 }
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+
+use crate::{
+    api::{
+        conditional::{self, IfMatch},
+        extract::ValidatedJson,
+        request::user::{
+            ChangePasswordRequest, DeleteUserRequest, PatchMeRequest, PatchUserRequest,
+            PostsPolicy, SignupRequest,
+        },
+        response::{
+            TokenClaims,
+            user::{PublicUserResponse, SingleUserResponse},
+        },
+    },
+    apperr::AppError,
+    model::Permission,
+    state::ApplicationState,
+};
+
+use super::current_user;
+
+#[utoipa::path(
+    post,
+    path = "/signup",
+    request_body = SignupRequest,
+    responses(
+        (status = 200, description = "Account created", body = PublicUserResponse),
+        (status = 409, description = "Username already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+)]
+pub async fn signup(
+    State(state): State<Arc<ApplicationState>>,
+    ValidatedJson(payload): ValidatedJson<SignupRequest>,
+) -> Result<Json<PublicUserResponse>, AppError> {
+    let user = state.user_service.create_user(payload.into()).await?;
+    let response = PublicUserResponse { data: user.into() };
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    responses(
+        (status = 200, description = "Account of the current user", body = SingleUserResponse, headers(
+            ("ETag" = String, description = "Current version of the user"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 304, description = "User has not changed since the given version or time"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "Return 304 if the user still has one of these ETags"),
+        ("If-Modified-Since" = Option<String>, Header, description = "Return 304 if the user has not changed since this time"),
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn get(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let (version, updated) = (user.version, user.updated);
+    let response = SingleUserResponse { data: user.into() };
+    Ok(conditional::conditional(
+        &headers, version, updated, response,
+    ))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/me",
+    request_body = PatchMeRequest,
+    responses(
+        (status = 200, description = "Account updated successfully, changing the username signs out all sessions", body = SingleUserResponse, headers(
+            ("ETag" = String, description = "Current version of the user"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Current password is incorrect", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Username already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "User has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed or `current_password` is missing", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the user still has one of these ETags"),
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn patch(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<PatchMeRequest>,
+) -> Result<Response, AppError> {
+    let current = current_user(&state, &claims).await?;
+    let version = if_match.check(current.version)?;
+    if let Some(password) = payload.password_to_verify()?
+        && !state
+            .user_service
+            .verify_password(&current, password)
+            .await?
+    {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Current password is incorrect"),
+        )));
+    }
+    let user = state
+        .user_service
+        .patch_user(current.id, payload.into(), version)
+        .await?;
+    //旧用户名可以被他人重新注册，改名后让所有已登录的会话失效
+    if user.username != current.username {
+        state.session_service.revoke_user_sessions(user.id).await?;
+    }
+    let (version, updated) = (user.version, user.updated);
+    let response = SingleUserResponse { data: user.into() };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/password",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed, all sessions of the user are signed out"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Current password is incorrect", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn change_password(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    ValidatedJson(payload): ValidatedJson<ChangePasswordRequest>,
+) -> Result<(), AppError> {
+    let user = current_user(&state, &claims).await?;
+    if !state
+        .user_service
+        .verify_password(&user, &payload.current_password)
+        .await?
+    {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Current password is incorrect"),
+        )));
+    }
+    let req = PatchUserRequest {
+        username: None,
+        email: None,
+        password: Some(payload.new_password),
+        status: None,
+    };
+    state
+        .user_service
+        .patch_user(user.id, req, Some(user.version))
+        .await?;
+    //旧密码可能已经泄露，修改后让所有已登录的会话失效
+    state.session_service.revoke_user_sessions(user.id).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me",
+    responses(
+        (status = 200, description = "Account moved to the trash and signed out"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "`posts=reassign` requires the `users:write` permission", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "User still owns posts and `posts` is `block`", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "User has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Missing or invalid `reassign_to`", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the user still has one of these ETags"),
+        DeleteUserRequest,
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn delete(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<DeleteUserRequest>,
+    if_match: IfMatch,
+) -> Result<(), AppError> {
+    //转移文章会把文章署名改为其他用户，只有能管理用户的角色才可以这样删除自己
+    if matches!(query.posts, Some(PostsPolicy::Reassign))
+        && !claims.role.has_permission(Permission::UsersWrite)
+    {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Missing permission: {}", Permission::UsersWrite),
+        )));
+    }
+    let user = current_user(&state, &claims).await?;
+    let version = if_match.check(user.version)?;
+    state
+        .user_service
+        .delete_user(user.id, query.disposal(user.id)?, version)
+        .await?;
+    state.session_service.revoke_user_sessions(user.id).await?;
+    Ok(())
+}
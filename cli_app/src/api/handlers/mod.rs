@@ -1,4 +1,58 @@
+use axum::http::StatusCode;
+
+use crate::{
+    api::response::TokenClaims,
+    apperr::AppError,
+    model::{Permission, User},
+    services::{error::ServiceError, post::Visibility},
+    state::ApplicationState,
+};
+
+pub mod categories;
+pub mod comments;
 pub mod hello;
+pub mod jwks;
+pub mod lockouts;
 pub mod login;
+pub mod me;
+pub mod password;
 pub mod posts;
+pub mod revisions;
+pub mod tags;
+pub mod trash;
+pub mod two_factor;
 pub mod users;
+
+/// 根据令牌中的用户 ID 加载当前登录用户
+pub async fn current_user(
+    state: &ApplicationState,
+    claims: &TokenClaims,
+) -> Result<User, AppError> {
+    let Some(id) = claims.user_id() else {
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            format!("Invalid token subject: {}", claims.sub),
+        ));
+    };
+    state
+        .user_service
+        .get_user_by_id(id)
+        .await
+        .map_err(|e| match e {
+            ServiceError::NotFound(detail) => AppError::new(StatusCode::UNAUTHORIZED, detail),
+            e => e.into(),
+        })
+}
+
+/// 读者能看到的文章范围：匿名读者只能看到已发布的文章，作者还能看到自己的文章，
+/// 拥有 `posts:manage` 权限的用户可以看到全部文章
+pub async fn post_visibility(
+    state: &ApplicationState,
+    claims: Option<&TokenClaims>,
+) -> Result<Visibility, AppError> {
+    match claims {
+        None => Ok(Visibility::Published),
+        Some(claims) if claims.role.has_permission(Permission::PostsManage) => Ok(Visibility::All),
+        Some(claims) => Ok(Visibility::Author(current_user(state, claims).await?.id)),
+    }
+}
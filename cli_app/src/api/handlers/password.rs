@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+
+use crate::{
+    api::{
+        extract::ValidatedJson,
+        request::user::{ForgotPasswordRequest, PatchUserRequest, ResetPasswordRequest},
+    },
+    apperr::AppError,
+    model::{User, UserStatus},
+    services::{error::ServiceError, mail::Email},
+    state::ApplicationState,
+    utils::token,
+};
+
+#[utoipa::path(
+    post,
+    path = "/password/forgot",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "A reset token has been mailed if an active account uses the email"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+)]
+pub async fn forgot(
+    State(state): State<Arc<ApplicationState>>,
+    ValidatedJson(payload): ValidatedJson<ForgotPasswordRequest>,
+) -> Result<(), AppError> {
+    //无论邮箱是否存在都返回成功，避免暴露哪些邮箱已注册
+    let user = match state.user_service.get_user_by_email(&payload.email).await {
+        Ok(user) if user.status != UserStatus::Blocked => user,
+        Ok(_) | Err(ServiceError::NotFound(_)) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(to) = user.email.clone() else {
+        return Ok(());
+    };
+
+    let settings = state.settings.load().password_reset.clone();
+    let reset_token = token::generate_token(32);
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(settings.token_timeout_seconds);
+    state
+        .password_reset_service
+        .create_reset(user.id, &token::hash_token(&reset_token), expires_at)
+        .await?;
+
+    let email = Email {
+        to,
+        subject: "Reset your password".to_string(),
+        body: reset_mail_body(&user, &reset_token, settings.link.as_deref(), expires_at),
+    };
+    //后台发送，响应时间不随邮箱是否存在而变化
+    let mailer = state.mailer.clone();
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(email).await {
+            tracing::error!("Failed to send password reset mail: {:#}", e);
+        }
+    });
+    Ok(())
+}
+
+fn reset_mail_body(
+    user: &User,
+    reset_token: &str,
+    link: Option<&str>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+) -> String {
+    let action = match link {
+        Some(link) => format!(
+            "Open this link to choose a new password:\n\n{}",
+            link.replace("{token}", reset_token)
+        ),
+        None => format!(
+            "Use this token to choose a new password:\n\n{}",
+            reset_token
+        ),
+    };
+    format!(
+        "Someone asked to reset the password of the account {}.\n\n{}\n\n\
+         It can be used once and expires at {}. If you did not ask for this, ignore this mail.\n",
+        user.username,
+        action,
+        expires_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/reset",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password changed, all sessions of the user are signed out"),
+        (status = 400, description = "Invalid or expired reset token", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+)]
+pub async fn reset(
+    State(state): State<Arc<ApplicationState>>,
+    ValidatedJson(payload): ValidatedJson<ResetPasswordRequest>,
+) -> Result<(), AppError> {
+    let invalid = || {
+        AppError::from((
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Invalid or expired reset token"),
+        ))
+    };
+    //令牌只能使用一次，先检查新密码，避免不符合策略的请求作废令牌
+    state
+        .user_service
+        .check_password_policy(&payload.new_password)?;
+    let Some(user_id) = state
+        .password_reset_service
+        .consume_reset(&token::hash_token(&payload.token))
+        .await?
+    else {
+        return Err(invalid());
+    };
+    let req = PatchUserRequest {
+        username: None,
+        email: None,
+        password: Some(payload.new_password),
+        status: None,
+    };
+    match state.user_service.patch_user(user_id, req, None).await {
+        Ok(_) => {}
+        //令牌签发后用户已被删除
+        Err(ServiceError::NotFound(_)) => return Err(invalid()),
+        Err(e) => return Err(e.into()),
+    }
+    state.session_service.revoke_user_sessions(user_id).await?;
+    Ok(())
+}
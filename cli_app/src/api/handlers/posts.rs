@@ -2,68 +2,174 @@ use std::sync::Arc;
 
 use axum::{
     Extension, Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
 };
 
 use crate::{
     api::{
-        request::post::{CreatePostRequest, UpdatePostRequest},
+        conditional::{self, IfMatch},
+        extract::{OptionalClaims, ValidatedJson},
+        request::post::{
+            CreatePostRequest, ListPostsRequest, PatchPostRequest, SearchPostsRequest,
+            SetPostCategoryRequest, SetPostTagsRequest, UpdatePostRequest,
+        },
         response::{
             TokenClaims,
-            post::{ListPostResponse, SinglePostResponse},
+            post::{ListPostResponse, SearchPostHit, SearchPostResponse, SinglePostResponse},
         },
     },
     apperr::AppError,
-    services::post::PostService,
+    model::{Permission, Post, User},
+    services::{post::Visibility, search::SearchQuery},
     state::ApplicationState,
 };
 
+use super::{current_user, post_visibility};
+
+/// 只有文章作者或拥有 `posts:manage` 权限的用户可以修改、删除文章，返回当前用户和文章
+pub(super) async fn authorize_owner(
+    state: &ApplicationState,
+    claims: &TokenClaims,
+    id: i64,
+) -> Result<(User, Post), AppError> {
+    let user = current_user(state, claims).await?;
+    let post = state
+        .post_service
+        .get_post_by_id(id, Visibility::All)
+        .await?;
+    if post.author_id != user.id && !user.role.has_permission(Permission::PostsManage) {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Only the author or an editor can modify this post"),
+        )));
+    }
+    Ok((user, post))
+}
+
 #[utoipa::path(
     post,
     path = "/posts",
     request_body = CreatePostRequest,
     responses(
         (status = 200, description = "Post created successfully", body = SinglePostResponse),
-        (status = 400, description = "Bad request", body = AppError),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 400, description = "Bad request", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed, unknown tags or category, or invalid status or `publish_at`", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
     ),
     tag= "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn create(
-    Extension(_claims): Extension<TokenClaims>,
+    Extension(claims): Extension<TokenClaims>,
     State(state): State<Arc<ApplicationState>>,
-    Json(payload): Json<CreatePostRequest>,
+    ValidatedJson(payload): ValidatedJson<CreatePostRequest>,
 ) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.create_post(payload).await?;
+    let author = current_user(&state, &claims).await?;
+    let post = state.post_service.create_post(author.id, payload).await?;
     let response = SinglePostResponse { data: post };
     Ok(Json(response))
 }
 
 #[utoipa::path(
-    post,
+    put,
     path = "/posts/{id}",
     request_body = UpdatePostRequest,
     responses(
-        (status = 200, description = "Post updated successfully", body = SinglePostResponse),
-        (status = 400, description = "Bad request", body = AppError),
-        (status = 401, description = "Unauthorized", body = AppError),
-        (status =404, description = "Post not found", body = AppError),
+        (status = 200, description = "Post updated successfully", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 400, description = "Bad request", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed, status change not allowed or invalid `publish_at`", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status =404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "Post has been modified since it was read", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("id"=i64, Path, description = "Post ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the post still has one of these ETags"),
     ),
     tag = "Posts",
-
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn update(
+    Extension(claims): Extension<TokenClaims>,
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
-    Json(payload): Json<UpdatePostRequest>,
-) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.update_post(id, payload).await?;
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<UpdatePostRequest>,
+) -> Result<Response, AppError> {
+    let (user, post) = authorize_owner(&state, &claims, id).await?;
+    let version = if_match.check(post.version)?;
+    let post = state
+        .post_service
+        .update_post(id, user.id, payload, version)
+        .await?;
+    let (version, updated) = (post.version, post.updated);
     let response = SinglePostResponse { data: post };
-    Ok(Json(response))
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/posts/{id}",
+    request_body = PatchPostRequest,
+    responses(
+        (status = 200, description = "Post updated successfully", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 400, description = "Bad request", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed, status change not allowed or invalid `publish_at`", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "Post has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the post still has one of these ETags"),
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn patch(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<PatchPostRequest>,
+) -> Result<Response, AppError> {
+    let (user, post) = authorize_owner(&state, &claims, id).await?;
+    let version = if_match.check(post.version)?;
+    let post = state
+        .post_service
+        .patch_post(id, user.id, payload, version)
+        .await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
 }
 
 #[utoipa::path(
@@ -71,16 +177,82 @@ pub async fn update(
     path = "/posts",
     tag = "Posts",
     responses(
-        (status = 200, description = "List of posts", body = ListPostResponse),
-        (status = 404, description = "No posts found", body = AppError),
+        (status = 200, description = "Posts visible to the caller: published posts for anonymous readers, also their own posts for authors and all posts for editors", body = ListPostResponse),
+        (status = 400, description = "Invalid pagination or sort parameters", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Invalid or revoked token", body = AppError, content_type = "application/problem+json"),
     ),
+    params(ListPostsRequest),
     tag = "Posts",
+    security(
+        (),
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn list(
+    OptionalClaims(claims): OptionalClaims,
     State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<ListPostsRequest>,
 ) -> Result<Json<ListPostResponse>, AppError> {
-    let posts = state.post_service.get_all_posts().await?;
-    let response = ListPostResponse { data: posts };
+    let page = query
+        .page()
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    let visibility = post_visibility(&state, claims.as_ref()).await?;
+    let posts = state
+        .post_service
+        .list_posts(query.filter(visibility), page)
+        .await?;
+    let response = ListPostResponse {
+        data: posts.items,
+        total: posts.total,
+        next_cursor: posts.next_cursor,
+    };
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/search",
+    responses(
+        (status = 200, description = "Posts visible to the caller matching the query, most relevant first", body = SearchPostResponse),
+        (status = 400, description = "Empty query or invalid pagination parameters", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Invalid or revoked token", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(SearchPostsRequest),
+    tag = "Posts",
+    security(
+        (),
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn search(
+    OptionalClaims(claims): OptionalClaims,
+    State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<SearchPostsRequest>,
+) -> Result<Json<SearchPostResponse>, AppError> {
+    let (limit, offset) = query
+        .limit_offset()
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    let terms =
+        SearchQuery::parse(&query.q).map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    let visibility = post_visibility(&state, claims.as_ref()).await?;
+    let results = state
+        .post_service
+        .search_posts(&terms, query.filter(visibility), limit, offset)
+        .await?;
+    let next_offset = offset + results.hits.len() as i64;
+    let response = SearchPostResponse {
+        data: results
+            .hits
+            .into_iter()
+            .map(|hit| SearchPostHit {
+                post: hit.post,
+                rank: hit.rank,
+                snippet: hit.snippet,
+            })
+            .collect(),
+        total: results.total,
+        next_offset: (next_offset < results.total).then_some(next_offset),
+    };
     Ok(Json(response))
 }
 
@@ -88,52 +260,79 @@ pub async fn list(
     get,
     path = "/posts/{id}",
     responses(
-        (status = 200, description = "Post found", body = SinglePostResponse),
-        (status = 404, description = "Post not found", body = AppError),
+        (status = 200, description = "Post found", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 304, description = "Post has not changed since the given version or time"),
+        (status = 401, description = "Invalid or revoked token", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found or not visible to the caller", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("id"=i64, Path, description = "Post ID"),
+        ("If-None-Match" = Option<String>, Header, description = "Return 304 if the post still has one of these ETags"),
+        ("If-Modified-Since" = Option<String>, Header, description = "Return 304 if the post has not changed since this time"),
     ),
     tag= "Posts",
+    security(
+        (),
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn get(
+    OptionalClaims(claims): OptionalClaims,
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
-) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.get_post_by_id(id).await;
-    match post {
-        Ok(post) => {
-            let response = SinglePostResponse { data: post };
-            Ok(Json(response))
-        }
-        Err(e) => Err(AppError::from((StatusCode::NOT_FOUND, e))),
-    }
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let visibility = post_visibility(&state, claims.as_ref()).await?;
+    let post = state.post_service.get_post_by_id(id, visibility).await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::conditional(
+        &headers, version, updated, response,
+    ))
 }
 
 #[utoipa::path(
     get,
     path = "/posts/slug/{name}",
     responses(
-        (status = 200, description = "Post found", body = SinglePostResponse),
-        (status = 404, description = "Post not found", body = AppError),
+        (status = 200, description = "Post found", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 304, description = "Post has not changed since the given version or time"),
+        (status = 401, description = "Invalid or revoked token", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found or not visible to the caller", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("name"=String, Path, description = "Post Slug"),
+        ("If-None-Match" = Option<String>, Header, description = "Return 304 if the post still has one of these ETags"),
+        ("If-Modified-Since" = Option<String>, Header, description = "Return 304 if the post has not changed since this time"),
     ),
     tag= "Posts",
+    security(
+        (),
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn get_by_slug(
+    OptionalClaims(claims): OptionalClaims,
     State(state): State<Arc<ApplicationState>>,
     Path(name): Path<String>,
-) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.get_post_by_slug(&name).await;
-    match post {
-        Ok(post) => {
-            let response = SinglePostResponse { data: post };
-            Ok(Json(response))
-        }
-        Err(e) => Err(AppError::from((StatusCode::NOT_FOUND, e))),
-    }
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let visibility = post_visibility(&state, claims.as_ref()).await?;
+    let post = state
+        .post_service
+        .get_post_by_slug(&name, visibility)
+        .await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::conditional(
+        &headers, version, updated, response,
+    ))
 }
 
 #[utoipa::path(
@@ -141,17 +340,120 @@ pub async fn get_by_slug(
     path = "/posts/{id}",
     responses(
         (status = 200, description = "Post deleted successfully"),
-        (status = 404, description = "Post not found", body = AppError),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "Post has been modified since it was read", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("id"=i64, Path, description = "Post ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the post still has one of these ETags"),
     ),
     tag= "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn delete(
+    Extension(claims): Extension<TokenClaims>,
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
+    if_match: IfMatch,
 ) -> Result<(), AppError> {
-    state.post_service.delete_post(id).await?;
+    let (_, post) = authorize_owner(&state, &claims, id).await?;
+    let version = if_match.check(post.version)?;
+    state.post_service.delete_post(id, version).await?;
     Ok(())
 }
+
+#[utoipa::path(
+    put,
+    path = "/posts/{id}/tags",
+    request_body = SetPostTagsRequest,
+    responses(
+        (status = 200, description = "Tags of the post replaced", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "Post has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed or unknown tags", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the post still has one of these ETags"),
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn set_tags(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<SetPostTagsRequest>,
+) -> Result<Response, AppError> {
+    let (_, post) = authorize_owner(&state, &claims, id).await?;
+    let version = if_match.check(post.version)?;
+    let post = state
+        .post_service
+        .set_post_tags(id, payload.tags, version)
+        .await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/posts/{id}/category",
+    request_body = SetPostCategoryRequest,
+    responses(
+        (status = 200, description = "Category of the post changed", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "Post has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Category not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the post still has one of these ETags"),
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn set_category(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<SetPostCategoryRequest>,
+) -> Result<Response, AppError> {
+    let (_, post) = authorize_owner(&state, &claims, id).await?;
+    let version = if_match.check(post.version)?;
+    let post = state
+        .post_service
+        .set_post_category(id, payload.category_id, version)
+        .await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
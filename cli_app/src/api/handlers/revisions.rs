@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    response::Response,
+};
+use similar::TextDiff;
+
+use crate::{
+    api::{
+        conditional::{self, IfMatch},
+        request::post::{PatchPostRequest, RevisionDiffRequest},
+        response::{
+            TokenClaims,
+            post::{
+                ListRevisionResponse, RevisionDiffResponse, SinglePostResponse,
+                SingleRevisionResponse, StatusChange, TextChange,
+            },
+        },
+    },
+    apperr::AppError,
+    model::PostRevision,
+    state::ApplicationState,
+};
+
+use super::posts::authorize_owner;
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions",
+    responses(
+        (status = 200, description = "Revisions of the post, newest first", body = ListRevisionResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn list(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ListRevisionResponse>, AppError> {
+    authorize_owner(&state, &claims, id).await?;
+    let revisions = state.post_service.list_revisions(id).await?;
+    Ok(Json(ListRevisionResponse { data: revisions }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions/{rev}",
+    responses(
+        (status = 200, description = "Revision found", body = SingleRevisionResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or revision not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("rev"=i64, Path, description = "Revision number"),
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn get(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, rev)): Path<(i64, i64)>,
+) -> Result<Json<SingleRevisionResponse>, AppError> {
+    authorize_owner(&state, &claims, id).await?;
+    let revision = state.post_service.get_revision(id, rev).await?;
+    Ok(Json(SingleRevisionResponse { data: revision }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions/diff",
+    responses(
+        (status = 200, description = "Changes between the two revisions", body = RevisionDiffResponse),
+        (status = 400, description = "Missing or invalid revision numbers", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or revision not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        RevisionDiffRequest,
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn diff(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<RevisionDiffRequest>,
+) -> Result<Json<RevisionDiffResponse>, AppError> {
+    authorize_owner(&state, &claims, id).await?;
+    let from = state.post_service.get_revision(id, query.from).await?;
+    let to = state.post_service.get_revision(id, query.to).await?;
+    Ok(Json(diff_revisions(&from, &to)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/revisions/{rev}/restore",
+    responses(
+        (status = 200, description = "Title, slug and content restored from the revision, the status and publishing time are kept", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Not the author of the post", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post or revision not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Slug of the revision is used by another post", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "Post has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+        ("rev"=i64, Path, description = "Revision number"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the post still has one of these ETags"),
+    ),
+    tag = "Posts",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn restore(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, rev)): Path<(i64, i64)>,
+    if_match: IfMatch,
+) -> Result<Response, AppError> {
+    let (user, post) = authorize_owner(&state, &claims, id).await?;
+    let version = if_match.check(post.version)?;
+    let revision = state.post_service.get_revision(id, rev).await?;
+    //恢复也是一次普通的修改，会生成新的版本而不是删除之后的历史；
+    //状态和发布时间属于发布流程，不随内容回退
+    let payload = PatchPostRequest {
+        title: Some(revision.title),
+        slug: Some(revision.slug),
+        content: Some(revision.content),
+        status: None,
+        publish_at: None,
+    };
+    let post = state
+        .post_service
+        .patch_post(id, user.id, payload, version)
+        .await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+fn diff_revisions(from: &PostRevision, to: &PostRevision) -> RevisionDiffResponse {
+    let text_change = |from: &str, to: &str| {
+        (from != to).then(|| TextChange {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    };
+    let content = TextDiff::from_lines(&from.content, &to.content)
+        .unified_diff()
+        .header(
+            &format!("revision {}", from.revision),
+            &format!("revision {}", to.revision),
+        )
+        .to_string();
+    RevisionDiffResponse {
+        from: from.revision,
+        to: to.revision,
+        title: text_change(&from.title, &to.title),
+        slug: text_change(&from.slug, &to.slug),
+        status: (from.status != to.status).then_some(StatusChange {
+            from: from.status,
+            to: to.status,
+        }),
+        content,
+    }
+}
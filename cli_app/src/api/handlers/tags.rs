@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use crate::{
+    api::{
+        extract::ValidatedJson,
+        request::tag::{CreateTagRequest, UpdateTagRequest},
+        response::tag::{ListTagResponse, SingleTagResponse},
+    },
+    apperr::AppError,
+    state::ApplicationState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    responses(
+        (status = 200, description = "All tags with the number of published posts using them", body = ListTagResponse),
+    ),
+    tag = "Tags",
+)]
+pub async fn list(
+    State(state): State<Arc<ApplicationState>>,
+) -> Result<Json<ListTagResponse>, AppError> {
+    let tags = state.post_service.list_tags().await?;
+    Ok(Json(ListTagResponse { data: tags }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags/{id}",
+    responses(
+        (status = 200, description = "Tag found", body = SingleTagResponse),
+        (status = 404, description = "Tag not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Tag ID"),
+    ),
+    tag = "Tags",
+)]
+pub async fn get(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SingleTagResponse>, AppError> {
+    let tag = state.post_service.get_tag(id).await?;
+    Ok(Json(SingleTagResponse { data: tag }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tags",
+    request_body = CreateTagRequest,
+    responses(
+        (status = 200, description = "Tag created successfully", body = SingleTagResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Tag slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Tags",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn create(
+    State(state): State<Arc<ApplicationState>>,
+    ValidatedJson(payload): ValidatedJson<CreateTagRequest>,
+) -> Result<Json<SingleTagResponse>, AppError> {
+    let tag = state.post_service.create_tag(payload).await?;
+    Ok(Json(SingleTagResponse { data: tag }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/tags/{id}",
+    request_body = UpdateTagRequest,
+    responses(
+        (status = 200, description = "Tag updated successfully", body = SingleTagResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Tag not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Tag slug already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Tag ID"),
+    ),
+    tag = "Tags",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn update(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateTagRequest>,
+) -> Result<Json<SingleTagResponse>, AppError> {
+    let tag = state.post_service.update_tag(id, payload).await?;
+    Ok(Json(SingleTagResponse { data: tag }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tags/{id}",
+    responses(
+        (status = 200, description = "Tag deleted and removed from all posts"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Tag not found", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Tag ID"),
+    ),
+    tag = "Tags",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn delete(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<(), AppError> {
+    state.post_service.delete_tag(id).await?;
+    Ok(())
+}
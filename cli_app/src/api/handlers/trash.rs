@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Response,
+};
+
+use crate::{
+    api::{
+        conditional,
+        request::{
+            post::ListPostsRequest,
+            user::{DeleteUserRequest, ListUsersRequest},
+        },
+        response::{
+            post::{ListPostResponse, SinglePostResponse},
+            user::{AdminUser, ListUserResponse, SingleUserResponse},
+        },
+    },
+    apperr::AppError,
+    services::{
+        post::{PostFilter, Visibility},
+        user::UserFilter,
+    },
+    state::ApplicationState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/trash/posts",
+    responses(
+        (status = 200, description = "Posts in the trash", body = ListPostResponse),
+        (status = 400, description = "Invalid pagination or sort parameters", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(ListPostsRequest),
+    tag = "Trash",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn list_posts(
+    State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<ListPostsRequest>,
+) -> Result<Json<ListPostResponse>, AppError> {
+    let page = query
+        .page()
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    let filter = PostFilter {
+        trashed: true,
+        ..query.filter(Visibility::All)
+    };
+    let posts = state.post_service.list_posts(filter, page).await?;
+    let response = ListPostResponse {
+        data: posts.items,
+        total: posts.total,
+        next_cursor: posts.next_cursor,
+    };
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/posts/{id}/restore",
+    responses(
+        (status = 200, description = "Post restored", body = SinglePostResponse, headers(
+            ("ETag" = String, description = "Current version of the post"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found in the trash", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+    ),
+    tag = "Trash",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn restore_post(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let post = state.post_service.restore_post(id).await?;
+    let (version, updated) = (post.version, post.updated);
+    let response = SinglePostResponse { data: post };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/posts/{id}",
+    responses(
+        (status = 200, description = "Post and its revisions deleted permanently"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "Post not found in the trash", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "Post ID"),
+    ),
+    tag = "Trash",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn purge_post(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<(), AppError> {
+    state.post_service.purge_post(id).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/trash/users",
+    responses(
+        (status = 200, description = "Users in the trash", body = ListUserResponse),
+        (status = 400, description = "Invalid pagination or sort parameters", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(ListUsersRequest),
+    tag = "Trash",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn list_users(
+    State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<ListUsersRequest>,
+) -> Result<Json<ListUserResponse>, AppError> {
+    let page = query
+        .page()
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    let filter = UserFilter {
+        trashed: true,
+        ..query.filter()
+    };
+    let users = state.user_service.list_users(filter, page).await?;
+    let response = ListUserResponse {
+        data: users.items.into_iter().map(AdminUser::from).collect(),
+        total: users.total,
+        next_cursor: users.next_cursor,
+    };
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/users/{id}/restore",
+    responses(
+        (status = 200, description = "User restored, posts moved to the trash with the user stay there", body = SingleUserResponse, headers(
+            ("ETag" = String, description = "Current version of the user"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found in the trash", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "User ID"),
+    ),
+    tag = "Trash",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn restore_user(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let user = state.user_service.restore_user(id).await?;
+    let (version, updated) = (user.version, user.updated);
+    let response = SingleUserResponse { data: user.into() };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/users/{id}",
+    responses(
+        (status = 200, description = "User deleted permanently, their comments are kept without an author"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found in the trash", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "User still owns posts and `posts` is `block`", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Missing or invalid `reassign_to`", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id"=i64, Path, description = "User ID"),
+        DeleteUserRequest,
+    ),
+    tag = "Trash",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn purge_user(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<DeleteUserRequest>,
+) -> Result<(), AppError> {
+    state
+        .user_service
+        .purge_user(id, query.disposal(id)?)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State, http::StatusCode};
+
+use crate::{
+    api::{
+        request::user::TwoFactorCodeRequest,
+        response::{
+            TokenClaims,
+            user::{RecoveryCodesResponse, TwoFactorEnrollmentResponse},
+        },
+    },
+    apperr::AppError,
+    model::TwoFactor,
+    state::ApplicationState,
+    utils::{token, totp},
+};
+
+use super::current_user;
+
+/// 未配置加密密钥时无法保存密钥
+fn not_configured() -> AppError {
+    AppError::from((
+        StatusCode::NOT_IMPLEMENTED,
+        anyhow::anyhow!("Two-factor authentication is not configured"),
+    ))
+}
+
+/// 密文与用户绑定，复制到其他用户的记录后无法解密
+fn secret_aad(user_id: i64) -> Vec<u8> {
+    format!("user_totp:{}", user_id).into_bytes()
+}
+
+fn invalid_code() -> AppError {
+    AppError::from((
+        StatusCode::BAD_REQUEST,
+        anyhow::anyhow!("Invalid two-factor code"),
+    ))
+}
+
+/// 恢复码忽略大小写、空白和分隔符
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// 校验动态码或恢复码，通过后该动态码或恢复码不能再次使用
+pub async fn check_code(
+    state: &ApplicationState,
+    two_factor: &TwoFactor,
+    code: &str,
+) -> Result<bool, AppError> {
+    let code = code.trim();
+    if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+        let secret = state
+            .totp_cipher
+            .as_ref()
+            .ok_or_else(not_configured)?
+            .decrypt(&two_factor.secret, &secret_aad(two_factor.user_id))?;
+        let Some(step) = totp::matching_step(&secret, code, chrono::Utc::now()) else {
+            return Ok(false);
+        };
+        Ok(state
+            .two_factor_service
+            .use_step(two_factor.user_id, step)
+            .await?)
+    } else {
+        let code_hash = token::hash_token(&normalize_recovery_code(code));
+        Ok(state
+            .two_factor_service
+            .use_recovery_code(two_factor.user_id, &code_hash)
+            .await?)
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/enroll",
+    responses(
+        (status = 200, description = "New secret generated, confirm it with `/me/2fa/enable`", body = TwoFactorEnrollmentResponse),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Two-factor authentication is already enabled", body = AppError, content_type = "application/problem+json"),
+        (status = 501, description = "Two-factor authentication is not configured on the server", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn enroll(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+) -> Result<Json<TwoFactorEnrollmentResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let secret = totp::generate_secret();
+    let encrypted = state
+        .totp_cipher
+        .as_ref()
+        .ok_or_else(not_configured)?
+        .encrypt(&secret, &secret_aad(user.id))?;
+    if !state
+        .two_factor_service
+        .start_enrollment(user.id, &encrypted)
+        .await?
+    {
+        return Err(AppError::from((
+            StatusCode::CONFLICT,
+            anyhow::anyhow!("Two-factor authentication is already enabled"),
+        )));
+    }
+    let issuer = state.settings.load().two_factor.issuer.clone();
+    let (secret, otpauth_uri) = totp::otpauth(&secret, &issuer, &user.username)?;
+    Ok(Json(TwoFactorEnrollmentResponse {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/enable",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid two-factor code", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "No pending enrollment, or two-factor authentication is already enabled", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn enable(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let not_pending = || {
+        AppError::from((
+            StatusCode::CONFLICT,
+            anyhow::anyhow!("No pending two-factor enrollment"),
+        ))
+    };
+    let two_factor = state
+        .two_factor_service
+        .get_two_factor(user.id)
+        .await?
+        .filter(|two_factor| !two_factor.is_enabled())
+        .ok_or_else(not_pending)?;
+
+    //启用前只接受动态码，确认身份验证器已正确保存密钥
+    let secret = state
+        .totp_cipher
+        .as_ref()
+        .ok_or_else(not_configured)?
+        .decrypt(&two_factor.secret, &secret_aad(user.id))?;
+    let step = totp::matching_step(&secret, payload.code.trim(), chrono::Utc::now())
+        .ok_or_else(invalid_code)?;
+
+    let count = state.settings.load().two_factor.recovery_codes;
+    let recovery_codes: Vec<String> = (0..count).map(|_| token::generate_token(8)).collect();
+    let hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| token::hash_token(code))
+        .collect();
+    if !state
+        .two_factor_service
+        .enable_two_factor(user.id, step, &hashes)
+        .await?
+    {
+        return Err(not_pending());
+    }
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/2fa/disable",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled, the secret and recovery codes are removed"),
+        (status = 400, description = "Invalid two-factor code", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Two-factor authentication is not enabled", body = AppError, content_type = "application/problem+json"),
+    ),
+    tag = "Account",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn disable(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<(), AppError> {
+    let user = current_user(&state, &claims).await?;
+    let two_factor = state
+        .two_factor_service
+        .get_two_factor(user.id)
+        .await?
+        .filter(TwoFactor::is_enabled)
+        .ok_or_else(|| {
+            AppError::from((
+                StatusCode::CONFLICT,
+                anyhow::anyhow!("Two-factor authentication is not enabled"),
+            ))
+        })?;
+    if !check_code(&state, &two_factor, &payload.code).await? {
+        return Err(invalid_code());
+    }
+    state.two_factor_service.disable_two_factor(user.id).await?;
+    Ok(())
+}
@@ -2,17 +2,26 @@ use std::sync::Arc;
 
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
 };
 use utoipa::OpenApi;
 
 use crate::{
     api::{
-        request::user::{CreateUserRequest, UpdateUserRequest},
-        response::user::{ListUserResponse, SingleUserResponse},
+        conditional::{self, IfMatch},
+        extract::ValidatedJson,
+        request::user::{
+            CreateUserRequest, DeleteUserRequest, ListUsersRequest, PatchUserRequest, PostsPolicy,
+            UpdateUserRequest,
+        },
+        response::user::{
+            AdminUser, ListUserResponse, PublicUser, PublicUserResponse, SingleUserResponse,
+        },
     },
     apperr::AppError,
-    services::user::UserService,
+    model::User,
     state::ApplicationState,
 };
 
@@ -24,12 +33,19 @@ use crate::{
         get,
         get_by_username,
         update,
+        patch,
         delete,
+        revoke_sessions,
     ),
     components(
         schemas(
             CreateUserRequest,
             UpdateUserRequest,
+            PatchUserRequest,
+            PostsPolicy,
+            PublicUser,
+            AdminUser,
+            PublicUserResponse,
             ListUserResponse,
             SingleUserResponse,
         ),
@@ -45,18 +61,25 @@ pub struct UsersApi;
     path = "",
     request_body = CreateUserRequest,
     responses(
-        (status = 200, description = "User created successfully", body = SingleUserResponse),
-        (status = 400, description = "Bad request", body = AppError),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 200, description = "User created successfully", body = PublicUserResponse),
+        (status = 400, description = "Bad request", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Username already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
     ),
     tag= "Users",
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn create(
     State(state): State<Arc<ApplicationState>>,
-    Json(payload): Json<CreateUserRequest>,
-) -> Result<Json<SingleUserResponse>, AppError> {
+    ValidatedJson(payload): ValidatedJson<CreateUserRequest>,
+) -> Result<Json<PublicUserResponse>, AppError> {
     let user = state.user_service.create_user(payload).await?;
-    let response = SingleUserResponse { data: user };
+    let response = PublicUserResponse { data: user.into() };
     Ok(Json(response))
 }
 
@@ -65,15 +88,30 @@ pub async fn create(
     path = "",
     responses(
         (status = 200, description = "List of users", body = ListUserResponse),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 400, description = "Invalid pagination or sort parameters", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(ListUsersRequest),
+    tag= "Users",
+    security(
+        ("bearer_auth" = []),
     ),
-    tag="Users"
 )]
 pub async fn list(
     State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<ListUsersRequest>,
 ) -> Result<Json<ListUserResponse>, AppError> {
-    let users = state.user_service.get_all_users().await?;
-    let response = ListUserResponse { data: users };
+    let page = query
+        .page()
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+    let users = state.user_service.list_users(query.filter(), page).await?;
+    let response = ListUserResponse {
+        data: users.items.into_iter().map(AdminUser::from).collect(),
+        total: users.total,
+        next_cursor: users.next_cursor,
+    };
     Ok(Json(response))
 }
 
@@ -81,27 +119,37 @@ pub async fn list(
     get,
     path = "/{id}",
     responses(
-        (status = 200, description = "User found", body = SingleUserResponse),
-        (status = 404, description = "User not found", body = AppError),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 200, description = "User found", body = SingleUserResponse, headers(
+            ("ETag" = String, description = "Current version of the user"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 304, description = "User has not changed since the given version or time"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("id" = i64, Path, description = "User ID"),
+        ("If-None-Match" = Option<String>, Header, description = "Return 304 if the user still has one of these ETags"),
+        ("If-Modified-Since" = Option<String>, Header, description = "Return 304 if the user has not changed since this time"),
+    ),
+    tag= "Users",
+    security(
+        ("bearer_auth" = []),
     ),
-    tag="Users"
 )]
 pub async fn get(
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
-) -> Result<Json<SingleUserResponse>, AppError> {
-    let user = state.user_service.get_user_by_id(id).await;
-    match user {
-        Ok(user) => {
-            let response = SingleUserResponse { data: user };
-            Ok(Json(response))
-        }
-        Err(e) => Err(AppError::from((axum::http::StatusCode::NOT_FOUND, e))),
-    }
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user = state.user_service.get_user_by_id(id).await?;
+    let (version, updated) = (user.version, user.updated);
+    let response = SingleUserResponse { data: user.into() };
+    Ok(conditional::conditional(
+        &headers, version, updated, response,
+    ))
 }
 
 #[utoipa::path(
@@ -109,26 +157,26 @@ pub async fn get(
     path = "/name/{username}",
     responses(
         (status = 200, description = "User found", body = SingleUserResponse),
-        (status = 404, description = "User not found", body = AppError),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("username" = String, Path, description = "User username"),
     ),
-    tag="Users"
+    tag= "Users",
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn get_by_username(
     State(state): State<Arc<ApplicationState>>,
     Path(username): Path<String>,
 ) -> Result<Json<SingleUserResponse>, AppError> {
-    let user = state.user_service.get_user_by_username(&username).await;
-    match user {
-        Ok(user) => {
-            let response = SingleUserResponse { data: user };
-            Ok(Json(response))
-        }
-        Err(e) => Err(AppError::from((axum::http::StatusCode::NOT_FOUND, e))),
-    }
+    let user = state.user_service.get_user_by_username(&username).await?;
+    let response = SingleUserResponse { data: user.into() };
+    Ok(Json(response))
 }
 
 #[utoipa::path(
@@ -136,43 +184,171 @@ pub async fn get_by_username(
     path = "/{id}",
     request_body = UpdateUserRequest,
     responses(
-        (status = 200, description = "User updated successfully", body = SingleUserResponse),
-        (status = 400, description = "Bad request", body = AppError),
-        (status = 404, description = "User not found", body = AppError),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 200, description = "User updated successfully, the user is signed out", body = SingleUserResponse, headers(
+            ("ETag" = String, description = "Current version of the user"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 400, description = "Bad request", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Username already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "User has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("id" = i64, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the user still has one of these ETags"),
     ),
     tag= "Users",
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn update(
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
-    Json(payload): Json<UpdateUserRequest>,
-) -> Result<Json<SingleUserResponse>, AppError> {
-    let user = state.user_service.update_user(id, payload).await?;
-    let response = SingleUserResponse { data: user };
-    Ok(Json(response))
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<UpdateUserRequest>,
+) -> Result<Response, AppError> {
+    let before = state.user_service.get_user_by_id(id).await?;
+    let version = if_match.check(before.version)?;
+    let user = state.user_service.update_user(id, payload, version).await?;
+    //整体更新总会设置新的密码
+    revoke_sessions_if_changed(&state, &before, &user, true).await?;
+    let (version, updated) = (user.version, user.updated);
+    let response = SingleUserResponse { data: user.into() };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    request_body = PatchUserRequest,
+    responses(
+        (status = 200, description = "User updated successfully, changing the username, password or status signs the user out", body = SingleUserResponse, headers(
+            ("ETag" = String, description = "Current version of the user"),
+            ("Last-Modified" = String, description = "Time of the last change"),
+        )),
+        (status = 400, description = "Bad request", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Request validation failed", body = AppError, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "Username already exists", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "User has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id" = i64, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the user still has one of these ETags"),
+    ),
+    tag= "Users",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn patch(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    if_match: IfMatch,
+    ValidatedJson(payload): ValidatedJson<PatchUserRequest>,
+) -> Result<Response, AppError> {
+    let before = state.user_service.get_user_by_id(id).await?;
+    let version = if_match.check(before.version)?;
+    let password_changed = payload.password.is_some();
+    let user = state.user_service.patch_user(id, payload, version).await?;
+    revoke_sessions_if_changed(&state, &before, &user, password_changed).await?;
+    let (version, updated) = (user.version, user.updated);
+    let response = SingleUserResponse { data: user.into() };
+    Ok(conditional::with_validators(
+        version,
+        updated,
+        Json(response),
+    ))
 }
 
 #[utoipa::path(
     delete,
     path = "/{id}",
     responses(
-        (status = 200, description = "User deleted successfully"),
-        (status = 404, description = "User not found", body = AppError),
-        (status = 500, description = "Internal server error", body = AppError),
+        (status = 200, description = "User moved to the trash and signed out"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = AppError, content_type = "application/problem+json"),
+        (status = 409, description = "User still owns posts and `posts` is `block`", body = AppError, content_type = "application/problem+json"),
+        (status = 412, description = "User has been modified since it was read", body = AppError, content_type = "application/problem+json"),
+        (status = 422, description = "Missing or invalid `reassign_to`", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
     ),
     params(
         ("id" = i64, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if the user still has one of these ETags"),
+        DeleteUserRequest,
     ),
     tag= "Users",
+    security(
+        ("bearer_auth" = []),
+    ),
 )]
 pub async fn delete(
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
+    Query(query): Query<DeleteUserRequest>,
+    if_match: IfMatch,
+) -> Result<(), AppError> {
+    let user = state.user_service.get_user_by_id(id).await?;
+    let version = if_match.check(user.version)?;
+    state
+        .user_service
+        .delete_user(id, query.disposal(id)?, version)
+        .await?;
+    state.session_service.revoke_user_sessions(id).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}/sessions",
+    responses(
+        (status = 200, description = "All sessions of the user revoked"),
+        (status = 401, description = "Unauthorized", body = AppError, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = AppError, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = AppError, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = AppError, content_type = "application/problem+json"),
+    ),
+    params(
+        ("id" = i64, Path, description = "User ID"),
+    ),
+    tag= "Users",
+    security(
+        ("bearer_auth" = []),
+    ),
+)]
+pub async fn revoke_sessions(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<(), AppError> {
+    let user = state.user_service.get_user_by_id(id).await?;
+    state.session_service.revoke_user_sessions(user.id).await?;
+    Ok(())
+}
+
+/// 用户名、密码或状态变化后让该用户所有已登录的会话失效，
+/// 旧令牌不能继续使用改名前的身份或被封禁前的权限
+async fn revoke_sessions_if_changed(
+    state: &ApplicationState,
+    before: &User,
+    after: &User,
+    password_changed: bool,
 ) -> Result<(), AppError> {
-    state.user_service.delete_user(id).await?;
+    if password_changed || before.username != after.username || before.status != after.status {
+        state.session_service.revoke_user_sessions(after.id).await?;
+    }
     Ok(())
 }
@@ -1,59 +1,67 @@
 use std::sync::Arc;
 
+use crate::{
+    api::response::TokenClaims, apperr::AppError, services::error::ServiceError,
+    state::ApplicationState,
+};
 use axum::{
     body::Body,
     extract::{Request, State},
-    http::{StatusCode, header},
+    http::{HeaderMap, StatusCode, header},
     middleware::Next,
     response::IntoResponse,
 };
-use jsonwebtoken::{DecodingKey, Validation, decode};
-
-use crate::{api::response::TokenClaims, apperr::AppError, state::ApplicationState};
 
 pub async fn auth(
     State(state): State<Arc<ApplicationState>>,
     mut req: Request<Body>,
     next: Next,
 ) -> Result<impl IntoResponse, AppError> {
-    let token = req
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_value| {
-            auth_value
-                .strip_prefix("Bearer ")
-                .map(|stripped| stripped.to_owned())
-        });
-
-    let token = token.ok_or_else(|| {
+    let claims = verify_bearer(&state, req.headers()).await?.ok_or_else(|| {
         AppError::from((
             StatusCode::UNAUTHORIZED,
             anyhow::anyhow!("Missing authorization header"),
         ))
     })?;
 
-    let secret = state
-        .settings
-        .load()
-        .token_secret
-        .clone()
-        .unwrap_or("secret".to_string());
-
-    let claims = decode::<TokenClaims>(
-        &token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|err| {
+    req.extensions_mut().insert(claims);
+
+    Ok(next.run(req).await)
+}
+
+/// 校验请求中的 Bearer 令牌，没有令牌时返回 `None`，令牌无效或已撤销时返回 401
+pub async fn verify_bearer(
+    state: &ApplicationState,
+    headers: &HeaderMap,
+) -> Result<Option<TokenClaims>, AppError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .and_then(|auth_value| auth_value.strip_prefix("Bearer "));
+
+    let Some(token) = token else {
+        return Ok(None);
+    };
+
+    let claims = state.keys.decode::<TokenClaims>(token).map_err(|err| {
         AppError::from((
             StatusCode::UNAUTHORIZED,
             anyhow::anyhow!("Invalid token: {}", err),
         ))
-    })?
-    .claims;
+    })?;
 
-    req.extensions_mut().insert(claims);
+    //令牌所属会话已注销或过期时拒绝访问
+    let active = match state.session_service.get_session(&claims.jti).await {
+        Ok(session) => session.is_active(),
+        Err(ServiceError::NotFound(_)) => false,
+        Err(err) => return Err(err.into()),
+    };
+    if !active {
+        return Err(AppError::from((
+            StatusCode::UNAUTHORIZED,
+            anyhow::anyhow!("Token has been revoked"),
+        )));
+    }
 
-    Ok(next.run(req).await)
+    Ok(Some(claims))
 }
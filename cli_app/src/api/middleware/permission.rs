@@ -0,0 +1,75 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+
+use crate::{api::response::TokenClaims, apperr::AppError, model::Permission};
+
+/// 要求当前令牌的角色拥有指定权限，需放在 `auth` 中间件之内使用
+///
+/// ```ignore
+/// delete(handlers::users::delete)
+///     .route_layer(RequirePermission(Permission::UsersWrite))
+///     .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+/// ```
+#[derive(Clone, Copy)]
+pub struct RequirePermission(pub Permission);
+
+impl<S> Layer<S> for RequirePermission {
+    type Service = RequirePermissionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequirePermissionService {
+            inner,
+            permission: self.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequirePermissionService<S> {
+    inner: S,
+    permission: Permission,
+}
+
+impl<S> Service<Request> for RequirePermissionService<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let claims = req.extensions().get::<TokenClaims>();
+        let rejection = match claims {
+            None => Some(AppError::from((
+                StatusCode::UNAUTHORIZED,
+                anyhow::anyhow!("Missing authorization header"),
+            ))),
+            Some(claims) if !claims.role.has_permission(self.permission) => Some(AppError::from((
+                StatusCode::FORBIDDEN,
+                anyhow::anyhow!("Missing permission: {}", self.permission),
+            ))),
+            Some(_) => None,
+        };
+
+        match rejection {
+            Some(err) => Box::pin(async move { Ok(err.into_response()) }),
+            None => Box::pin(self.inner.call(req)),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use axum::{
+    Json,
+    body::Body,
+    extract::Request,
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::apperr::{AppError, PROBLEM_JSON};
+
+/// 为错误响应补充 `instance`，即出错的请求路径
+pub async fn problem_instance(req: Request<Body>, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    let mut response = next.run(req).await;
+
+    match response.extensions_mut().remove::<AppError>() {
+        Some(mut problem) if problem.instance.is_none() => {
+            problem.instance = Some(path);
+            let (mut parts, _) = response.into_parts();
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts
+                .headers
+                .insert(header::CONTENT_TYPE, PROBLEM_JSON.parse().unwrap());
+            let body = Json(&problem).into_response().into_body();
+            Response::from_parts(parts, body)
+        }
+        _ => response,
+    }
+}
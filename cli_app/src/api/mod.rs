@@ -1,11 +1,17 @@
 use std::sync::Arc;
 
 use crate::state::ApplicationState;
-use axum::{extract::{MatchedPath, Request}, Router};
+use axum::{
+    Router,
+    extract::{MatchedPath, Request},
+    routing::get,
+};
 use tower_http::trace::TraceLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+mod conditional;
+mod extract;
 mod handlers;
 mod middleware;
 pub mod request;
@@ -18,12 +24,19 @@ pub fn configure(state: Arc<ApplicationState>) -> Router {
             "/v1/api-docs/openapi.json",
             crate::api::v1::ApiDoc::openapi(),
         ))
+        .route(
+            "/.well-known/jwks.json",
+            get(handlers::jwks::jwks).with_state(state.clone()),
+        )
         .nest("/v1", v1::configure(state))
+        .layer(axum::middleware::from_fn(
+            middleware::problem::problem_instance,
+        ))
         .layer(axum::middleware::from_fn(middleware::trace::trace))
         .layer(
-            TraceLayer::new_for_http().make_span_with(|request:&Request|{
+            TraceLayer::new_for_http().make_span_with(|request: &Request| {
                 let matched_path = request.extensions().get().map(MatchedPath::as_str);
                 tracing::info_span!("http_request",method=?request.method(),path=?matched_path)
-            })
+            }),
         )
 }
@@ -0,0 +1,41 @@
+use crate::api::request::SLUG_REGEX;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateCategoryRequest {
+    /// Parent category, absent for a top level category
+    pub parent_id: Option<i64>,
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    #[schema(min_length = 1, max_length = 255)]
+    pub name: String,
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 255, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
+    pub slug: String,
+}
+
+/// 修改分类时可以移动到其他父分类下，但不能移动到自己的子分类下
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateCategoryRequest {
+    /// Parent category, absent to make this a top level category
+    pub parent_id: Option<i64>,
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    #[schema(min_length = 1, max_length = 255)]
+    pub name: String,
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 255, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
+    pub slug: String,
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateCommentRequest {
+    /// Comment to reply to, omit for a top level comment
+    pub parent_id: Option<i64>,
+    #[validate(length(
+        min = 1,
+        max = 10000,
+        message = "must be between 1 and 10000 characters"
+    ))]
+    #[schema(min_length = 1, max_length = 10000)]
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateCommentRequest {
+    #[validate(length(
+        min = 1,
+        max = 10000,
+        message = "must be between 1 and 10000 characters"
+    ))]
+    #[schema(min_length = 1, max_length = 10000)]
+    pub content: String,
+}
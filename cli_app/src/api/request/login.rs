@@ -6,3 +6,16 @@ pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct LoginTwoFactorRequest {
+    /// Token returned by `login` for accounts with two-factor authentication
+    pub challenge_token: String,
+    /// Code from the authenticator app, or one of the recovery codes
+    pub code: String,
+}
@@ -1,3 +1,17 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+pub mod category;
+pub mod comment;
 pub mod login;
 pub mod post;
+pub mod tag;
 pub mod user;
+
+pub const USERNAME_PATTERN: &str = "^[A-Za-z0-9_.-]+$";
+pub const SLUG_PATTERN: &str = "^[a-z0-9]+(-[a-z0-9]+)*$";
+
+pub static USERNAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(USERNAME_PATTERN).unwrap());
+pub static SLUG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(SLUG_PATTERN).unwrap());
@@ -1,22 +1,202 @@
-use crate::model::PostStatus;
+use crate::{
+    api::request::SLUG_REGEX,
+    model::PostStatus,
+    services::{
+        pagination::{DEFAULT_LIMIT, MAX_LIMIT, PageRequest},
+        post::{PostFilter, Visibility},
+    },
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreatePostRequest {
-    pub author_id: i64,
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    #[schema(min_length = 1, max_length = 255)]
     pub title: String,
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 255, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
     pub slug: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    #[schema(min_length = 1)]
     pub content: String,
     pub status: PostStatus,
+    /// Time to publish the post, required for and only allowed with `Scheduled`
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Category of the post
+    pub category_id: Option<i64>,
+    /// Slugs of existing tags to attach to the post
+    #[serde(default)]
+    #[validate(length(max = 20, message = "must not have more than 20 tags"))]
+    #[schema(max_items = 20)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdatePostRequest {
     pub id: i64,
-    pub author_id: i64,
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    #[schema(min_length = 1, max_length = 255)]
     pub title: String,
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 255, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
     pub slug: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    #[schema(min_length = 1)]
     pub content: String,
     pub status: PostStatus,
+    /// Time to publish the post, required for and only allowed with `Scheduled`
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// 部分更新文章，只修改请求中出现的字段
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct PatchPostRequest {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    #[schema(min_length = 1, max_length = 255)]
+    pub title: Option<String>,
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 255, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
+    pub slug: Option<String>,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    #[schema(min_length = 1)]
+    pub content: Option<String>,
+    pub status: Option<PostStatus>,
+    /// New publishing time of a scheduled post, kept unchanged when omitted
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListPostsRequest {
+    /// Maximum number of posts to return, between 1 and 100 (default 20)
+    pub limit: Option<i64>,
+    /// Number of posts to skip, cannot be combined with `cursor`
+    pub offset: Option<i64>,
+    /// Cursor taken from `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Comma separated sort fields (`id`, `created`, `updated`), prefix with `-` for descending order
+    #[param(example = "created,-updated")]
+    pub sort: Option<String>,
+    /// Only return posts with this status
+    pub status: Option<PostStatus>,
+    /// Only return posts written by this author
+    pub author_id: Option<i64>,
+    /// Only return posts with the tag of this slug
+    pub tag: Option<String>,
+    /// Only return posts in the category of this slug or one of its subcategories
+    pub category: Option<String>,
+    /// Only return posts created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only return posts created before this time
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl ListPostsRequest {
+    pub fn page(&self) -> anyhow::Result<PageRequest> {
+        PageRequest::new(
+            self.limit,
+            self.offset,
+            self.cursor.as_deref(),
+            self.sort.as_deref(),
+        )
+    }
+
+    pub fn filter(&self, visibility: Visibility) -> PostFilter {
+        PostFilter {
+            trashed: false,
+            visibility,
+            status: self.status,
+            author_id: self.author_id,
+            tag: self.tag.clone(),
+            category: self.category.clone(),
+            created_after: self.created_after,
+            created_before: self.created_before,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchPostsRequest {
+    /// Words to search for in the title and content, all of which must match.
+    /// `"quoted phrases"` match words in order and `prefix*` matches words starting with the prefix
+    #[param(example = "\"full text\" search*")]
+    pub q: String,
+    /// Maximum number of results to return, between 1 and 100 (default 20)
+    pub limit: Option<i64>,
+    /// Number of results to skip
+    pub offset: Option<i64>,
+    /// Only return posts with this status
+    pub status: Option<PostStatus>,
+    /// Only return posts written by this author
+    pub author_id: Option<i64>,
+}
+
+impl SearchPostsRequest {
+    /// 检索结果按相关度排序，只支持偏移量分页
+    pub fn limit_offset(&self) -> anyhow::Result<(i64, i64)> {
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            anyhow::bail!("limit must be between 1 and {}", MAX_LIMIT);
+        }
+        let offset = self.offset.unwrap_or(0);
+        if offset < 0 {
+            anyhow::bail!("offset must not be negative");
+        }
+        Ok((limit, offset))
+    }
+
+    pub fn filter(&self, visibility: Visibility) -> PostFilter {
+        PostFilter {
+            visibility,
+            status: self.status,
+            author_id: self.author_id,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RevisionDiffRequest {
+    /// Revision to compare from
+    pub from: i64,
+    /// Revision to compare to
+    pub to: i64,
+}
+
+/// 替换文章的全部标签
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct SetPostTagsRequest {
+    /// Slugs of existing tags, an empty list removes all tags
+    #[validate(length(max = 20, message = "must not have more than 20 tags"))]
+    #[schema(max_items = 20)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct SetPostCategoryRequest {
+    /// New category of the post, null to remove the post from its category
+    pub category_id: Option<i64>,
 }
@@ -0,0 +1,36 @@
+use crate::api::request::SLUG_REGEX;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateTagRequest {
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters"))]
+    #[schema(min_length = 1, max_length = 64)]
+    pub name: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 64, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
+    pub slug: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateTagRequest {
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters"))]
+    #[schema(min_length = 1, max_length = 64)]
+    pub name: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        regex(
+            path = *SLUG_REGEX,
+            message = "must be lowercase letters and digits separated by single '-'"
+        )
+    )]
+    #[schema(min_length = 1, max_length = 64, pattern = "^[a-z0-9]+(-[a-z0-9]+)*$")]
+    pub slug: String,
+}
@@ -1,18 +1,273 @@
-use crate::model::UserStatus;
+use crate::{
+    api::request::USERNAME_REGEX,
+    model::UserStatus,
+    services::{
+        error::ServiceError,
+        pagination::PageRequest,
+        user::{PostsDisposal, UserFilter},
+    },
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateUserRequest {
+    #[validate(
+        length(min = 3, max = 64, message = "must be between 3 and 64 characters"),
+        regex(
+            path = *USERNAME_REGEX,
+            message = "may only contain letters, digits, '_', '.' and '-'"
+        )
+    )]
+    #[schema(min_length = 3, max_length = 64, pattern = "^[A-Za-z0-9_.-]+$")]
     pub username: String,
+    /// Address that password reset mails are sent to
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = Email, max_length = 255)]
+    pub email: Option<String>,
+    /// Checked against the server's password policy
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
+    #[schema(max_length = 128)]
     pub password: String,
     pub status: UserStatus,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
+/// 公开注册，新账号总是使用默认状态，状态只能由管理员修改
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct SignupRequest {
+    #[validate(
+        length(min = 3, max = 64, message = "must be between 3 and 64 characters"),
+        regex(
+            path = *USERNAME_REGEX,
+            message = "may only contain letters, digits, '_', '.' and '-'"
+        )
+    )]
+    #[schema(min_length = 3, max_length = 64, pattern = "^[A-Za-z0-9_.-]+$")]
+    pub username: String,
+    /// Address that password reset mails are sent to
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = Email, max_length = 255)]
+    pub email: Option<String>,
+    /// Checked against the server's password policy
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
+    #[schema(max_length = 128)]
+    pub password: String,
+}
+
+impl From<SignupRequest> for CreateUserRequest {
+    fn from(req: SignupRequest) -> Self {
+        Self {
+            username: req.username,
+            email: req.email,
+            password: req.password,
+            status: UserStatus::Active,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateUserRequest {
     pub id: i64,
+    #[validate(
+        length(min = 3, max = 64, message = "must be between 3 and 64 characters"),
+        regex(
+            path = *USERNAME_REGEX,
+            message = "may only contain letters, digits, '_', '.' and '-'"
+        )
+    )]
+    #[schema(min_length = 3, max_length = 64, pattern = "^[A-Za-z0-9_.-]+$")]
     pub username: String,
+    /// Address that password reset mails are sent to, removed when absent
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = Email, max_length = 255)]
+    pub email: Option<String>,
+    /// Checked against the server's password policy
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
+    #[schema(max_length = 128)]
     pub password: String,
     pub status: UserStatus,
 }
+
+/// 部分更新用户，只修改请求中出现的字段
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct PatchUserRequest {
+    #[validate(
+        length(min = 3, max = 64, message = "must be between 3 and 64 characters"),
+        regex(
+            path = *USERNAME_REGEX,
+            message = "may only contain letters, digits, '_', '.' and '-'"
+        )
+    )]
+    #[schema(min_length = 3, max_length = 64, pattern = "^[A-Za-z0-9_.-]+$")]
+    pub username: Option<String>,
+    /// The email is only changed when this field is present
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = Email, max_length = 255)]
+    pub email: Option<String>,
+    /// The password is only changed when this field is present, checked against the server's password policy
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
+    #[schema(max_length = 128)]
+    pub password: Option<String>,
+    pub status: Option<UserStatus>,
+}
+
+/// 修改自己的账号，密码需通过 `POST /me/password` 修改
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct PatchMeRequest {
+    /// Changing the username signs out all sessions, sign in again with the new name
+    #[validate(
+        length(min = 3, max = 64, message = "must be between 3 and 64 characters"),
+        regex(
+            path = *USERNAME_REGEX,
+            message = "may only contain letters, digits, '_', '.' and '-'"
+        )
+    )]
+    #[schema(min_length = 3, max_length = 64, pattern = "^[A-Za-z0-9_.-]+$")]
+    pub username: Option<String>,
+    /// Address that password reset mails are sent to
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = Email, max_length = 255)]
+    pub email: Option<String>,
+    /// Password the user signs in with now, required to change the username or email
+    pub current_password: Option<String>,
+}
+
+impl PatchMeRequest {
+    /// 修改用户名或邮箱时需要校验的当前密码，避免被盗用的令牌借助找回密码接管账号
+    pub fn password_to_verify(&self) -> Result<Option<&str>, ServiceError> {
+        if self.username.is_none() && self.email.is_none() {
+            return Ok(None);
+        }
+        match self.current_password.as_deref() {
+            Some(password) => Ok(Some(password)),
+            None => Err(ServiceError::Validation(
+                "current_password is required to change the username or email".to_string(),
+            )),
+        }
+    }
+}
+
+impl From<PatchMeRequest> for PatchUserRequest {
+    fn from(req: PatchMeRequest) -> Self {
+        Self {
+            username: req.username,
+            email: req.email,
+            password: None,
+            status: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct ChangePasswordRequest {
+    /// Password the user signs in with now
+    pub current_password: String,
+    /// Checked against the server's password policy
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
+    #[schema(max_length = 128)]
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    /// Code from the authenticator app, `disable` also accepts a recovery code
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct ForgotPasswordRequest {
+    /// Email of the account, the response is the same whether or not an account uses it
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = Email, max_length = 255)]
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct ResetPasswordRequest {
+    /// Token from the password reset mail, it can only be used once
+    pub token: String,
+    /// Checked against the server's password policy
+    #[validate(length(max = 128, message = "must be at most 128 characters"))]
+    #[schema(max_length = 128)]
+    pub new_password: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListUsersRequest {
+    /// Maximum number of users to return, between 1 and 100 (default 20)
+    pub limit: Option<i64>,
+    /// Number of users to skip, cannot be combined with `cursor`
+    pub offset: Option<i64>,
+    /// Cursor taken from `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Comma separated sort fields (`id`, `created`, `updated`), prefix with `-` for descending order
+    #[param(example = "created,-updated")]
+    pub sort: Option<String>,
+    /// Only return users with this status
+    pub status: Option<UserStatus>,
+    /// Only return users created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only return users created before this time
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl ListUsersRequest {
+    pub fn page(&self) -> anyhow::Result<PageRequest> {
+        PageRequest::new(
+            self.limit,
+            self.offset,
+            self.cursor.as_deref(),
+            self.sort.as_deref(),
+        )
+    }
+
+    pub fn filter(&self) -> UserFilter {
+        UserFilter {
+            trashed: false,
+            status: self.status,
+            created_after: self.created_after,
+            created_before: self.created_before,
+        }
+    }
+}
+
+/// What happens to the posts of a user when the user is deleted
+#[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PostsPolicy {
+    /// Refuse to delete a user who still owns posts, including posts in the trash
+    #[default]
+    Block,
+    /// Move the posts to the trash together with the user, or remove them for good when the user is purged
+    Cascade,
+    /// Transfer the posts to the user given by `reassign_to`
+    Reassign,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteUserRequest {
+    /// What happens to the posts of the user (default `block`)
+    pub posts: Option<PostsPolicy>,
+    /// User who receives the posts when `posts` is `reassign`
+    pub reassign_to: Option<i64>,
+}
+
+impl DeleteUserRequest {
+    /// 转换为删除用户 `id` 时对文章的处理方式
+    pub fn disposal(&self, id: i64) -> Result<PostsDisposal, ServiceError> {
+        match self.posts.unwrap_or_default() {
+            PostsPolicy::Block => Ok(PostsDisposal::Block),
+            PostsPolicy::Cascade => Ok(PostsDisposal::Cascade),
+            PostsPolicy::Reassign => match self.reassign_to.filter(|to| *to != id) {
+                Some(to) => Ok(PostsDisposal::Reassign(to)),
+                None => Err(ServiceError::Validation(
+                    "reassign_to must name another user when posts=reassign".to_string(),
+                )),
+            },
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use crate::model::Category;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// 分类树中的一个节点
+#[derive(Serialize, ToSchema)]
+pub struct CategoryNode {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    /// Subcategories ordered by name
+    #[schema(no_recursion)]
+    pub children: Vec<CategoryNode>,
+}
+
+impl CategoryNode {
+    /// 将分类列表按 `parent_id` 组装成树，同级分类按名称排序
+    pub fn tree(categories: Vec<Category>) -> Vec<CategoryNode> {
+        let mut children: HashMap<Option<i64>, Vec<Category>> = HashMap::new();
+        for category in categories {
+            children
+                .entry(category.parent_id)
+                .or_default()
+                .push(category);
+        }
+        Self::build(None, &mut children)
+    }
+
+    fn build(
+        parent_id: Option<i64>,
+        children: &mut HashMap<Option<i64>, Vec<Category>>,
+    ) -> Vec<CategoryNode> {
+        let mut categories = children.remove(&parent_id).unwrap_or_default();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        categories
+            .into_iter()
+            .map(|category| CategoryNode {
+                id: category.id,
+                name: category.name,
+                slug: category.slug,
+                children: Self::build(Some(category.id), children),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryTreeResponse {
+    /// Top level categories with their subcategories
+    pub data: Vec<CategoryNode>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SingleCategoryResponse {
+    pub data: Category,
+}
@@ -0,0 +1,14 @@
+use crate::model::Comment;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct ListCommentResponse {
+    /// Comments of the post, oldest first
+    pub data: Vec<Comment>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SingleCommentResponse {
+    pub data: Comment,
+}
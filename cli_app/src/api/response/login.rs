@@ -1,8 +1,26 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::model::Lockout;
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct LoginResponse {
     pub status: String,
     pub token: String,
+    pub refresh_token: String,
+}
+
+/// Returned by `login` instead of tokens when the account uses two-factor authentication
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorChallengeResponse {
+    pub status: String,
+    /// Pass this token together with a code to `/login/2fa`
+    pub challenge_token: String,
+    /// Seconds until the challenge token expires
+    pub expires_in: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListLockoutResponse {
+    pub data: Vec<Lockout>,
 }
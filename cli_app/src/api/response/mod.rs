@@ -1,12 +1,30 @@
 use serde::{Deserialize, Serialize};
 
+use crate::model::Role;
+
+pub mod category;
+pub mod comment;
 pub mod login;
 pub mod post;
+pub mod tag;
 pub mod user;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenClaims {
+    /// 用户 ID，用户名可以修改并被他人重新注册，不能用来识别用户
     pub sub: String,
     pub iat: usize,
     pub exp: usize,
+    /// 会话 ID，撤销会话后该会话签发的令牌全部失效
+    #[serde(default)]
+    pub jti: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+impl TokenClaims {
+    /// 令牌所属用户的 ID，`sub` 不是数字时返回 `None`
+    pub fn user_id(&self) -> Option<i64> {
+        self.sub.parse().ok()
+    }
 }
@@ -1,4 +1,4 @@
-use crate::model::Post;
+use crate::model::{Post, PostRevision, PostStatus};
 use serde::Serialize;
 use utoipa::ToSchema;
 
@@ -10,4 +10,66 @@ pub struct SinglePostResponse {
 #[derive(Serialize, ToSchema)]
 pub struct ListPostResponse {
     pub data: Vec<Post>,
+    /// Number of posts matching the filters
+    pub total: i64,
+    /// Cursor for the next page, absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchPostHit {
+    pub post: Post,
+    /// Relevance of the post, higher is better
+    pub rank: f32,
+    /// Part of the content around the first match, matches are wrapped in `<mark>`
+    #[schema(example = "…supports <mark>full</mark> <mark>text</mark> search…")]
+    pub snippet: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchPostResponse {
+    /// Matching posts, most relevant first
+    pub data: Vec<SearchPostHit>,
+    /// Number of posts matching the query and filters
+    pub total: i64,
+    /// Offset of the next page, absent on the last page
+    pub next_offset: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListRevisionResponse {
+    /// Revisions of the post, newest first
+    pub data: Vec<PostRevision>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SingleRevisionResponse {
+    pub data: PostRevision,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TextChange {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StatusChange {
+    pub from: PostStatus,
+    pub to: PostStatus,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RevisionDiffResponse {
+    pub from: i64,
+    pub to: i64,
+    /// Absent if the title did not change
+    pub title: Option<TextChange>,
+    /// Absent if the slug did not change
+    pub slug: Option<TextChange>,
+    /// Absent if the status did not change
+    pub status: Option<StatusChange>,
+    /// Unified diff of the content, empty if the content did not change
+    #[schema(example = "@@ -1 +1 @@\n-old line\n+new line\n")]
+    pub content: String,
 }
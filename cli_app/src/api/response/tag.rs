@@ -0,0 +1,14 @@
+use crate::model::{Tag, TagCount};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct ListTagResponse {
+    /// All tags ordered by slug
+    pub data: Vec<TagCount>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SingleTagResponse {
+    pub data: Tag,
+}
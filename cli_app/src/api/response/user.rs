@@ -1,13 +1,91 @@
-use crate::model::User;
+use crate::model::{Role, User, UserStatus};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use utoipa::ToSchema;
 
+/// 返回给任何调用者的用户信息
+#[derive(Serialize, ToSchema)]
+pub struct PublicUser {
+    pub id: i64,
+    pub username: String,
+    pub created: DateTime<Utc>,
+}
+
+impl From<User> for PublicUser {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            created: user.created,
+        }
+    }
+}
+
+/// 返回给拥有用户管理权限的调用者的用户信息，同样不包含密码哈希
+#[derive(Serialize, ToSchema)]
+pub struct AdminUser {
+    pub id: i64,
+    pub username: String,
+    /// Address that password reset mails are sent to
+    pub email: Option<String>,
+    pub status: UserStatus,
+    pub role: Role,
+    /// Incremented on every change, used as the entity tag
+    pub version: i64,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub last_login: Option<DateTime<Utc>>,
+    /// Time the user was moved to the trash, absent for active users
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for AdminUser {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            status: user.status,
+            role: user.role,
+            version: user.version,
+            created: user.created,
+            updated: user.updated,
+            last_login: user.last_login,
+            deleted_at: user.deleted_at,
+        }
+    }
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct ListUserResponse {
-    pub data: Vec<User>,
+    pub data: Vec<AdminUser>,
+    /// Number of users matching the filters
+    pub total: i64,
+    /// Cursor for the next page, absent on the last page
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
 pub struct SingleUserResponse {
-    pub data: User,
+    pub data: AdminUser,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PublicUserResponse {
+    pub data: PublicUser,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorEnrollmentResponse {
+    /// Base32 encoded secret for entering into an authenticator app by hand
+    pub secret: String,
+    /// `otpauth://` URI, usually shown as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    /// Single-use codes that replace an authenticator code, they are only shown once
+    pub recovery_codes: Vec<String>,
 }
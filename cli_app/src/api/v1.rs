@@ -1,13 +1,17 @@
 use std::sync::Arc;
 
+use crate::model::Permission;
 use crate::state::ApplicationState;
 
 use super::handlers;
 use super::middleware::auth::auth;
-use axum::routing::{delete, get, post, put};
+use super::middleware::permission::RequirePermission;
+use axum::routing::{delete, get, patch, post, put};
 use axum::{Router, middleware};
-use utoipa::OpenApi;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 pub fn configure(state: Arc<ApplicationState>) -> Router {
+    let require_auth = || middleware::from_fn_with_state(state.clone(), auth);
     Router::new()
         .route(
             "/hello",
@@ -17,12 +21,17 @@ pub fn configure(state: Arc<ApplicationState>) -> Router {
             "/posts",
             post(handlers::posts::create)
                 .with_state(state.clone())
-                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
         )
         .route(
             "/posts",
             get(handlers::posts::list).with_state(state.clone()),
         )
+        .route(
+            "/posts/search",
+            get(handlers::posts::search).with_state(state.clone()),
+        )
         .route(
             "/posts/{id}",
             get(handlers::posts::get).with_state(state.clone()),
@@ -33,37 +42,328 @@ pub fn configure(state: Arc<ApplicationState>) -> Router {
         )
         .route(
             "/posts/{id}",
-            put(handlers::posts::update).with_state(state.clone()),
+            put(handlers::posts::update)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
         )
         .route(
             "/posts/{id}",
-            delete(handlers::posts::delete).with_state(state.clone()),
+            patch(handlers::posts::patch)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}",
+            delete(handlers::posts::delete)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/revisions",
+            get(handlers::revisions::list)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/revisions/diff",
+            get(handlers::revisions::diff)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/revisions/{rev}",
+            get(handlers::revisions::get)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/revisions/{rev}/restore",
+            post(handlers::revisions::restore)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/tags",
+            put(handlers::posts::set_tags)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/category",
+            put(handlers::posts::set_category)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/comments",
+            get(handlers::comments::list).with_state(state.clone()),
+        )
+        .route(
+            "/posts/{id}/comments",
+            post(handlers::comments::create)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/comments/hidden",
+            get(handlers::comments::list_hidden)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/comments/{comment_id}",
+            put(handlers::comments::update)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/comments/{comment_id}",
+            delete(handlers::comments::delete)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/comments/{comment_id}/hide",
+            post(handlers::comments::hide)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/posts/{id}/comments/{comment_id}/approve",
+            post(handlers::comments::approve)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route("/tags", get(handlers::tags::list).with_state(state.clone()))
+        .route(
+            "/tags/{id}",
+            get(handlers::tags::get).with_state(state.clone()),
+        )
+        .route(
+            "/tags",
+            post(handlers::tags::create)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/tags/{id}",
+            put(handlers::tags::update)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/tags/{id}",
+            delete(handlers::tags::delete)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/categories",
+            get(handlers::categories::tree).with_state(state.clone()),
+        )
+        .route(
+            "/categories/{id}",
+            get(handlers::categories::get).with_state(state.clone()),
+        )
+        .route(
+            "/categories",
+            post(handlers::categories::create)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/categories/{id}",
+            put(handlers::categories::update)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/categories/{id}",
+            delete(handlers::categories::delete)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
         )
         .route(
             "/users",
-            post(handlers::users::create).with_state(state.clone()),
+            post(handlers::users::create)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
         )
         .route(
             "/users",
-            get(handlers::users::list).with_state(state.clone()),
+            get(handlers::users::list)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersRead))
+                .route_layer(require_auth()),
         )
         .route(
             "/users/{id}",
-            get(handlers::users::get).with_state(state.clone()),
+            get(handlers::users::get)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersRead))
+                .route_layer(require_auth()),
         )
         .route(
             "/users/name/{name}",
-            get(handlers::users::get_by_username).with_state(state.clone()),
+            get(handlers::users::get_by_username)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersRead))
+                .route_layer(require_auth()),
         )
         .route(
             "/users/{id}",
-            put(handlers::users::update).with_state(state.clone()),
+            put(handlers::users::update)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
         )
         .route(
             "/users/{id}",
-            delete(handlers::users::delete).with_state(state.clone()),
+            patch(handlers::users::patch)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/users/{id}",
+            delete(handlers::users::delete)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/users/{id}/sessions",
+            delete(handlers::users::revoke_sessions)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/lockouts",
+            get(handlers::lockouts::list)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/lockouts/{kind}/{value}",
+            delete(handlers::lockouts::clear)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/trash/posts",
+            get(handlers::trash::list_posts)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/trash/posts/{id}/restore",
+            post(handlers::trash::restore_post)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/trash/posts/{id}",
+            delete(handlers::trash::purge_post)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::PostsManage))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/trash/users",
+            get(handlers::trash::list_users)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
         )
+        .route(
+            "/trash/users/{id}/restore",
+            post(handlers::trash::restore_user)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/trash/users/{id}",
+            delete(handlers::trash::purge_user)
+                .with_state(state.clone())
+                .route_layer(RequirePermission(Permission::UsersWrite))
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me",
+            get(handlers::me::get)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me",
+            patch(handlers::me::patch)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me",
+            delete(handlers::me::delete)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me/password",
+            post(handlers::me::change_password)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me/2fa/enroll",
+            post(handlers::two_factor::enroll)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me/2fa/enable",
+            post(handlers::two_factor::enable)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route(
+            "/me/2fa/disable",
+            post(handlers::two_factor::disable)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
+        .route("/signup", post(handlers::me::signup))
+        .route("/password/forgot", post(handlers::password::forgot))
+        .route("/password/reset", post(handlers::password::reset))
         .route("/login", post(handlers::login::login))
+        .route("/login/2fa", post(handlers::login::login_2fa))
+        .route("/token/refresh", post(handlers::login::refresh))
+        .route(
+            "/logout",
+            post(handlers::login::logout)
+                .with_state(state.clone())
+                .route_layer(require_auth()),
+        )
         .with_state(state.clone())
 }
 
@@ -76,29 +376,143 @@ pub fn configure(state: Arc<ApplicationState>) -> Router {
         handlers::hello::hello,
         handlers::posts::create,
         handlers::posts::list,
+        handlers::posts::search,
         handlers::posts::get,
         handlers::posts::get_by_slug,
         handlers::posts::update,
+        handlers::posts::patch,
         handlers::posts::delete,
+        handlers::posts::set_tags,
+        handlers::posts::set_category,
+        handlers::comments::list,
+        handlers::comments::list_hidden,
+        handlers::comments::create,
+        handlers::comments::update,
+        handlers::comments::delete,
+        handlers::comments::hide,
+        handlers::comments::approve,
+        handlers::tags::list,
+        handlers::tags::get,
+        handlers::tags::create,
+        handlers::tags::update,
+        handlers::tags::delete,
+        handlers::categories::tree,
+        handlers::categories::get,
+        handlers::categories::create,
+        handlers::categories::update,
+        handlers::categories::delete,
+        handlers::revisions::list,
+        handlers::revisions::get,
+        handlers::revisions::diff,
+        handlers::revisions::restore,
+        handlers::trash::list_posts,
+        handlers::trash::restore_post,
+        handlers::trash::purge_post,
+        handlers::trash::list_users,
+        handlers::trash::restore_user,
+        handlers::trash::purge_user,
+        handlers::me::signup,
+        handlers::me::get,
+        handlers::me::patch,
+        handlers::me::change_password,
+        handlers::me::delete,
+        handlers::two_factor::enroll,
+        handlers::two_factor::enable,
+        handlers::two_factor::disable,
+        handlers::password::forgot,
+        handlers::password::reset,
         handlers::login::login,
+        handlers::login::login_2fa,
+        handlers::login::refresh,
+        handlers::login::logout,
+        handlers::lockouts::list,
+        handlers::lockouts::clear,
     ),
     components(
         schemas(
             crate::api::request::post::CreatePostRequest,
             crate::api::response::post::ListPostResponse,
             crate::api::response::post::SinglePostResponse,
+            crate::api::response::post::SearchPostResponse,
+            crate::api::response::post::SearchPostHit,
+            crate::api::response::post::ListRevisionResponse,
+            crate::api::response::post::SingleRevisionResponse,
+            crate::api::response::post::RevisionDiffResponse,
+            crate::api::response::post::TextChange,
+            crate::api::response::post::StatusChange,
+            crate::model::PostRevision,
+            crate::api::request::post::SetPostTagsRequest,
+            crate::api::request::post::SetPostCategoryRequest,
+            crate::api::request::tag::CreateTagRequest,
+            crate::api::request::tag::UpdateTagRequest,
+            crate::api::response::tag::ListTagResponse,
+            crate::api::response::tag::SingleTagResponse,
+            crate::api::request::category::CreateCategoryRequest,
+            crate::api::request::category::UpdateCategoryRequest,
+            crate::api::response::category::CategoryNode,
+            crate::api::response::category::CategoryTreeResponse,
+            crate::api::response::category::SingleCategoryResponse,
+            crate::api::request::comment::CreateCommentRequest,
+            crate::api::request::comment::UpdateCommentRequest,
+            crate::api::response::comment::ListCommentResponse,
+            crate::api::response::comment::SingleCommentResponse,
+            crate::model::Comment,
+            crate::model::CommentStatus,
+            crate::model::Tag,
+            crate::model::TagCount,
+            crate::model::Category,
             crate::api::request::post::CreatePostRequest,
+            crate::api::request::post::PatchPostRequest,
+            crate::api::request::user::SignupRequest,
+            crate::api::request::user::PatchMeRequest,
+            crate::api::request::user::ChangePasswordRequest,
+            crate::api::request::user::ForgotPasswordRequest,
+            crate::api::request::user::ResetPasswordRequest,
+            crate::api::request::user::TwoFactorCodeRequest,
+            crate::api::response::user::TwoFactorEnrollmentResponse,
+            crate::api::response::user::RecoveryCodesResponse,
             crate::api::request::login::LoginRequest,
+            crate::api::request::login::RefreshTokenRequest,
+            crate::api::request::login::LoginTwoFactorRequest,
             crate::api::response::login::LoginResponse,
+            crate::api::response::login::TwoFactorChallengeResponse,
+            crate::api::response::login::ListLockoutResponse,
+            crate::model::Lockout,
+            crate::model::LockoutKind,
         )
     ),
     tags(
         (name="Hello",description="hello world"),
         (name="Posts",description="posts api"),
+        (name="Comments",description="comments on posts"),
+        (name="Tags",description="post tags"),
+        (name="Categories",description="post categories"),
+        (name="Trash",description="deleted posts and users"),
+        (name="Account",description="account of the current user"),
         (name="Login",description="login api"),
     ),
     servers(
         (url="/v1",description="v1版本")
     ),
+    modifiers(&SecurityAddon),
 )]
 pub struct ApiDoc;
+
+/// 注册 JWT Bearer 认证方式，供需要登录的接口引用
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(
+                    Http::builder()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+        }
+    }
+}
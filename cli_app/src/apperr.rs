@@ -1,35 +1,143 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use serde::Serialize;
 use utoipa::ToSchema;
-#[derive(Debug, ToSchema)]
+
+use crate::services::error::ServiceError;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Problem details as described in RFC 7807, returned as `application/problem+json`
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct AppError {
-    status_code: u16,
-    err: String,
+    /// URI reference identifying the problem type
+    #[serde(rename = "type")]
+    #[schema(example = "/problems/not-found")]
+    pub problem_type: String,
+    /// Short, human-readable summary of the problem type
+    #[schema(example = "Not Found")]
+    pub title: String,
+    /// HTTP status code of the response
+    #[schema(example = 404)]
+    pub status: u16,
+    /// Explanation specific to this occurrence of the problem
+    #[schema(example = "Post not found: 42")]
+    pub detail: String,
+    /// Request path that produced the problem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "/v1/posts/42")]
+    pub instance: Option<String>,
+    /// Per-field details of a failed request validation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// A single rule violated by a request field
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    /// Name of the offending field
+    #[schema(example = "slug")]
+    pub field: String,
+    /// Identifier of the violated rule
+    #[schema(example = "regex")]
+    pub code: String,
+    /// Human-readable explanation
+    #[schema(example = "must be lowercase letters and digits separated by single '-'")]
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        let problem_type = match status {
+            StatusCode::BAD_REQUEST => "/problems/bad-request",
+            StatusCode::UNAUTHORIZED => "/problems/unauthorized",
+            StatusCode::FORBIDDEN => "/problems/forbidden",
+            StatusCode::NOT_FOUND => "/problems/not-found",
+            StatusCode::CONFLICT => "/problems/conflict",
+            StatusCode::PRECONDITION_FAILED => "/problems/precondition-failed",
+            StatusCode::UNPROCESSABLE_ENTITY => "/problems/validation",
+            StatusCode::TOO_MANY_REQUESTS => "/problems/too-many-requests",
+            StatusCode::INTERNAL_SERVER_ERROR => "/problems/internal",
+            _ => "about:blank",
+        };
+        Self {
+            problem_type: problem_type.to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            instance: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// 请求参数校验失败，返回 422 并列出每个字段的错误
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        Self {
+            errors,
+            ..Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Request validation failed",
+            )
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// 内部错误只记录日志，不向客户端暴露细节
+    fn internal(err: anyhow::Error) -> Self {
+        tracing::error!("Internal error: {:#}", err);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+    }
 }
 
 impl From<(StatusCode, anyhow::Error)> for AppError {
     fn from((status_code, err): (StatusCode, anyhow::Error)) -> Self {
-        Self {
-            status_code: status_code.as_u16(),
-            err: err.to_string(),
+        if status_code.is_server_error() {
+            return Self::internal(err);
         }
+        Self::new(status_code, err.to_string())
     }
 }
 
 impl From<anyhow::Error> for AppError {
     fn from(err: anyhow::Error) -> Self {
-        Self {
-            status_code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-            err: err.to_string(),
+        Self::internal(err)
+    }
+}
+
+impl From<ServiceError> for AppError {
+    fn from(err: ServiceError) -> Self {
+        let status = match &err {
+            ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            ServiceError::Conflict(_) => StatusCode::CONFLICT,
+            ServiceError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ServiceError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServiceError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ServiceError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ServiceError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        match err {
+            ServiceError::Internal(err) => Self::internal(err),
+            err => Self::new(status, err.to_string()),
         }
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        (
-            StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
-            self.err,
+        let mut response = (
+            self.status_code(),
+            [(header::CONTENT_TYPE, PROBLEM_JSON)],
+            Json(&self),
         )
-            .into_response()
+            .into_response();
+        //供中间件补充 instance
+        response.extensions_mut().insert(self);
+        response
     }
 }
@@ -0,0 +1,236 @@
+use std::{collections::HashSet, str::FromStr};
+
+use anyhow::Context;
+use clap::{ArgMatches, Command};
+use sqlx::{
+    Database, PgPool, Pool, SqlitePool,
+    migrate::{Migrate, Migrator},
+    sqlite::SqliteConnectOptions,
+};
+
+use crate::settings::{Settings, StorageBackend};
+
+pub const COMMAND_NAME: &str = "migrate";
+
+const UP: &str = "up";
+const DOWN: &str = "down";
+const STATUS: &str = "status";
+const REDO: &str = "redo";
+const BASELINE: &str = "baseline";
+
+/// 将旧版 `sqlx/schema.sql` 建立的数据库调整为与 0001 迁移一致的结构
+const POSTGRES_BASELINE: &str = include_str!("../../sqlx/baseline/postgres.sql");
+
+/// 编译期嵌入的数据库迁移，版本记录在 `_sqlx_migrations` 表中
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./sqlx/migrations/postgres");
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./sqlx/migrations/sqlite");
+pub fn configure() -> Command {
+    Command::new(COMMAND_NAME)
+        .about("Manage database schema migrations")
+        .subcommand(Command::new(UP).about("Apply all pending migrations"))
+        .subcommand(Command::new(DOWN).about("Revert the latest applied migration"))
+        .subcommand(Command::new(STATUS).about("Show applied and pending migrations"))
+        .subcommand(
+            Command::new(REDO).about("Revert the latest applied migration and apply it again"),
+        )
+        .subcommand(Command::new(BASELINE).about(
+            "Mark the initial migration as applied on a database created from the old schema.sql",
+        ))
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+}
+
+pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
+    let Some((mode, _)) = matches.subcommand() else {
+        return Ok(());
+    };
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            let db_url = settings
+                .database
+                .url
+                .clone()
+                .context("Database URL is not set")?;
+
+            match settings.storage.backend {
+                StorageBackend::Postgres => {
+                    let pool = connect_postgres(&db_url).await?;
+                    if mode == BASELINE {
+                        return baseline_postgres(&POSTGRES_MIGRATOR, &pool).await;
+                    }
+                    execute(mode, &POSTGRES_MIGRATOR, &pool).await
+                }
+                StorageBackend::Sqlite if mode == BASELINE => {
+                    anyhow::bail!("Only PostgreSQL databases were created from the old schema.sql")
+                }
+                StorageBackend::Sqlite => {
+                    let pool = connect_sqlite(&db_url).await?;
+                    execute(mode, &SQLITE_MIGRATOR, &pool).await
+                }
+                StorageBackend::Memory => {
+                    anyhow::bail!("The memory storage backend has no schema to migrate")
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+async fn execute<DB>(mode: &str, migrator: &Migrator, pool: &Pool<DB>) -> anyhow::Result<()>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    match mode {
+        UP => up(migrator, pool).await?,
+        DOWN => down(migrator, pool).await?,
+        STATUS => status(migrator, pool).await?,
+        REDO => {
+            down(migrator, pool).await?;
+            up(migrator, pool).await?;
+        }
+        &_ => {}
+    }
+    Ok(())
+}
+
+async fn connect_postgres(db_url: &str) -> anyhow::Result<PgPool> {
+    sqlx::postgres::PgPoolOptions::new()
+        .max_connections(1)
+        .connect(db_url)
+        .await
+        .context("Failed to create database connection pool")
+}
+
+/// 连接SQLite数据库，数据库文件不存在时自动创建
+pub async fn connect_sqlite(db_url: &str) -> anyhow::Result<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(db_url)?.create_if_missing(true);
+    sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+        .context("Failed to create database connection pool")
+}
+
+/// 执行所有未应用的迁移
+pub async fn up<DB>(migrator: &Migrator, pool: &Pool<DB>) -> anyhow::Result<()>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    migrator
+        .run(pool)
+        .await
+        .context("Failed to apply migrations")?;
+    println!("Database is up to date");
+    Ok(())
+}
+
+/// 回滚最近一次应用的迁移
+async fn down<DB>(migrator: &Migrator, pool: &Pool<DB>) -> anyhow::Result<()>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let mut applied = applied_versions(pool).await?;
+    applied.sort_unstable();
+
+    let Some(latest) = applied.pop() else {
+        println!("No migrations to revert");
+        return Ok(());
+    };
+    let target = applied.last().copied().unwrap_or(0);
+
+    migrator
+        .undo(pool, target)
+        .await
+        .with_context(|| format!("Failed to revert migration {}", latest))?;
+    println!("Reverted migration {}", latest);
+    Ok(())
+}
+
+/// 旧版本通过 `sqlx/schema.sql` 手工建表，没有迁移记录，直接执行 `up` 会因表已存在而失败。
+/// 在同一事务中把表结构调整为 0001 迁移的结果并记录该迁移已应用，之后再执行 `up`
+async fn baseline_postgres(migrator: &Migrator, pool: &PgPool) -> anyhow::Result<()> {
+    let applied = applied_versions(pool).await?;
+    if !applied.is_empty() {
+        anyhow::bail!("The database already has applied migrations, run `migrate up` instead");
+    }
+    let initial = migrator
+        .iter()
+        .find(|m| !m.migration_type.is_down_migration())
+        .context("No migrations embedded in the binary")?;
+
+    let mut tx = pool.begin().await?;
+    let (exists,): (bool,) = sqlx::query_as("SELECT to_regclass('users') IS NOT NULL")
+        .fetch_one(&mut *tx)
+        .await?;
+    if !exists {
+        anyhow::bail!("The database has no users table, run `migrate up` instead");
+    }
+    sqlx::raw_sql(POSTGRES_BASELINE)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to adjust the schema to the initial migration")?;
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+         VALUES ($1, $2, TRUE, $3, 0)",
+    )
+    .bind(initial.version)
+    .bind(initial.description.as_ref())
+    .bind(initial.checksum.as_ref())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    println!(
+        "Marked migration {} as applied, run `migrate up` to apply the rest",
+        initial.version
+    );
+    Ok(())
+}
+
+async fn status<DB>(migrator: &Migrator, pool: &Pool<DB>) -> anyhow::Result<()>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let applied: HashSet<i64> = applied_versions(pool).await?.into_iter().collect();
+
+    for migration in migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+    {
+        let state = if applied.contains(&migration.version) {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!(
+            "{:>6}  {:<8}  {}",
+            migration.version, state, migration.description
+        );
+    }
+
+    for version in applied.iter() {
+        if !migrator.version_exists(*version) {
+            println!("{:>6}  {:<8}  <missing from binary>", version, "applied");
+        }
+    }
+
+    Ok(())
+}
+
+async fn applied_versions<DB>(pool: &Pool<DB>) -> anyhow::Result<Vec<i64>>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+    Ok(applied.into_iter().map(|m| m.version).collect())
+}
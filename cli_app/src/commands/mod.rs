@@ -1,14 +1,33 @@
+use std::sync::Arc;
+
+use anyhow::Context;
 use clap::{ArgMatches, Command};
 
-use crate::settings::Settings;
+use crate::{
+    services::{
+        comment::{PgSqlCommentService, SqliteCommentService},
+        password_reset::{PgSqlPasswordResetService, SqlitePasswordResetService},
+        post::{PgSqlPostService, SqlitePostService},
+        session::{PgSqlSessionService, SqliteSessionService},
+        two_factor::{PgSqlTwoFactorService, SqliteTwoFactorService},
+        user::{PgSqlUserService, SqliteUserService},
+    },
+    settings::{Settings, StorageBackend},
+    state::ApplicationState,
+    utils::password::PasswordHasher,
+};
 
 mod hello;
+pub mod migrate;
+mod roles;
 mod serve;
 
 pub fn configure(command: Command) -> Command {
     command
         .subcommand(hello::configure())
         .subcommand(serve::configure())
+        .subcommand(migrate::configure())
+        .subcommand(roles::configure())
         .arg_required_else_help(true)
 }
 
@@ -17,8 +36,74 @@ pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
         match cmd {
             hello::COMMAND_NAME => hello::handle(matches, settings)?,
             serve::COMMAND_NAME => serve::handle(matches, settings)?,
+            migrate::COMMAND_NAME => migrate::handle(matches, settings)?,
+            roles::COMMAND_NAME => roles::handle(matches, settings)?,
             &_ => {}
         }
     }
     Ok(())
 }
+
+/// 根据配置选择存储后端并创建应用程序状态
+///
+/// # 参数
+///
+/// * `settings` - 应用程序的配置设置
+/// * `migrate` - 创建状态前是否执行未应用的数据库迁移
+pub async fn build_state(settings: &Settings, migrate: bool) -> anyhow::Result<ApplicationState> {
+    let hasher = PasswordHasher::new(&settings.password)?;
+    match settings.storage.backend {
+        StorageBackend::Postgres => {
+            //数据库连接
+            let db_url = settings
+                .database
+                .url
+                .clone()
+                .context("Database URL is not set")?;
+
+            let pool = sqlx::postgres::PgPoolOptions::new()
+                .max_connections(5)
+                .connect(&db_url)
+                .await
+                .context("Failed to create database connection pool")?;
+
+            if migrate {
+                migrate::up(&migrate::POSTGRES_MIGRATOR, &pool).await?;
+            }
+
+            ApplicationState::new(
+                settings,
+                Arc::new(PgSqlUserService::new(pool.clone(), hasher)),
+                Arc::new(PgSqlPostService::new(pool.clone())),
+                Arc::new(PgSqlCommentService::new(pool.clone())),
+                Arc::new(PgSqlSessionService::new(pool.clone())),
+                Arc::new(PgSqlPasswordResetService::new(pool.clone())),
+                Arc::new(PgSqlTwoFactorService::new(pool)),
+            )
+        }
+        StorageBackend::Memory => ApplicationState::in_memory(settings),
+        StorageBackend::Sqlite => {
+            let db_url = settings
+                .database
+                .url
+                .clone()
+                .context("Database URL is not set")?;
+
+            let pool = migrate::connect_sqlite(&db_url).await?;
+
+            if migrate {
+                migrate::up(&migrate::SQLITE_MIGRATOR, &pool).await?;
+            }
+
+            ApplicationState::new(
+                settings,
+                Arc::new(SqliteUserService::new(pool.clone(), hasher)),
+                Arc::new(SqlitePostService::new(pool.clone())),
+                Arc::new(SqliteCommentService::new(pool.clone())),
+                Arc::new(SqliteSessionService::new(pool.clone())),
+                Arc::new(SqlitePasswordResetService::new(pool.clone())),
+                Arc::new(SqliteTwoFactorService::new(pool)),
+            )
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{model::Role, settings::Settings};
+
+pub const COMMAND_NAME: &str = "roles";
+
+const GRANT: &str = "grant";
+const REVOKE: &str = "revoke";
+
+pub fn configure() -> Command {
+    let username = Arg::new("username")
+        .value_name("USERNAME")
+        .help("User to change")
+        .required(true);
+
+    Command::new(COMMAND_NAME)
+        .about("Grant and revoke user roles")
+        .subcommand(
+            Command::new(GRANT)
+                .about("Grant a role to a user, their sessions are signed out")
+                .arg(username.clone())
+                .arg(
+                    Arg::new("role")
+                        .value_name("ROLE")
+                        .help("Role to grant")
+                        .value_parser(["admin", "editor", "author", "reader"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new(REVOKE)
+                .about("Revoke the role of a user, falling back to reader and signing out their sessions")
+                .arg(username),
+        )
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+}
+
+pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
+    let Some((mode, matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+
+    let username = matches
+        .get_one::<String>("username")
+        .map(|s| s.as_str())
+        .unwrap_or("");
+    let role = match mode {
+        GRANT => matches
+            .get_one::<String>("role")
+            .map(|s| s.parse::<Role>())
+            .transpose()?
+            .unwrap_or_default(),
+        _ => Role::default(),
+    };
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            let state = super::build_state(settings, false).await?;
+            let before = state.user_service.get_user_by_username(username).await?;
+            let user = state.user_service.set_role(before.id, role).await?;
+            println!("User {} now has role {:?}", user.username, user.role);
+            //令牌中记录了签发时的角色，角色变化后让已登录的会话失效，重新登录后才能使用新的权限
+            if user.role != before.role {
+                let count = state.session_service.revoke_user_sessions(user.id).await?;
+                println!("Signed out {} session(s) of {}", count, user.username);
+            }
+            anyhow::Ok(())
+        })?;
+
+    Ok(())
+}
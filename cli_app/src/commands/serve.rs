@@ -2,9 +2,10 @@ use std::{
     collections::HashMap,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     sync::Arc,
+    time::Duration,
 };
 
-use clap::{Arg, ArgMatches, Command, value_parser};
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
 use opentelemetry::{
     KeyValue, global,
     trace::{TraceError, TracerProvider},
@@ -16,6 +17,7 @@ use opentelemetry_sdk::{
     runtime,
     trace::{self, RandomIdGenerator, Sampler, Tracer},
 };
+use tokio::time::MissedTickBehavior;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -39,13 +41,20 @@ pub fn configure() -> Command {
                 .default_value("8080")
                 .value_parser(value_parser!(u16)),
         )
+        .arg(
+            Arg::new("migrate")
+                .long("migrate")
+                .help("Apply pending database migrations before starting")
+                .action(ArgAction::SetTrue),
+        )
 }
 
 pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
     let port = *matches.get_one("port").unwrap_or(&8080);
+    let migrate = matches.get_flag("migrate");
     println!("Start the HTTP server on port {}", port);
 
-    start_tokio(port, settings)?;
+    start_tokio(port, migrate, settings)?;
 
     Ok(())
 }
@@ -55,12 +64,13 @@ pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
 /// # 参数
 ///
 /// * `port` - 服务器监听的端口号
+/// * `migrate` - 启动前是否执行未应用的数据库迁移
 /// * `settings` - 应用程序的配置设置
 ///
 /// # 返回值
 ///
 /// 如果成功，返回`Ok(())`；如果失败，返回`Err`，其中包含错误信息
-fn start_tokio(port: u16, settings: &Settings) -> anyhow::Result<()> {
+fn start_tokio(port: u16, migrate: bool, settings: &Settings) -> anyhow::Result<()> {
     // 创建一个新的Tokio运行时构建器
     tokio::runtime::Builder::new_multi_thread()
         // 启用所有Tokio特性
@@ -82,7 +92,7 @@ fn start_tokio(port: u16, settings: &Settings) -> anyhow::Result<()> {
                     .add_directive("axum=debug".parse()?)
                     .add_directive("tower_http=debug".parse()?)
                     .add_directive("sqlx=debug".parse()?)
-                    .add_directive("sqlx::query=debug".parse()?)
+                    .add_directive("sqlx::query=debug".parse()?),
             );
 
             // 创建一个新的追踪订阅器，包含Telemetry层和标准输出日志层
@@ -93,31 +103,23 @@ fn start_tokio(port: u16, settings: &Settings) -> anyhow::Result<()> {
             // 初始化追踪订阅器
             subscriber.init();
 
-            //数据库连接
-            let db_url = settings
-                .database
-                .url
-                .clone()
-                .expect("Database URL is not set");
-
-            let pool = sqlx::postgres::PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&db_url)
-                .await
-                .expect("Failed to create database connection pool");
-
-            // 创建一个新的应用程序状态
-            let state = Arc::new(ApplicationState::new(settings, pool)?);
+            // 根据配置选择存储后端，创建一个新的应用程序状态
+            let state = Arc::new(super::build_state(settings, migrate).await?);
+            // 在后台定期发布到期的定时文章，随运行时一起结束
+            tokio::spawn(publish_scheduled_posts(state.clone()));
             // 配置应用程序的路由
             let router = crate::api::configure(state).layer(TraceLayer::new_for_http()); // 创建一个新的套接字地址
             let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
             // 绑定到套接字地址并监听
             let listener = tokio::net::TcpListener::bind(addr).await?;
             // 启动HTTP服务器并处理请求
-            axum::serve(listener, router.into_make_service())
-                // 使用优雅关闭信号处理程序
-                .with_graceful_shutdown(shutdown::shutdown_signal())
-                .await?;
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            // 使用优雅关闭信号处理程序
+            .with_graceful_shutdown(shutdown::shutdown_signal())
+            .await?;
 
             anyhow::Ok(())
         })?;
@@ -125,6 +127,25 @@ fn start_tokio(port: u16, settings: &Settings) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// 按配置的间隔发布到期的定时文章，单次失败只记录日志，下次继续检查
+async fn publish_scheduled_posts(state: Arc<ApplicationState>) {
+    let interval = state.settings.load().scheduler.interval_seconds.max(1);
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        match state
+            .post_service
+            .publish_due_posts(chrono::offset::Utc::now())
+            .await
+        {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Published {} scheduled posts", count),
+            Err(err) => tracing::error!("Failed to publish scheduled posts: {:#}", err),
+        }
+    }
+}
+
 pub fn init_tracer(otlp_target: &OtlpTarget) -> Result<Tracer, TraceError> {
     global::set_text_map_propagator(TraceContextPropagator::new());
 
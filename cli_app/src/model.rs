@@ -1,20 +1,35 @@
+use std::{fmt, str::FromStr};
+
 use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Copy, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+/// 数据库中保存的枚举值无法识别
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown {kind}: {code}")]
+pub struct UnknownCode {
+    pub kind: &'static str,
+    pub code: i32,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub enum UserStatus {
     Active = 1,
     Blocked = 2,
 }
 
-impl From<i32> for UserStatus {
-    fn from(value: i32) -> Self {
+impl TryFrom<i32> for UserStatus {
+    type Error = UnknownCode;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
         match value {
-            1 => Self::Active,
-            2 => Self::Blocked,
-            _ => Self::Active,
+            1 => Ok(Self::Active),
+            2 => Ok(Self::Blocked),
+            code => Err(UnknownCode {
+                kind: "user status",
+                code,
+            }),
         }
     }
 }
@@ -28,18 +43,48 @@ impl From<UserStatus> for i32 {
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub enum PostStatus {
     Draft = 1,
     Published = 2,
+    /// Published automatically at `publish_at`
+    Scheduled = 3,
+    /// No longer listed as published, can be published again
+    Archived = 4,
+    /// Waiting for an editor to review it
+    PendingReview = 5,
+}
+
+impl PostStatus {
+    /// 是否允许从当前状态变更为 `next`，状态不变总是允许的
+    pub fn can_transition_to(self, next: PostStatus) -> bool {
+        use PostStatus::*;
+        self == next
+            || matches!(
+                (self, next),
+                (Draft, PendingReview | Scheduled | Published)
+                    | (PendingReview, Draft | Scheduled | Published)
+                    | (Scheduled, Draft | PendingReview | Published)
+                    | (Published, Draft | Archived)
+                    | (Archived, Draft | Published)
+            )
+    }
 }
 
-impl From<i32> for PostStatus {
-    fn from(value: i32) -> Self {
+impl TryFrom<i32> for PostStatus {
+    type Error = UnknownCode;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
         match value {
-            1 => Self::Draft,
-            2 => Self::Published,
-            _ => Self::Draft,
+            1 => Ok(Self::Draft),
+            2 => Ok(Self::Published),
+            3 => Ok(Self::Scheduled),
+            4 => Ok(Self::Archived),
+            5 => Ok(Self::PendingReview),
+            code => Err(UnknownCode {
+                kind: "post status",
+                code,
+            }),
         }
     }
 }
@@ -49,19 +94,163 @@ impl From<PostStatus> for i32 {
         match value {
             PostStatus::Draft => 1,
             PostStatus::Published => 2,
+            PostStatus::Scheduled => 3,
+            PostStatus::Archived => 4,
+            PostStatus::PendingReview => 5,
         }
     }
 }
 
-#[derive(Clone, Serialize, ToSchema)]
+/// 评论状态，被隐藏的评论只有文章作者和管理员可见
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub enum CommentStatus {
+    Approved = 1,
+    Hidden = 2,
+}
+
+impl TryFrom<i32> for CommentStatus {
+    type Error = UnknownCode;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Approved),
+            2 => Ok(Self::Hidden),
+            code => Err(UnknownCode {
+                kind: "comment status",
+                code,
+            }),
+        }
+    }
+}
+
+impl From<CommentStatus> for i32 {
+    fn from(value: CommentStatus) -> Self {
+        match value {
+            CommentStatus::Approved => 1,
+            CommentStatus::Hidden => 2,
+        }
+    }
+}
+
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+pub enum Role {
+    Admin = 1,
+    Editor = 2,
+    Author = 3,
+    #[default]
+    Reader = 4,
+}
+
+impl Role {
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[
+                Permission::UsersRead,
+                Permission::UsersWrite,
+                Permission::PostsWrite,
+                Permission::PostsManage,
+            ],
+            Role::Editor => &[
+                Permission::UsersRead,
+                Permission::PostsWrite,
+                Permission::PostsManage,
+            ],
+            Role::Author => &[Permission::PostsWrite],
+            Role::Reader => &[],
+        }
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl TryFrom<i32> for Role {
+    type Error = UnknownCode;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Admin),
+            2 => Ok(Self::Editor),
+            3 => Ok(Self::Author),
+            4 => Ok(Self::Reader),
+            code => Err(UnknownCode { kind: "role", code }),
+        }
+    }
+}
+
+impl From<Role> for i32 {
+    fn from(value: Role) -> Self {
+        match value {
+            Role::Admin => 1,
+            Role::Editor => 2,
+            Role::Author => 3,
+            Role::Reader => 4,
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "admin" => Ok(Self::Admin),
+            "editor" => Ok(Self::Editor),
+            "author" => Ok(Self::Author),
+            "reader" => Ok(Self::Reader),
+            _ => anyhow::bail!("Unknown role: {}", s),
+        }
+    }
+}
+
+/// 角色拥有的权限，`posts:manage` 允许修改和删除他人的文章
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Permission {
+    UsersRead,
+    UsersWrite,
+    PostsWrite,
+    PostsManage,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::UsersRead => "users:read",
+            Permission::UsersWrite => "users:write",
+            Permission::PostsWrite => "posts:write",
+            Permission::PostsManage => "posts:manage",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 服务层内部使用的用户，包含密码哈希，不能直接序列化返回给客户端，
+/// 接口响应使用 `api::response::user` 中的 `PublicUser` 或 `AdminUser`
+#[derive(Clone)]
 pub struct User {
     pub id: i64,
     pub username: String,
+    /// 用于接收密码重置邮件，比较时不区分大小写
+    pub email: Option<String>,
+    /// argon2 密码哈希
     pub password: String,
     pub status: UserStatus,
+    pub role: Role,
+    /// Incremented on every change, used as the entity tag
+    pub version: i64,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
+    /// 移入回收站的时间，正常用户为空
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone, Serialize, ToSchema)]
@@ -72,6 +261,140 @@ pub struct Post {
     pub title: String,
     pub content: String,
     pub status: PostStatus,
+    /// Time a scheduled post will be published, only set while the post is scheduled
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Time the post was first published, absent if it has never been published
+    pub published_at: Option<DateTime<Utc>>,
+    /// Incremented on every change, used as the entity tag
+    pub version: i64,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
+    /// Category of the post, absent if the post is uncategorized
+    pub category_id: Option<i64>,
+    /// Slugs of the tags attached to the post, in alphabetical order
+    pub tags: Vec<String>,
+    /// Time the post was moved to the trash, absent for live posts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+}
+
+/// 标签及使用该标签的已发布文章数
+#[derive(Clone, Serialize, ToSchema)]
+pub struct TagCount {
+    #[serde(flatten)]
+    pub tag: Tag,
+    /// Number of published posts with this tag
+    pub post_count: i64,
+}
+
+/// 文章分类，`parent_id` 为空的是顶级分类
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Category {
+    pub id: i64,
+    /// Parent category, absent for top level categories
+    pub parent_id: Option<i64>,
+    pub name: String,
+    pub slug: String,
+}
+
+/// 文章某次修改后的完整快照，`revision` 等于修改后文章的 `version`
+#[derive(Clone, Serialize, ToSchema)]
+pub struct PostRevision {
+    pub post_id: i64,
+    pub revision: i64,
+    /// User who made the change, absent if the user has been deleted
+    pub editor_id: Option<i64>,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    pub status: PostStatus,
+    pub created: DateTime<Utc>,
+}
+
+/// 文章的评论，回复通过 `parent_id` 指向被回复的评论
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Comment {
+    pub id: i64,
+    pub post_id: i64,
+    /// Absent when the author has been deleted permanently, show the comment as from a deleted user
+    pub author_id: Option<i64>,
+    /// Comment this one replies to, absent for top level comments
+    pub parent_id: Option<i64>,
+    pub content: String,
+    pub status: CommentStatus,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+/// 登录会话，访问令牌通过 `jti` 关联会话，刷新令牌只保存哈希值
+#[derive(Clone)]
+pub struct Session {
+    pub id: String,
+    pub user_id: i64,
+    pub refresh_token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
+/// 用户的 TOTP 两步验证设置，`secret` 为加密后的密钥
+#[derive(Clone)]
+pub struct TwoFactor {
+    pub user_id: i64,
+    pub secret: String,
+    /// 验证第一个动态码后启用，为空时只是登记了密钥
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// 最近一次使用的时间步，同一时间步的动态码不能重复使用
+    pub last_step: Option<i64>,
+    pub created: DateTime<Utc>,
+}
+
+impl TwoFactor {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LockoutKind {
+    Username,
+    Ip,
+}
+
+impl FromStr for LockoutKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "username" => Ok(Self::Username),
+            "ip" => Ok(Self::Ip),
+            _ => anyhow::bail!("Unknown lockout kind: {}", s),
+        }
+    }
+}
+
+/// 登录失败记录，`locked_until` 在失败次数达到上限后设置
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Lockout {
+    pub kind: LockoutKind,
+    pub value: String,
+    pub failures: u32,
+    pub last_failure: DateTime<Utc>,
+    pub retry_after: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres, Row, Sqlite, sqlite::SqliteRow};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::request::comment::{CreateCommentRequest, UpdateCommentRequest},
+    model::{Comment, CommentStatus},
+    services::error::{ServiceError, ServiceResult},
+};
+
+pub struct InMemoryCommentStore {
+    pub counter: i64,
+    pub items: HashMap<i64, Comment>,
+}
+
+impl InMemoryCommentStore {
+    /// 被回复的评论必须属于同一篇文章且未被隐藏
+    fn check_parent(&self, post_id: i64, parent_id: Option<i64>) -> ServiceResult<()> {
+        match parent_id {
+            Some(id)
+                if !self.items.get(&id).is_some_and(|parent| {
+                    parent.post_id == post_id && parent.status == CommentStatus::Approved
+                }) =>
+            {
+                Err(ServiceError::Validation(format!(
+                    "Parent comment not found: {}",
+                    id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_mut(&mut self, post_id: i64, id: i64) -> ServiceResult<&mut Comment> {
+        self.items
+            .get_mut(&id)
+            .filter(|comment| comment.post_id == post_id)
+            .ok_or_else(|| ServiceError::NotFound(format!("Comment not found: {}", id)))
+    }
+}
+
+pub struct InMemoryCommentService {
+    data: Mutex<InMemoryCommentStore>,
+}
+
+impl Default for InMemoryCommentService {
+    fn default() -> Self {
+        Self {
+            data: Mutex::new(InMemoryCommentStore {
+                counter: 0,
+                items: HashMap::new(),
+            }),
+        }
+    }
+}
+
+pub struct PgSqlCommentService {
+    pub pool: Pool<Postgres>,
+}
+
+impl PgSqlCommentService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    async fn check_parent(&self, post_id: i64, parent_id: Option<i64>) -> ServiceResult<()> {
+        let Some(parent_id) = parent_id else {
+            return Ok(());
+        };
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM comments
+            WHERE id = $1 AND post_id = $2 AND status = $3
+            "#,
+            parent_id,
+            post_id,
+            i32::from(CommentStatus::Approved)
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if count == 0 {
+            return Err(ServiceError::Validation(format!(
+                "Parent comment not found: {}",
+                parent_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub struct SqliteCommentService {
+    pub pool: Pool<Sqlite>,
+}
+
+impl SqliteCommentService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    fn map_row(row: SqliteRow) -> Result<Comment, sqlx::Error> {
+        Ok(Comment {
+            id: row.try_get("id")?,
+            post_id: row.try_get("post_id")?,
+            author_id: row.try_get("author_id")?,
+            parent_id: row.try_get("parent_id")?,
+            content: row.try_get("content")?,
+            status: CommentStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+            updated: row.try_get::<Option<_>, _>("updated")?.unwrap_or_default(),
+        })
+    }
+
+    async fn check_parent(&self, post_id: i64, parent_id: Option<i64>) -> ServiceResult<()> {
+        let Some(parent_id) = parent_id else {
+            return Ok(());
+        };
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM comments WHERE id = $1 AND post_id = $2 AND status = $3",
+        )
+        .bind(parent_id)
+        .bind(post_id)
+        .bind(i32::from(CommentStatus::Approved))
+        .fetch_one(&self.pool)
+        .await?;
+        if count == 0 {
+            return Err(ServiceError::Validation(format!(
+                "Parent comment not found: {}",
+                parent_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 评论只通过所属文章访问，`post_id` 不匹配的评论视为不存在
+#[async_trait]
+pub trait CommentService: Send + Sync {
+    /// 文章的评论，按发表时间排序，`status` 为空时返回全部评论
+    async fn list_comments(
+        &self,
+        post_id: i64,
+        status: Option<CommentStatus>,
+    ) -> ServiceResult<Vec<Comment>>;
+    async fn get_comment(&self, post_id: i64, id: i64) -> ServiceResult<Comment>;
+    /// 被回复的评论必须属于同一篇文章且未被隐藏
+    async fn create_comment(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        req: CreateCommentRequest,
+    ) -> ServiceResult<Comment>;
+    async fn update_comment(
+        &self,
+        post_id: i64,
+        id: i64,
+        req: UpdateCommentRequest,
+    ) -> ServiceResult<Comment>;
+    async fn set_comment_status(
+        &self,
+        post_id: i64,
+        id: i64,
+        status: CommentStatus,
+    ) -> ServiceResult<Comment>;
+    /// 删除评论，它的回复改为回复被删除评论的上一级评论
+    async fn delete_comment(&self, post_id: i64, id: i64) -> ServiceResult<()>;
+    /// 永久删除用户时保留其评论，将作者置空，返回修改的数量
+    async fn clear_comment_author(&self, author_id: i64) -> ServiceResult<u64>;
+}
+
+#[async_trait]
+impl CommentService for InMemoryCommentService {
+    async fn list_comments(
+        &self,
+        post_id: i64,
+        status: Option<CommentStatus>,
+    ) -> ServiceResult<Vec<Comment>> {
+        let data = self.data.lock().await;
+        let mut comments: Vec<Comment> = data
+            .items
+            .values()
+            .filter(|comment| comment.post_id == post_id)
+            .filter(|comment| status.is_none_or(|status| comment.status == status))
+            .cloned()
+            .collect();
+        comments.sort_by_key(|comment| comment.id);
+        Ok(comments)
+    }
+
+    async fn get_comment(&self, post_id: i64, id: i64) -> ServiceResult<Comment> {
+        let data = self.data.lock().await;
+        match data
+            .items
+            .get(&id)
+            .filter(|comment| comment.post_id == post_id)
+        {
+            Some(comment) => Ok(comment.clone()),
+            None => Err(ServiceError::NotFound(format!("Comment not found: {}", id))),
+        }
+    }
+
+    async fn create_comment(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        req: CreateCommentRequest,
+    ) -> ServiceResult<Comment> {
+        let mut data = self.data.lock().await;
+        data.check_parent(post_id, req.parent_id)?;
+        data.counter += 1;
+        let ts = chrono::offset::Utc::now();
+        let comment = Comment {
+            id: data.counter,
+            post_id,
+            author_id: Some(author_id),
+            parent_id: req.parent_id,
+            content: req.content,
+            status: CommentStatus::Approved,
+            created: ts,
+            updated: ts,
+        };
+        data.items.insert(comment.id, comment.clone());
+        Ok(comment)
+    }
+
+    async fn update_comment(
+        &self,
+        post_id: i64,
+        id: i64,
+        req: UpdateCommentRequest,
+    ) -> ServiceResult<Comment> {
+        let mut data = self.data.lock().await;
+        let comment = data.get_mut(post_id, id)?;
+        comment.content = req.content;
+        comment.updated = chrono::offset::Utc::now();
+        Ok(comment.clone())
+    }
+
+    async fn set_comment_status(
+        &self,
+        post_id: i64,
+        id: i64,
+        status: CommentStatus,
+    ) -> ServiceResult<Comment> {
+        let mut data = self.data.lock().await;
+        let comment = data.get_mut(post_id, id)?;
+        comment.status = status;
+        comment.updated = chrono::offset::Utc::now();
+        Ok(comment.clone())
+    }
+
+    async fn delete_comment(&self, post_id: i64, id: i64) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        let parent_id = data.get_mut(post_id, id)?.parent_id;
+        data.items.remove(&id);
+        for comment in data.items.values_mut() {
+            if comment.parent_id == Some(id) {
+                comment.parent_id = parent_id;
+            }
+        }
+        Ok(())
+    }
+
+    async fn clear_comment_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let mut data = self.data.lock().await;
+        let mut count = 0;
+        for comment in data
+            .items
+            .values_mut()
+            .filter(|comment| comment.author_id == Some(author_id))
+        {
+            comment.author_id = None;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl CommentService for PgSqlCommentService {
+    async fn list_comments(
+        &self,
+        post_id: i64,
+        status: Option<CommentStatus>,
+    ) -> ServiceResult<Vec<Comment>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, post_id, author_id, parent_id, content, status, created, updated
+            FROM comments
+            WHERE post_id = $1 AND ($2::int IS NULL OR status = $2)
+            ORDER BY id
+            "#,
+            post_id,
+            status.map(i32::from)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(Comment {
+                    id: row.id,
+                    post_id: row.post_id,
+                    author_id: row.author_id,
+                    parent_id: row.parent_id,
+                    content: row.content,
+                    status: CommentStatus::try_from(row.status)?,
+                    created: row.created.unwrap_or_default(),
+                    updated: row.updated.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    async fn get_comment(&self, post_id: i64, id: i64) -> ServiceResult<Comment> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, post_id, author_id, parent_id, content, status, created, updated
+            FROM comments
+            WHERE id = $1 AND post_id = $2
+            "#,
+            id,
+            post_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServiceError::or_not_found(e, format!("Comment not found: {}", id)))?;
+        Ok(Comment {
+            id: row.id,
+            post_id: row.post_id,
+            author_id: row.author_id,
+            parent_id: row.parent_id,
+            content: row.content,
+            status: CommentStatus::try_from(row.status)?,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+        })
+    }
+
+    async fn create_comment(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        req: CreateCommentRequest,
+    ) -> ServiceResult<Comment> {
+        self.check_parent(post_id, req.parent_id).await?;
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO comments (post_id, author_id, parent_id, content, status)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            post_id,
+            author_id,
+            req.parent_id,
+            req.content,
+            i32::from(CommentStatus::Approved)
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        self.get_comment(post_id, id).await
+    }
+
+    async fn update_comment(
+        &self,
+        post_id: i64,
+        id: i64,
+        req: UpdateCommentRequest,
+    ) -> ServiceResult<Comment> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE comments
+            SET content = $1, updated = NOW()
+            WHERE id = $2 AND post_id = $3
+            "#,
+            req.content,
+            id,
+            post_id
+        );
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Comment not found: {}", id)));
+        }
+        self.get_comment(post_id, id).await
+    }
+
+    async fn set_comment_status(
+        &self,
+        post_id: i64,
+        id: i64,
+        status: CommentStatus,
+    ) -> ServiceResult<Comment> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE comments
+            SET status = $1, updated = NOW()
+            WHERE id = $2 AND post_id = $3
+            "#,
+            i32::from(status),
+            id,
+            post_id
+        );
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Comment not found: {}", id)));
+        }
+        self.get_comment(post_id, id).await
+    }
+
+    async fn delete_comment(&self, post_id: i64, id: i64) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            UPDATE comments
+            SET parent_id = (SELECT parent_id FROM comments WHERE id = $1)
+            WHERE parent_id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let res = sqlx::query!(
+            "DELETE FROM comments WHERE id = $1 AND post_id = $2",
+            id,
+            post_id
+        );
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Comment not found: {}", id)));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn clear_comment_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query!(
+            "UPDATE comments SET author_id = NULL WHERE author_id = $1",
+            author_id
+        );
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+}
+
+#[async_trait]
+impl CommentService for SqliteCommentService {
+    async fn list_comments(
+        &self,
+        post_id: i64,
+        status: Option<CommentStatus>,
+    ) -> ServiceResult<Vec<Comment>> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, post_id, author_id, parent_id, content, status, created, updated
+            FROM comments
+            WHERE post_id = $1 AND ($2 IS NULL OR status = $2)
+            ORDER BY id
+            "#,
+        )
+        .bind(post_id)
+        .bind(status.map(i32::from));
+        Ok(res.try_map(Self::map_row).fetch_all(&self.pool).await?)
+    }
+
+    async fn get_comment(&self, post_id: i64, id: i64) -> ServiceResult<Comment> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, post_id, author_id, parent_id, content, status, created, updated
+            FROM comments
+            WHERE id = $1 AND post_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(post_id);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Comment not found: {}", id)))
+    }
+
+    async fn create_comment(
+        &self,
+        post_id: i64,
+        author_id: i64,
+        req: CreateCommentRequest,
+    ) -> ServiceResult<Comment> {
+        self.check_parent(post_id, req.parent_id).await?;
+        let ts = chrono::offset::Utc::now();
+        let res = sqlx::query(
+            r#"
+            INSERT INTO comments (post_id, author_id, parent_id, content, status, created, updated)
+            VALUES ($1, $2, $3, $4, $5, $6, $6)
+            RETURNING id, post_id, author_id, parent_id, content, status, created, updated
+            "#,
+        )
+        .bind(post_id)
+        .bind(author_id)
+        .bind(req.parent_id)
+        .bind(req.content)
+        .bind(i32::from(CommentStatus::Approved))
+        .bind(ts);
+        Ok(res.try_map(Self::map_row).fetch_one(&self.pool).await?)
+    }
+
+    async fn update_comment(
+        &self,
+        post_id: i64,
+        id: i64,
+        req: UpdateCommentRequest,
+    ) -> ServiceResult<Comment> {
+        let res = sqlx::query(
+            r#"
+            UPDATE comments
+            SET content = $1, updated = $2
+            WHERE id = $3 AND post_id = $4
+            RETURNING id, post_id, author_id, parent_id, content, status, created, updated
+            "#,
+        )
+        .bind(req.content)
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(post_id);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Comment not found: {}", id)))
+    }
+
+    async fn set_comment_status(
+        &self,
+        post_id: i64,
+        id: i64,
+        status: CommentStatus,
+    ) -> ServiceResult<Comment> {
+        let res = sqlx::query(
+            r#"
+            UPDATE comments
+            SET status = $1, updated = $2
+            WHERE id = $3 AND post_id = $4
+            RETURNING id, post_id, author_id, parent_id, content, status, created, updated
+            "#,
+        )
+        .bind(i32::from(status))
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(post_id);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Comment not found: {}", id)))
+    }
+
+    async fn delete_comment(&self, post_id: i64, id: i64) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE comments
+            SET parent_id = (SELECT parent_id FROM comments WHERE id = $1)
+            WHERE parent_id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let res = sqlx::query("DELETE FROM comments WHERE id = $1 AND post_id = $2")
+            .bind(id)
+            .bind(post_id);
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Comment not found: {}", id)));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+    async fn clear_comment_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query("UPDATE comments SET author_id = NULL WHERE author_id = $1")
+            .bind(author_id);
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+}
@@ -0,0 +1,101 @@
+use sqlx::error::DatabaseError;
+use thiserror::Error;
+
+use crate::{model::UnknownCode, utils::password::PolicyViolation};
+
+/// 业务层错误，HTTP 层根据类型选择状态码
+#[derive(Debug, Error)]
+pub enum ServiceError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+pub type ServiceResult<T> = Result<T, ServiceError>;
+
+/// 唯一索引与冲突说明，Postgres 按索引名匹配，SQLite 按 `表.列` 匹配，
+/// 表达式索引在 SQLite 的错误信息中只有索引名
+const UNIQUE_CONSTRAINTS: &[(&str, &str, &str)] = &[
+    (
+        "idx_users_username",
+        "users.username",
+        "Username already exists",
+    ),
+    (
+        "idx_users_email",
+        "index 'idx_users_email'",
+        "Email already exists",
+    ),
+    ("idx_posts_slug", "posts.slug", "Slug already exists"),
+    ("idx_tags_slug", "tags.slug", "Tag slug already exists"),
+    (
+        "idx_categories_slug",
+        "categories.slug",
+        "Category slug already exists",
+    ),
+];
+
+impl ServiceError {
+    /// 查询不到记录时返回带说明的 `NotFound`，其他错误按默认规则转换
+    pub fn or_not_found(err: sqlx::Error, message: impl Into<String>) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => ServiceError::NotFound(message.into()),
+            err => err.into(),
+        }
+    }
+}
+
+impl From<UnknownCode> for ServiceError {
+    fn from(err: UnknownCode) -> Self {
+        ServiceError::Internal(err.into())
+    }
+}
+
+impl From<PolicyViolation> for ServiceError {
+    fn from(err: PolicyViolation) -> Self {
+        ServiceError::Validation(err.to_string())
+    }
+}
+
+/// 读取记录时遇到无法识别的枚举值按解码错误处理
+impl From<UnknownCode> for sqlx::Error {
+    fn from(err: UnknownCode) -> Self {
+        sqlx::Error::Decode(Box::new(err))
+    }
+}
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::RowNotFound => ServiceError::NotFound("Record not found".to_string()),
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                ServiceError::Conflict(unique_violation(db.as_ref()))
+            }
+            sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+                ServiceError::Conflict("Record is still referenced by other records".to_string())
+            }
+            _ => ServiceError::Internal(err.into()),
+        }
+    }
+}
+
+fn unique_violation(err: &dyn DatabaseError) -> String {
+    UNIQUE_CONSTRAINTS
+        .iter()
+        .find(|(index, column, _)| {
+            err.constraint() == Some(*index) || err.message().contains(*column)
+        })
+        .map(|(_, _, message)| message.to_string())
+        .unwrap_or_else(|| "Record already exists".to_string())
+}
@@ -0,0 +1,247 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+
+use crate::{
+    settings::{Mail, MailTransport, Smtp, SmtpTls},
+    utils::token,
+};
+
+/// 待发送的纯文本邮件
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> anyhow::Result<()>;
+}
+
+/// 根据配置选择邮件发送方式
+pub fn from_settings(settings: &Mail) -> anyhow::Result<Arc<dyn Mailer>> {
+    let from: Mailbox = settings
+        .from
+        .parse()
+        .with_context(|| format!("Invalid mail sender:{}", settings.from))?;
+    Ok(match settings.transport {
+        MailTransport::Smtp => Arc::new(SmtpMailer::new(from, &settings.smtp)?),
+        MailTransport::File => Arc::new(FileMailer::new(from, &settings.directory)),
+        MailTransport::Log => Arc::new(LogMailer),
+    })
+}
+
+fn build_message(from: &Mailbox, email: Email) -> anyhow::Result<Message> {
+    let to: Mailbox = email
+        .to
+        .parse()
+        .with_context(|| format!("Invalid mail recipient:{}", email.to))?;
+    Ok(Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body)?)
+}
+
+/// 通过 SMTP 服务器发送邮件
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(from: Mailbox, settings: &Smtp) -> anyhow::Result<Self> {
+        let mut builder = match settings.tls {
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?,
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+        };
+        if let Some(port) = settings.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(Self {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(message)
+            .await
+            .context("Failed to send mail")?;
+        Ok(())
+    }
+}
+
+/// 把每封邮件写成目录中的一个 `.eml` 文件
+pub struct FileMailer {
+    from: Mailbox,
+    directory: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: Mailbox, directory: impl Into<PathBuf>) -> Self {
+        Self {
+            from,
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let message = build_message(&self.from, email)?;
+        tokio::fs::create_dir_all(&self.directory).await?;
+        //时间戳便于按发送顺序查看，随机后缀避免同一毫秒内的文件名冲突
+        let path = self.directory.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%d%H%M%S%3f"),
+            token::generate_token(4)
+        ));
+        tokio::fs::write(&path, message.formatted())
+            .await
+            .with_context(|| format!("Failed to write mail to {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// 只记录日志，不发送邮件
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        tracing::info!(to = %email.to, subject = %email.subject, "{}", email.body);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn email() -> Email {
+        Email {
+            to: "alice@example.com".to_string(),
+            subject: "Reset your password".to_string(),
+            body: "token".to_string(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("cli_app-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn written(directory: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(directory)
+            .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn file_mailer_writes_one_eml_per_mail() {
+        let directory = temp_dir("file-mailer");
+        let mailer = FileMailer::new("noreply@example.com".parse().unwrap(), &directory);
+        mailer.send(email()).await.unwrap();
+        mailer.send(email()).await.unwrap();
+
+        let files = written(&directory);
+        assert_eq!(files.len(), 2);
+        for path in &files {
+            assert_eq!(path.extension().unwrap(), "eml");
+            let content = std::fs::read_to_string(path).unwrap();
+            assert!(content.contains("From: noreply@example.com"), "{}", content);
+            assert!(content.contains("To: alice@example.com"), "{}", content);
+            assert!(
+                content.contains("Subject: Reset your password"),
+                "{}",
+                content
+            );
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_mailer_rejects_invalid_recipient() {
+        let directory = temp_dir("invalid-recipient");
+        let mailer = FileMailer::new("noreply@example.com".parse().unwrap(), &directory);
+        let email = Email {
+            to: "not an address".to_string(),
+            ..email()
+        };
+        assert!(mailer.send(email).await.is_err());
+        assert!(written(&directory).is_empty());
+    }
+
+    #[tokio::test]
+    async fn transport_follows_settings() {
+        let directory = temp_dir("transport");
+        let mut settings = Mail {
+            directory: directory.to_string_lossy().into_owned(),
+            ..Mail::default()
+        };
+
+        //log 只写日志，不产生文件
+        settings.transport = MailTransport::Log;
+        from_settings(&settings)
+            .unwrap()
+            .send(email())
+            .await
+            .unwrap();
+        assert!(written(&directory).is_empty());
+
+        settings.transport = MailTransport::File;
+        from_settings(&settings)
+            .unwrap()
+            .send(email())
+            .await
+            .unwrap();
+        assert_eq!(written(&directory).len(), 1);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        //创建 SMTP 传输时不连接服务器
+        settings.transport = MailTransport::Smtp;
+        settings.smtp.host = "smtp.example.com".to_string();
+        for tls in [SmtpTls::Starttls, SmtpTls::Tls, SmtpTls::None] {
+            settings.smtp.tls = tls;
+            assert!(from_settings(&settings).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_sender_is_rejected() {
+        let settings = Mail {
+            from: "not an address".to_string(),
+            ..Mail::default()
+        };
+        assert!(from_settings(&settings).is_err());
+    }
+}
@@ -1,2 +1,11 @@
+pub mod comment;
+pub mod error;
+pub mod mail;
+pub mod pagination;
+pub mod password_reset;
 pub mod post;
+pub mod search;
+pub mod session;
+pub mod throttle;
+pub mod two_factor;
 pub mod user;
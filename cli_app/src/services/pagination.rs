@@ -0,0 +1,382 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use sqlx::{Database, Encode, QueryBuilder, Type};
+
+use crate::model::{Post, User};
+
+pub const DEFAULT_LIMIT: i64 = 20;
+pub const MAX_LIMIT: i64 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortField {
+    Id,
+    Created,
+    Updated,
+}
+
+impl SortField {
+    fn column(&self) -> &'static str {
+        match self {
+            SortField::Id => "id",
+            SortField::Created => "created",
+            SortField::Updated => "updated",
+        }
+    }
+}
+
+impl FromStr for SortField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(SortField::Id),
+            "created" => Ok(SortField::Created),
+            "updated" => Ok(SortField::Updated),
+            _ => anyhow::bail!("Unknown sort field: {}", s),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+/// 排序规则，例如 `created,-updated`，`-` 前缀表示降序。
+/// 末尾总是以 `id` 作为唯一的排序键，保证游标分页的顺序稳定。
+#[derive(Clone, Debug)]
+pub struct Sort(Vec<SortKey>);
+
+impl Sort {
+    pub fn keys(&self) -> &[SortKey] {
+        &self.0
+    }
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort(vec![SortKey {
+            field: SortField::Id,
+            descending: false,
+        }])
+    }
+}
+
+impl FromStr for Sort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys: Vec<SortKey> = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (descending, name) = match part.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, part.strip_prefix('+').unwrap_or(part)),
+            };
+            let field = name.parse::<SortField>()?;
+            if keys.iter().any(|k| k.field == field) {
+                anyhow::bail!("Duplicate sort field: {}", name);
+            }
+            keys.push(SortKey { field, descending });
+        }
+        if !keys.iter().any(|k| k.field == SortField::Id) {
+            keys.push(SortKey {
+                field: SortField::Id,
+                descending: false,
+            });
+        }
+        Ok(Sort(keys))
+    }
+}
+
+/// 游标记录上一页最后一行在每个排序键上的取值，时间以纳秒表示
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor(Vec<i64>);
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    pub fn decode(value: &str) -> anyhow::Result<Self> {
+        let values = value
+            .split('_')
+            .map(|v| v.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow::anyhow!("Invalid cursor: {}", value))?;
+        Ok(Cursor(values))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PageStart {
+    Offset(i64),
+    After(Cursor),
+}
+
+#[derive(Clone, Debug)]
+pub struct PageRequest {
+    pub limit: i64,
+    pub start: PageStart,
+    pub sort: Sort,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_LIMIT,
+            start: PageStart::Offset(0),
+            sort: Sort::default(),
+        }
+    }
+}
+
+impl PageRequest {
+    pub fn new(
+        limit: Option<i64>,
+        offset: Option<i64>,
+        cursor: Option<&str>,
+        sort: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            anyhow::bail!("limit must be between 1 and {}", MAX_LIMIT);
+        }
+        let sort = match sort {
+            Some(sort) => sort.parse::<Sort>()?,
+            None => Sort::default(),
+        };
+        let start = match (cursor, offset) {
+            (Some(_), Some(_)) => anyhow::bail!("cursor and offset cannot be used together"),
+            (Some(cursor), None) => {
+                let cursor = Cursor::decode(cursor)?;
+                if cursor.0.len() != sort.keys().len() {
+                    anyhow::bail!("Cursor does not match the requested sort order");
+                }
+                PageStart::After(cursor)
+            }
+            (None, Some(offset)) if offset < 0 => anyhow::bail!("offset must not be negative"),
+            (None, offset) => PageStart::Offset(offset.unwrap_or(0)),
+        };
+        Ok(Self { limit, start, sort })
+    }
+
+    fn cursor_for<T: Sortable>(&self, item: &T) -> Cursor {
+        Cursor(
+            self.sort
+                .keys()
+                .iter()
+                .map(|k| item.sort_value(k.field))
+                .collect(),
+        )
+    }
+
+    fn compare<T: Sortable>(&self, a: &[i64], b: &T) -> Ordering {
+        for (i, key) in self.sort.keys().iter().enumerate() {
+            let ord = a[i].cmp(&b.sort_value(key.field));
+            let ord = if key.descending { ord.reverse() } else { ord };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// 在内存中对已过滤的数据排序并分页
+    pub fn paginate<T: Sortable>(&self, mut items: Vec<T>) -> Page<T> {
+        let total = items.len() as i64;
+        items.sort_by(|a, b| self.compare(&self.cursor_for(a).0, b));
+        let rows: Vec<T> = match &self.start {
+            PageStart::Offset(offset) => items
+                .into_iter()
+                .skip(*offset as usize)
+                .take(self.limit as usize + 1)
+                .collect(),
+            PageStart::After(cursor) => items
+                .into_iter()
+                .filter(|item| self.compare(&cursor.0, item) == Ordering::Less)
+                .take(self.limit as usize + 1)
+                .collect(),
+        };
+        self.page(rows, total)
+    }
+
+    /// 追加游标条件、排序和分页语句，需在所有 WHERE 条件之后调用。
+    /// 查询会多取一行用于判断是否存在下一页。
+    pub fn push_sql<'a, DB>(&self, builder: &mut QueryBuilder<'a, DB>)
+    where
+        DB: Database,
+        i64: Encode<'a, DB> + Type<DB>,
+        DateTime<Utc>: Encode<'a, DB> + Type<DB>,
+    {
+        if let PageStart::After(cursor) = &self.start {
+            let keys = self.sort.keys();
+            builder.push(" AND (");
+            for i in 0..keys.len() {
+                if i > 0 {
+                    builder.push(" OR ");
+                }
+                builder.push("(");
+                for (j, key) in keys.iter().enumerate().take(i) {
+                    builder.push(key.field.column()).push(" = ");
+                    push_value(builder, key.field, cursor.0[j]);
+                    builder.push(" AND ");
+                }
+                let key = keys[i];
+                builder
+                    .push(key.field.column())
+                    .push(if key.descending { " < " } else { " > " });
+                push_value(builder, key.field, cursor.0[i]);
+                builder.push(")");
+            }
+            builder.push(")");
+        }
+
+        builder.push(" ORDER BY ");
+        let mut order = builder.separated(", ");
+        for key in self.sort.keys() {
+            order.push(format!(
+                "{} {}",
+                key.field.column(),
+                if key.descending { "DESC" } else { "ASC" }
+            ));
+        }
+
+        builder.push(" LIMIT ").push_bind(self.limit + 1);
+        if let PageStart::Offset(offset) = self.start {
+            builder.push(" OFFSET ").push_bind(offset);
+        }
+    }
+
+    /// 根据多取的一行截断结果，并生成下一页的游标
+    pub fn page<T: Sortable>(&self, mut rows: Vec<T>, total: i64) -> Page<T> {
+        let next_cursor = if rows.len() as i64 > self.limit {
+            rows.truncate(self.limit as usize);
+            rows.last().map(|item| self.cursor_for(item).encode())
+        } else {
+            None
+        };
+        Page {
+            items: rows,
+            total,
+            next_cursor,
+        }
+    }
+}
+
+fn push_value<'a, DB>(builder: &mut QueryBuilder<'a, DB>, field: SortField, value: i64)
+where
+    DB: Database,
+    i64: Encode<'a, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'a, DB> + Type<DB>,
+{
+    match field {
+        SortField::Id => builder.push_bind(value),
+        SortField::Created | SortField::Updated => {
+            builder.push_bind(DateTime::<Utc>::from_timestamp_nanos(value))
+        }
+    };
+}
+
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+pub trait Sortable {
+    fn sort_value(&self, field: SortField) -> i64;
+}
+
+impl Sortable for Post {
+    fn sort_value(&self, field: SortField) -> i64 {
+        match field {
+            SortField::Id => self.id,
+            SortField::Created => self.created.timestamp_nanos_opt().unwrap_or_default(),
+            SortField::Updated => self.updated.timestamp_nanos_opt().unwrap_or_default(),
+        }
+    }
+}
+
+impl Sortable for User {
+    fn sort_value(&self, field: SortField) -> i64 {
+        match field {
+            SortField::Id => self.id,
+            SortField::Created => self.created.timestamp_nanos_opt().unwrap_or_default(),
+            SortField::Updated => self.updated.timestamp_nanos_opt().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row {
+        id: i64,
+        created: i64,
+    }
+
+    impl Sortable for Row {
+        fn sort_value(&self, field: SortField) -> i64 {
+            match field {
+                SortField::Id => self.id,
+                SortField::Created | SortField::Updated => self.created,
+            }
+        }
+    }
+
+    fn rows() -> Vec<Row> {
+        [(1, 30), (2, 10), (3, 30), (4, 20), (5, 10)]
+            .into_iter()
+            .map(|(id, created)| Row { id, created })
+            .collect()
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor(vec![1_700_000_000_000_000_000, -5, 42]);
+        let encoded = cursor.encode();
+        assert_eq!(encoded, "1700000000000000000_-5_42");
+        assert_eq!(Cursor::decode(&encoded).unwrap(), cursor);
+    }
+
+    #[test]
+    fn tampered_cursor_is_rejected() {
+        for value in ["", "1_", "_1", "1__2", "1_x", "1.5", "99999999999999999999"] {
+            assert!(Cursor::decode(value).is_err(), "accepted {:?}", value);
+        }
+        //游标的取值个数必须和排序键一致
+        let error = PageRequest::new(None, None, Some("10_3"), None).unwrap_err();
+        assert!(error.to_string().contains("does not match"), "{}", error);
+        let error = PageRequest::new(None, None, Some("3"), Some("-created")).unwrap_err();
+        assert!(error.to_string().contains("does not match"), "{}", error);
+        assert!(PageRequest::new(None, Some(0), Some("3"), None).is_err());
+    }
+
+    #[test]
+    fn next_cursor_continues_after_the_last_row() {
+        let ids = |page: &Page<Row>| page.items.iter().map(|r| r.id).collect::<Vec<_>>();
+        let request = PageRequest::new(Some(2), None, None, Some("-created")).unwrap();
+        let page = request.paginate(rows());
+        assert_eq!(ids(&page), [1, 3]);
+        assert_eq!(page.total, 5);
+
+        let cursor = page.next_cursor.unwrap();
+        assert_eq!(cursor, "30_3");
+        let request = PageRequest::new(Some(2), None, Some(&cursor), Some("-created")).unwrap();
+        let page = request.paginate(rows());
+        assert_eq!(ids(&page), [4, 2]);
+
+        let cursor = page.next_cursor.unwrap();
+        let request = PageRequest::new(Some(2), None, Some(&cursor), Some("-created")).unwrap();
+        let page = request.paginate(rows());
+        assert_eq!(ids(&page), [5]);
+        assert!(page.next_cursor.is_none());
+    }
+}
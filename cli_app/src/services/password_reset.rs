@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres, Sqlite};
+use tokio::sync::Mutex;
+
+use crate::services::error::ServiceResult;
+
+/// 内存中的重置令牌，键为令牌哈希
+struct ResetToken {
+    user_id: i64,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+}
+
+pub struct InMemoryPasswordResetService {
+    data: Mutex<HashMap<String, ResetToken>>,
+}
+
+impl Default for InMemoryPasswordResetService {
+    fn default() -> Self {
+        InMemoryPasswordResetService {
+            data: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+pub struct PgSqlPasswordResetService {
+    pub pool: Pool<Postgres>,
+}
+
+impl PgSqlPasswordResetService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+pub struct SqlitePasswordResetService {
+    pub pool: Pool<Sqlite>,
+}
+
+impl SqlitePasswordResetService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+pub trait PasswordResetService: Send + Sync {
+    /// 保存新的重置令牌哈希，同一用户之前未使用的令牌随之作废
+    async fn create_reset(
+        &self,
+        user_id: i64,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<()>;
+    /// 将令牌标记为已使用并返回所属用户，令牌不存在、已使用或已过期时返回 `None`
+    async fn consume_reset(&self, token_hash: &str) -> ServiceResult<Option<i64>>;
+}
+
+#[async_trait]
+impl PasswordResetService for InMemoryPasswordResetService {
+    async fn create_reset(
+        &self,
+        user_id: i64,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        data.retain(|_, reset| reset.user_id != user_id || reset.used_at.is_some());
+        data.insert(
+            token_hash.to_string(),
+            ResetToken {
+                user_id,
+                expires_at,
+                used_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn consume_reset(&self, token_hash: &str) -> ServiceResult<Option<i64>> {
+        let mut data = self.data.lock().await;
+        let ts = Utc::now();
+        match data
+            .get_mut(token_hash)
+            .filter(|reset| reset.used_at.is_none() && reset.expires_at > ts)
+        {
+            None => Ok(None),
+            Some(reset) => {
+                reset.used_at = Some(ts);
+                Ok(Some(reset.user_id))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PasswordResetService for PgSqlPasswordResetService {
+    async fn create_reset(
+        &self,
+        user_id: i64,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+                delete from password_resets
+                where user_id = $1 and used_at is null
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+                insert into password_resets(token_hash,user_id,expires_at,created)
+                values($1,$2,$3,Now())
+            "#,
+            token_hash,
+            user_id,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn consume_reset(&self, token_hash: &str) -> ServiceResult<Option<i64>> {
+        //条件更新保证并发请求中只有一个能使用同一令牌
+        let res = sqlx::query!(
+            r#"
+                update password_resets
+                set used_at = Now()
+                where token_hash = $1 and used_at is null and expires_at > Now()
+                returning user_id
+            "#,
+            token_hash
+        );
+        Ok(res.fetch_optional(&self.pool).await?.map(|row| row.user_id))
+    }
+}
+
+#[async_trait]
+impl PasswordResetService for SqlitePasswordResetService {
+    async fn create_reset(
+        &self,
+        user_id: i64,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                delete from password_resets
+                where user_id = $1 and used_at is null
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+                insert into password_resets(token_hash,user_id,expires_at,created)
+                values($1,$2,$3,$4)
+            "#,
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(expires_at)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn consume_reset(&self, token_hash: &str) -> ServiceResult<Option<i64>> {
+        //条件更新保证并发请求中只有一个能使用同一令牌
+        let res = sqlx::query_scalar(
+            r#"
+                update password_resets
+                set used_at = $1
+                where token_hash = $2 and used_at is null and expires_at > $1
+                returning user_id
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(token_hash);
+        Ok(res.fetch_optional(&self.pool).await?)
+    }
+}
@@ -1,16 +1,378 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-use sqlx::{Pool, Postgres};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    Database, Encode, Pool, Postgres, QueryBuilder, Row, Sqlite, Transaction, Type,
+    postgres::PgRow, sqlite::SqliteRow,
+};
 use tokio::sync::Mutex;
 
 use crate::{
-    api::request::post::{CreatePostRequest, UpdatePostRequest},
-    model::{Post, PostStatus},
+    api::request::{
+        category::{CreateCategoryRequest, UpdateCategoryRequest},
+        post::{CreatePostRequest, PatchPostRequest, UpdatePostRequest},
+        tag::{CreateTagRequest, UpdateTagRequest},
+    },
+    model::{Category, Post, PostRevision, PostStatus, Tag, TagCount},
+    services::{
+        error::{ServiceError, ServiceResult},
+        pagination::{Page, PageRequest},
+        search::{self, InvertedIndex, SearchHit, SearchQuery, SearchResults},
+    },
 };
 
+/// 读者能看到的文章范围
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    /// 匿名读者只能看到已发布的文章
+    #[default]
+    Published,
+    /// 登录用户还能看到自己的全部文章
+    Author(i64),
+    /// 管理员可以看到所有文章
+    All,
+}
+
+impl Visibility {
+    fn allows(self, post: &Post) -> bool {
+        match self {
+            Visibility::Published => post.status == PostStatus::Published,
+            Visibility::Author(id) => post.status == PostStatus::Published || post.author_id == id,
+            Visibility::All => true,
+        }
+    }
+
+    /// 单篇查询的绑定参数 `(status, author_id)`：`status` 为空时不限制，
+    /// 否则只返回该状态或作者为 `author_id` 的文章
+    fn bind_values(self) -> (Option<i32>, Option<i64>) {
+        let published = Some(i32::from(PostStatus::Published));
+        match self {
+            Visibility::Published => (published, None),
+            Visibility::Author(id) => (published, Some(id)),
+            Visibility::All => (None, None),
+        }
+    }
+}
+
+/// 文章列表的过滤条件，`created_after` 包含边界，`created_before` 不包含
+#[derive(Clone, Default)]
+pub struct PostFilter {
+    /// 为 `true` 时只返回回收站中的文章，否则只返回未删除的文章
+    pub trashed: bool,
+    /// 默认只返回已发布的文章
+    pub visibility: Visibility,
+    pub status: Option<PostStatus>,
+    pub author_id: Option<i64>,
+    /// 标签 slug
+    pub tag: Option<String>,
+    /// 分类 slug，同时匹配其所有子分类
+    pub category: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl PostFilter {
+    /// `categories` 为按 `category` 展开后的分类 ID 集合
+    fn matches(&self, post: &Post, categories: Option<&HashSet<i64>>) -> bool {
+        post.deleted_at.is_some() == self.trashed
+            && self.visibility.allows(post)
+            && self.status.is_none_or(|status| post.status == status)
+            && self
+                .author_id
+                .is_none_or(|author_id| post.author_id == author_id)
+            && self.tag.as_ref().is_none_or(|tag| post.tags.contains(tag))
+            && categories.is_none_or(|ids| post.category_id.is_some_and(|id| ids.contains(&id)))
+            && self.created_after.is_none_or(|ts| post.created >= ts)
+            && self.created_before.is_none_or(|ts| post.created < ts)
+    }
+
+    fn push_sql<'a, DB>(&self, builder: &mut QueryBuilder<'a, DB>)
+    where
+        DB: Database,
+        i32: Encode<'a, DB> + Type<DB>,
+        i64: Encode<'a, DB> + Type<DB>,
+        String: Encode<'a, DB> + Type<DB>,
+        DateTime<Utc>: Encode<'a, DB> + Type<DB>,
+    {
+        builder.push(if self.trashed {
+            " AND deleted_at IS NOT NULL"
+        } else {
+            " AND deleted_at IS NULL"
+        });
+        match self.visibility {
+            Visibility::Published => {
+                builder
+                    .push(" AND status = ")
+                    .push_bind(i32::from(PostStatus::Published));
+            }
+            Visibility::Author(author_id) => {
+                builder
+                    .push(" AND (status = ")
+                    .push_bind(i32::from(PostStatus::Published))
+                    .push(" OR author_id = ")
+                    .push_bind(author_id)
+                    .push(")");
+            }
+            Visibility::All => {}
+        }
+        if let Some(status) = self.status {
+            builder.push(" AND status = ").push_bind(i32::from(status));
+        }
+        if let Some(author_id) = self.author_id {
+            builder.push(" AND author_id = ").push_bind(author_id);
+        }
+        if let Some(tag) = &self.tag {
+            builder
+                .push(
+                    " AND id IN (SELECT pt.post_id FROM post_tags pt \
+                     JOIN tags t ON t.id = pt.tag_id WHERE t.slug = ",
+                )
+                .push_bind(tag.clone())
+                .push(")");
+        }
+        if let Some(category) = &self.category {
+            builder
+                .push(
+                    " AND category_id IN (WITH RECURSIVE subtree(id) AS \
+                     (SELECT id FROM categories WHERE slug = ",
+                )
+                .push_bind(category.clone())
+                .push(
+                    " UNION ALL SELECT c.id FROM categories c \
+                     JOIN subtree s ON c.parent_id = s.id) SELECT id FROM subtree)",
+                );
+        }
+        if let Some(ts) = self.created_after {
+            builder.push(" AND created >= ").push_bind(ts);
+        }
+        if let Some(ts) = self.created_before {
+            builder.push(" AND created < ").push_bind(ts);
+        }
+    }
+}
+
+const SELECT_POSTS: &str = r#"
+            SELECT id, author_id, title, slug, content, status, publish_at, published_at,
+                version, created, updated, deleted_at, category_id
+            FROM posts
+            WHERE 1 = 1"#;
+const COUNT_POSTS: &str = "SELECT COUNT(*) FROM posts WHERE 1 = 1";
+
+const PG_SEARCH_POSTS: &str = r#"
+            SELECT id, author_id, title, slug, content, status, publish_at, published_at,
+                version, created, updated, deleted_at, category_id,
+                ts_rank(search_vector, query) AS rank,
+                ts_headline('simple', content, query,
+                    'StartSel=<mark>, StopSel=</mark>, MaxWords=32, MinWords=16') AS snippet
+            FROM posts, to_tsquery('simple', "#;
+const PG_COUNT_SEARCH: &str = r#"
+            SELECT COUNT(*)
+            FROM posts, to_tsquery('simple', "#;
+
+const SQLITE_SEARCH_POSTS: &str = r#"
+            SELECT posts.id, author_id, posts.title, slug, posts.content, status, publish_at,
+                published_at, version, created, updated, deleted_at, category_id,
+                -bm25(posts_fts, 2.0, 1.0) AS rank,
+                snippet(posts_fts, 1, '<mark>', '</mark>', '…', 32) AS snippet
+            FROM posts_fts JOIN posts ON posts.id = posts_fts.rowid
+            WHERE posts_fts MATCH "#;
+const SELECT_POST_TAGS: &str = r#"
+            SELECT pt.post_id, t.slug
+            FROM post_tags pt JOIN tags t ON t.id = pt.tag_id
+            WHERE pt.post_id IN ("#;
+
+const SQLITE_COUNT_SEARCH: &str = r#"
+            SELECT COUNT(*)
+            FROM posts_fts JOIN posts ON posts.id = posts_fts.rowid
+            WHERE posts_fts MATCH "#;
+
+/// 去重并排序标签 slug
+fn tag_slugs(tags: &[String]) -> BTreeSet<&str> {
+    tags.iter().map(String::as_str).collect()
+}
+
+/// 请求中有不存在的标签时返回 `Validation`
+fn check_unknown_tags<'a>(
+    requested: &BTreeSet<&str>,
+    found: impl IntoIterator<Item = &'a str>,
+) -> ServiceResult<()> {
+    let found: HashSet<&str> = found.into_iter().collect();
+    let unknown: Vec<&str> = requested
+        .iter()
+        .copied()
+        .filter(|slug| !found.contains(slug))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(ServiceError::Validation(format!(
+            "Unknown tags: {}",
+            unknown.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// 把分类 `id` 移动到 `parent_id` 下是否会形成环，即新的父分类是它自己或它的子分类
+fn creates_cycle(categories: &[Category], id: i64, parent_id: Option<i64>) -> bool {
+    let parents: HashMap<i64, Option<i64>> = categories
+        .iter()
+        .map(|category| (category.id, category.parent_id))
+        .collect();
+    let mut current = parent_id;
+    while let Some(ancestor) = current {
+        if ancestor == id {
+            return true;
+        }
+        current = parents.get(&ancestor).copied().flatten();
+    }
+    false
+}
+
+/// 文章的发布状态及相关时间
+#[derive(Clone, Copy)]
+struct Lifecycle {
+    status: PostStatus,
+    publish_at: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+}
+
+impl Lifecycle {
+    /// 新建的文章视为从草稿变更而来
+    const NEW: Self = Self {
+        status: PostStatus::Draft,
+        publish_at: None,
+        published_at: None,
+    };
+
+    fn of(post: &Post) -> Self {
+        Self {
+            status: post.status,
+            publish_at: post.publish_at,
+            published_at: post.published_at,
+        }
+    }
+
+    /// 校验状态变更并返回变更后的状态
+    ///
+    /// 定时发布必须给出将来的 `publish_at`（未修改的原定时间除外），其他状态不能设置 `publish_at`，
+    /// 首次发布时记录发布时间
+    fn transition(
+        self,
+        status: PostStatus,
+        publish_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<Self> {
+        if !self.status.can_transition_to(status) {
+            return Err(ServiceError::Validation(format!(
+                "Cannot change post status from {:?} to {:?}",
+                self.status, status
+            )));
+        }
+        let publish_at = match (status, publish_at) {
+            (PostStatus::Scheduled, Some(ts)) if ts > now || Some(ts) == self.publish_at => {
+                Some(ts)
+            }
+            (PostStatus::Scheduled, _) => {
+                return Err(ServiceError::Validation(
+                    "Scheduled posts need a `publish_at` in the future".to_string(),
+                ));
+            }
+            (_, Some(_)) => {
+                return Err(ServiceError::Validation(
+                    "`publish_at` can only be set for scheduled posts".to_string(),
+                ));
+            }
+            (_, None) => None,
+        };
+        let published_at = match status {
+            PostStatus::Published => self.published_at.or(Some(now)),
+            _ => self.published_at,
+        };
+        Ok(Self {
+            status,
+            publish_at,
+            published_at,
+        })
+    }
+
+    /// 部分更新，未给出的状态保持不变，仍为定时发布时沿用原定时间
+    fn patch(
+        self,
+        status: Option<PostStatus>,
+        publish_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<Self> {
+        let status = status.unwrap_or(self.status);
+        let publish_at = publish_at.or(self.publish_at.filter(|_| status == PostStatus::Scheduled));
+        self.transition(status, publish_at, now)
+    }
+}
+
 pub struct InMemoryPostStore {
     pub counter: i64,
     pub items: HashMap<i64, Post>,
+    pub index: InvertedIndex,
+    pub revisions: HashMap<i64, Vec<PostRevision>>,
+    pub tag_counter: i64,
+    pub tags: HashMap<i64, Tag>,
+    pub category_counter: i64,
+    pub categories: HashMap<i64, Category>,
+}
+
+impl InMemoryPostStore {
+    /// 校验标签都已存在，返回排序后的 slug
+    fn resolve_tags(&self, tags: &[String]) -> ServiceResult<Vec<String>> {
+        let requested = tag_slugs(tags);
+        check_unknown_tags(&requested, self.tags.values().map(|tag| tag.slug.as_str()))?;
+        Ok(requested.into_iter().map(str::to_string).collect())
+    }
+
+    fn check_category(&self, id: Option<i64>) -> ServiceResult<()> {
+        match id {
+            Some(id) if !self.categories.contains_key(&id) => Err(ServiceError::Validation(
+                format!("Category not found: {}", id),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// slug 对应的分类及其所有子分类的 ID
+    fn category_subtree(&self, slug: &str) -> HashSet<i64> {
+        let mut ids = HashSet::new();
+        let mut pending: Vec<i64> = self
+            .categories
+            .values()
+            .filter(|category| category.slug == slug)
+            .map(|category| category.id)
+            .collect();
+        while let Some(id) = pending.pop() {
+            if ids.insert(id) {
+                pending.extend(
+                    self.categories
+                        .values()
+                        .filter(|category| category.parent_id == Some(id))
+                        .map(|category| category.id),
+                );
+            }
+        }
+        ids
+    }
+
+    fn record_revision(&mut self, post: &Post, editor_id: Option<i64>) {
+        self.revisions
+            .entry(post.id)
+            .or_default()
+            .push(PostRevision {
+                post_id: post.id,
+                revision: post.version,
+                editor_id,
+                title: post.title.clone(),
+                slug: post.slug.clone(),
+                content: post.content.clone(),
+                status: post.status,
+                created: post.updated,
+            });
+    }
 }
 
 pub struct InMemoryPostService {
@@ -23,6 +385,12 @@ impl Default for InMemoryPostService {
             data: Mutex::new(InMemoryPostStore {
                 counter: 0,
                 items: HashMap::new(),
+                index: InvertedIndex::default(),
+                revisions: HashMap::new(),
+                tag_counter: 0,
+                tags: HashMap::new(),
+                category_counter: 0,
+                categories: HashMap::new(),
             }),
         }
     }
@@ -36,218 +404,2447 @@ impl PgSqlPostService {
     pub fn new(pool: Pool<Postgres>) -> Self {
         Self { pool }
     }
+
+    /// 在同一事务中保存文章当前内容的快照
+    async fn record_revision(
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        editor_id: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO post_revisions (post_id, revision, editor_id, title, slug, content, status, created)
+            SELECT id, version, $2, title, slug, content, status, updated
+            FROM posts
+            WHERE id = $1
+            "#,
+            id,
+            editor_id
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// 批量加载文章的标签
+    async fn attach_tags<'p>(
+        &self,
+        posts: impl IntoIterator<Item = &'p mut Post>,
+    ) -> Result<(), sqlx::Error> {
+        let mut posts: HashMap<i64, &mut Post> =
+            posts.into_iter().map(|post| (post.id, post)).collect();
+        if posts.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<Postgres>::new(SELECT_POST_TAGS);
+        let mut ids = query.separated(", ");
+        for id in posts.keys() {
+            ids.push_bind(*id);
+        }
+        query.push(") ORDER BY t.slug");
+        let rows: Vec<(i64, String)> = query.build_query_as().fetch_all(&self.pool).await?;
+        for (post_id, slug) in rows {
+            if let Some(post) = posts.get_mut(&post_id) {
+                post.tags.push(slug);
+            }
+        }
+        Ok(())
+    }
+
+    /// 用给定 slug 的标签替换文章的全部标签
+    async fn replace_tags(
+        tx: &mut Transaction<'_, Postgres>,
+        post_id: i64,
+        tags: &[String],
+    ) -> ServiceResult<()> {
+        sqlx::query!("DELETE FROM post_tags WHERE post_id = $1", post_id)
+            .execute(&mut **tx)
+            .await?;
+        let requested = tag_slugs(tags);
+        if requested.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id, slug FROM tags WHERE slug IN (");
+        let mut slugs = query.separated(", ");
+        for slug in &requested {
+            slugs.push_bind(slug.to_string());
+        }
+        query.push(")");
+        let found: Vec<(i64, String)> = query.build_query_as().fetch_all(&mut **tx).await?;
+        check_unknown_tags(&requested, found.iter().map(|(_, slug)| slug.as_str()))?;
+
+        let mut insert = QueryBuilder::<Postgres>::new("INSERT INTO post_tags (post_id, tag_id) ");
+        insert.push_values(&found, |mut row, (tag_id, _)| {
+            row.push_bind(post_id).push_bind(*tag_id);
+        });
+        insert.build().execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    /// 锁定文章并读取发布状态，给出 `version` 时先校验版本
+    async fn lock_lifecycle(
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        version: Option<i64>,
+    ) -> ServiceResult<Lifecycle> {
+        let row = sqlx::query!(
+            r#"
+            SELECT status, publish_at, published_at, version
+            FROM posts
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| ServiceError::or_not_found(e, format!("Post not found: {}", id)))?;
+        if version.is_some_and(|version| version != row.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
+        }
+        Ok(Lifecycle {
+            status: PostStatus::try_from(row.status)?,
+            publish_at: row.publish_at,
+            published_at: row.published_at,
+        })
+    }
+
+    async fn check_category(tx: &mut Transaction<'_, Postgres>, id: i64) -> ServiceResult<()> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM categories WHERE id = $1"#,
+            id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        if count == 0 {
+            return Err(ServiceError::Validation(format!(
+                "Category not found: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    fn map_row(row: PgRow) -> Result<Post, sqlx::Error> {
+        Ok(Post {
+            id: row.try_get("id")?,
+            author_id: row.try_get("author_id")?,
+            title: row.try_get("title")?,
+            slug: row.try_get("slug")?,
+            content: row.try_get("content")?,
+            status: PostStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            publish_at: row.try_get("publish_at")?,
+            published_at: row.try_get("published_at")?,
+            version: row.try_get("version")?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+            updated: row.try_get::<Option<_>, _>("updated")?.unwrap_or_default(),
+            category_id: row.try_get("category_id")?,
+            tags: Vec::new(),
+            deleted_at: row.try_get("deleted_at")?,
+        })
+    }
+
+    /// 条件写入没有更新任何行时，区分记录不存在和版本不一致
+    async fn write_failed(&self, id: i64) -> ServiceError {
+        match self.get_post_by_id(id, Visibility::All).await {
+            Ok(_) => ServiceError::PreconditionFailed(format!("Post has been modified: {}", id)),
+            Err(err) => err,
+        }
+    }
+}
+
+pub struct SqlitePostService {
+    pub pool: Pool<Sqlite>,
+}
+
+impl SqlitePostService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// 在同一事务中保存文章当前内容的快照
+    async fn record_revision(
+        tx: &mut Transaction<'_, Sqlite>,
+        id: i64,
+        editor_id: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO post_revisions (post_id, revision, editor_id, title, slug, content, status, created)
+            SELECT id, version, $2, title, slug, content, status, updated
+            FROM posts
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(editor_id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// 批量加载文章的标签
+    async fn attach_tags<'p>(
+        &self,
+        posts: impl IntoIterator<Item = &'p mut Post>,
+    ) -> Result<(), sqlx::Error> {
+        let mut posts: HashMap<i64, &mut Post> =
+            posts.into_iter().map(|post| (post.id, post)).collect();
+        if posts.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<Sqlite>::new(SELECT_POST_TAGS);
+        let mut ids = query.separated(", ");
+        for id in posts.keys() {
+            ids.push_bind(*id);
+        }
+        query.push(") ORDER BY t.slug");
+        let rows: Vec<(i64, String)> = query.build_query_as().fetch_all(&self.pool).await?;
+        for (post_id, slug) in rows {
+            if let Some(post) = posts.get_mut(&post_id) {
+                post.tags.push(slug);
+            }
+        }
+        Ok(())
+    }
+
+    /// 用给定 slug 的标签替换文章的全部标签
+    async fn replace_tags(
+        tx: &mut Transaction<'_, Sqlite>,
+        post_id: i64,
+        tags: &[String],
+    ) -> ServiceResult<()> {
+        sqlx::query("DELETE FROM post_tags WHERE post_id = $1")
+            .bind(post_id)
+            .execute(&mut **tx)
+            .await?;
+        let requested = tag_slugs(tags);
+        if requested.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT id, slug FROM tags WHERE slug IN (");
+        let mut slugs = query.separated(", ");
+        for slug in &requested {
+            slugs.push_bind(slug.to_string());
+        }
+        query.push(")");
+        let found: Vec<(i64, String)> = query.build_query_as().fetch_all(&mut **tx).await?;
+        check_unknown_tags(&requested, found.iter().map(|(_, slug)| slug.as_str()))?;
+
+        let mut insert = QueryBuilder::<Sqlite>::new("INSERT INTO post_tags (post_id, tag_id) ");
+        insert.push_values(&found, |mut row, (tag_id, _)| {
+            row.push_bind(post_id).push_bind(*tag_id);
+        });
+        insert.build().execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    /// 读取文章的发布状态，给出 `version` 时先校验版本
+    async fn read_lifecycle(
+        tx: &mut Transaction<'_, Sqlite>,
+        id: i64,
+        version: Option<i64>,
+    ) -> ServiceResult<Lifecycle> {
+        let row = sqlx::query(
+            r#"
+            SELECT status, publish_at, published_at, version
+            FROM posts
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| ServiceError::or_not_found(e, format!("Post not found: {}", id)))?;
+        if version.is_some_and(|version| version != row.get::<i64, _>("version")) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
+        }
+        Ok(Lifecycle {
+            status: PostStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            publish_at: row.try_get("publish_at")?,
+            published_at: row.try_get("published_at")?,
+        })
+    }
+
+    async fn check_category(tx: &mut Transaction<'_, Sqlite>, id: i64) -> ServiceResult<()> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE id = $1")
+            .bind(id)
+            .fetch_one(&mut **tx)
+            .await?;
+        if count == 0 {
+            return Err(ServiceError::Validation(format!(
+                "Category not found: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    fn map_tag_row(row: SqliteRow) -> Result<Tag, sqlx::Error> {
+        Ok(Tag {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            slug: row.try_get("slug")?,
+        })
+    }
+
+    fn map_category_row(row: SqliteRow) -> Result<Category, sqlx::Error> {
+        Ok(Category {
+            id: row.try_get("id")?,
+            parent_id: row.try_get("parent_id")?,
+            name: row.try_get("name")?,
+            slug: row.try_get("slug")?,
+        })
+    }
+
+    fn map_revision_row(row: SqliteRow) -> Result<PostRevision, sqlx::Error> {
+        Ok(PostRevision {
+            post_id: row.try_get("post_id")?,
+            revision: row.try_get("revision")?,
+            editor_id: row.try_get("editor_id")?,
+            title: row.try_get("title")?,
+            slug: row.try_get("slug")?,
+            content: row.try_get("content")?,
+            status: PostStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+        })
+    }
+
+    fn map_row(row: SqliteRow) -> Result<Post, sqlx::Error> {
+        Ok(Post {
+            id: row.try_get("id")?,
+            author_id: row.try_get("author_id")?,
+            title: row.try_get("title")?,
+            slug: row.try_get("slug")?,
+            content: row.try_get("content")?,
+            status: PostStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            publish_at: row.try_get("publish_at")?,
+            published_at: row.try_get("published_at")?,
+            version: row.try_get("version")?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+            updated: row.try_get::<Option<_>, _>("updated")?.unwrap_or_default(),
+            category_id: row.try_get("category_id")?,
+            tags: Vec::new(),
+            deleted_at: row.try_get("deleted_at")?,
+        })
+    }
+
+    /// 条件写入没有更新任何行时，区分记录不存在和版本不一致
+    async fn write_failed(&self, id: i64) -> ServiceError {
+        match self.get_post_by_id(id, Visibility::All).await {
+            Ok(_) => ServiceError::PreconditionFailed(format!("Post has been modified: {}", id)),
+            Err(err) => err,
+        }
+    }
 }
 
-#[allow(async_fn_in_trait)]
-pub trait PostService {
-    async fn get_all_posts(&self) -> anyhow::Result<Vec<Post>>;
-    async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post>;
-    async fn get_post_by_slug(&self, name: &str) -> anyhow::Result<Post>;
-    async fn create_post(&self, req: CreatePostRequest) -> anyhow::Result<Post>;
-    async fn update_post(&self, id: i64, req: UpdatePostRequest) -> anyhow::Result<Post>;
-    async fn delete_post(&self, id: i64) -> anyhow::Result<()>;
+#[async_trait]
+pub trait PostService: Send + Sync {
+    async fn list_posts(&self, filter: PostFilter, page: PageRequest) -> ServiceResult<Page<Post>>;
+    /// 超出 `visibility` 范围的文章与不存在的文章一样返回 `NotFound`
+    async fn get_post_by_id(&self, id: i64, visibility: Visibility) -> ServiceResult<Post>;
+    async fn get_post_by_slug(&self, name: &str, visibility: Visibility) -> ServiceResult<Post>;
+    /// 全文检索标题和正文，结果按相关度从高到低排列
+    async fn search_posts(
+        &self,
+        query: &SearchQuery,
+        filter: PostFilter,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<SearchResults>;
+    async fn create_post(&self, author_id: i64, req: CreatePostRequest) -> ServiceResult<Post>;
+    /// 写操作的 `version` 为客户端期望的版本，给出且与当前版本不一致时返回 `PreconditionFailed`
+    /// 每次写入都会以 `editor_id` 为修改人保存一个历史版本
+    async fn update_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: UpdatePostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post>;
+    /// 只更新请求中给出的字段
+    async fn patch_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: PatchPostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post>;
+    /// 将文章移入回收站，回收站中的文章不会出现在列表、检索和 `get_*` 查询中
+    async fn delete_post(&self, id: i64, version: Option<i64>) -> ServiceResult<()>;
+    /// 从回收站恢复文章
+    async fn restore_post(&self, id: i64) -> ServiceResult<Post>;
+    /// 永久删除回收站中的文章及其历史版本
+    async fn purge_post(&self, id: i64) -> ServiceResult<()>;
+    /// 作者名下的文章数量，包括回收站中的文章
+    async fn count_posts_by_author(&self, author_id: i64) -> ServiceResult<i64>;
+    /// 将作者名下未删除的文章移入回收站，返回移入的数量
+    async fn trash_posts_by_author(&self, author_id: i64) -> ServiceResult<u64>;
+    /// 永久删除作者名下的所有文章，包括回收站中的文章
+    async fn purge_posts_by_author(&self, author_id: i64) -> ServiceResult<u64>;
+    /// 将作者名下的所有文章转给另一个用户
+    async fn reassign_posts(&self, from_author_id: i64, to_author_id: i64) -> ServiceResult<u64>;
+    /// 发布 `publish_at` 不晚于 `now` 的定时文章，发布时间记为原定时间，返回发布的数量
+    async fn publish_due_posts(&self, now: DateTime<Utc>) -> ServiceResult<u64>;
+    /// 所有标签及其已发布文章数，按 slug 排序
+    async fn list_tags(&self) -> ServiceResult<Vec<TagCount>>;
+    async fn get_tag(&self, id: i64) -> ServiceResult<Tag>;
+    async fn create_tag(&self, req: CreateTagRequest) -> ServiceResult<Tag>;
+    async fn update_tag(&self, id: i64, req: UpdateTagRequest) -> ServiceResult<Tag>;
+    /// 删除标签，同时从所有文章上移除
+    async fn delete_tag(&self, id: i64) -> ServiceResult<()>;
+    /// 用给定 slug 的标签替换文章的全部标签，标签必须已存在
+    async fn set_post_tags(
+        &self,
+        id: i64,
+        tags: Vec<String>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post>;
+    async fn list_categories(&self) -> ServiceResult<Vec<Category>>;
+    async fn get_category(&self, id: i64) -> ServiceResult<Category>;
+    async fn create_category(&self, req: CreateCategoryRequest) -> ServiceResult<Category>;
+    /// 可以移动到其他父分类下，但不能移动到自己或自己的子分类下
+    async fn update_category(&self, id: i64, req: UpdateCategoryRequest)
+    -> ServiceResult<Category>;
+    /// 有子分类的分类不能删除，其下的文章变为未分类
+    async fn delete_category(&self, id: i64) -> ServiceResult<()>;
+    async fn set_post_category(
+        &self,
+        id: i64,
+        category_id: Option<i64>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post>;
+    /// 文章的历史版本，最新的在前
+    async fn list_revisions(&self, id: i64) -> ServiceResult<Vec<PostRevision>>;
+    async fn get_revision(&self, id: i64, revision: i64) -> ServiceResult<PostRevision>;
 }
 
+#[async_trait]
 impl PostService for InMemoryPostService {
-    async fn get_all_posts(&self) -> anyhow::Result<Vec<Post>> {
+    async fn list_posts(&self, filter: PostFilter, page: PageRequest) -> ServiceResult<Page<Post>> {
         let data = self.data.lock().await;
-        Ok(data.items.values().map(|post| (*post).clone()).collect())
+        let categories = filter
+            .category
+            .as_deref()
+            .map(|slug| data.category_subtree(slug));
+        let posts = data
+            .items
+            .values()
+            .filter(|post| filter.matches(post, categories.as_ref()))
+            .cloned()
+            .collect();
+        Ok(page.paginate(posts))
     }
 
-    async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post> {
+    async fn get_post_by_id(&self, id: i64, visibility: Visibility) -> ServiceResult<Post> {
         let data = self.data.lock().await;
-        match data.items.get(&id) {
+        match data
+            .items
+            .get(&id)
+            .filter(|post| post.deleted_at.is_none() && visibility.allows(post))
+        {
             Some(post) => Ok(post.clone()),
-            None => Err(anyhow::anyhow!("Post not found: {}", id)),
+            None => Err(ServiceError::NotFound(format!("Post not found: {}", id))),
         }
     }
 
-    async fn get_post_by_slug(&self, name: &str) -> anyhow::Result<Post> {
+    async fn get_post_by_slug(&self, name: &str, visibility: Visibility) -> ServiceResult<Post> {
         let data = self.data.lock().await;
         for (_id, post) in data.items.iter() {
-            if post.slug == name {
+            if post.slug == name && post.deleted_at.is_none() && visibility.allows(post) {
                 return Ok(post.clone());
             }
         }
-        anyhow::bail!("Post not found: {}", name);
+        Err(ServiceError::NotFound(format!("Post not found: {}", name)))
+    }
+
+    async fn search_posts(
+        &self,
+        query: &SearchQuery,
+        filter: PostFilter,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<SearchResults> {
+        let data = self.data.lock().await;
+        let categories = filter
+            .category
+            .as_deref()
+            .map(|slug| data.category_subtree(slug));
+        let mut hits: Vec<SearchHit> = data
+            .index
+            .search(query)
+            .into_iter()
+            .filter_map(|(id, rank)| data.items.get(&id).map(|post| (post, rank)))
+            .filter(|(post, _)| filter.matches(post, categories.as_ref()))
+            .map(|(post, rank)| SearchHit {
+                post: post.clone(),
+                rank,
+                snippet: String::new(),
+            })
+            .collect();
+        let total = hits.len() as i64;
+        hits.sort_by(|a, b| b.rank.total_cmp(&a.rank).then(a.post.id.cmp(&b.post.id)));
+        let mut hits: Vec<SearchHit> = hits
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        for hit in &mut hits {
+            hit.snippet = search::highlight(&hit.post.content, query);
+        }
+        Ok(SearchResults { hits, total })
     }
 
-    async fn create_post(&self, req: CreatePostRequest) -> anyhow::Result<Post> {
+    async fn create_post(&self, author_id: i64, req: CreatePostRequest) -> ServiceResult<Post> {
         let mut data = self.data.lock().await;
-        data.counter += 1;
+        if data.items.values().any(|post| post.slug == req.slug) {
+            return Err(ServiceError::Conflict(format!(
+                "Slug already exists: {}",
+                req.slug
+            )));
+        }
+        data.check_category(req.category_id)?;
+        let tags = data.resolve_tags(&req.tags)?;
         let ts = chrono::offset::Utc::now();
+        let lifecycle = Lifecycle::NEW.transition(req.status, req.publish_at, ts)?;
+        data.counter += 1;
         let post = Post {
             id: data.counter,
-            author_id: req.author_id,
+            author_id,
             title: req.title,
             slug: req.slug,
             content: req.content,
-            status: req.status,
+            status: lifecycle.status,
+            publish_at: lifecycle.publish_at,
+            published_at: lifecycle.published_at,
+            version: 1,
             created: ts,
             updated: ts,
+            category_id: req.category_id,
+            tags,
+            deleted_at: None,
         };
+        data.index.insert(&post);
+        data.record_revision(&post, Some(author_id));
         data.items.insert(post.id, post);
 
         match data.items.get(&data.counter) {
             None => {
-                anyhow::bail!("Post not found: {}", data.counter);
+                return Err(ServiceError::NotFound(format!(
+                    "Post not found: {}",
+                    data.counter
+                )));
             }
             Some(post) => Ok(post.clone()),
         }
     }
 
-    async fn update_post(&self, id: i64, req: UpdatePostRequest) -> anyhow::Result<Post> {
+    async fn update_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: UpdatePostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
         let mut data = self.data.lock().await;
-        let post = data.items.get_mut(&id).unwrap();
+        if data
+            .items
+            .values()
+            .any(|post| post.id != id && post.slug == req.slug)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Slug already exists: {}",
+                req.slug
+            )));
+        }
+        let Some(post) = data
+            .items
+            .get_mut(&id)
+            .filter(|post| post.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        };
+        if version.is_some_and(|version| version != post.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
+        }
+        let ts = chrono::offset::Utc::now();
+        let lifecycle = Lifecycle::of(post).transition(req.status, req.publish_at, ts)?;
         post.slug = req.slug;
         post.title = req.title;
         post.content = req.content;
-        post.status = req.status;
+        post.status = lifecycle.status;
+        post.publish_at = lifecycle.publish_at;
+        post.published_at = lifecycle.published_at;
+        post.version += 1;
+        post.updated = ts;
 
-        match data.items.get(&data.counter) {
-            None => {
-                anyhow::bail!("Post not found: {}", data.counter);
-            }
-            Some(post) => Ok(post.clone()),
+        let post = post.clone();
+        data.index.insert(&post);
+        data.record_revision(&post, Some(editor_id));
+        Ok(post)
+    }
+
+    async fn patch_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: PatchPostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut data = self.data.lock().await;
+        if let Some(slug) = &req.slug
+            && data
+                .items
+                .values()
+                .any(|post| post.id != id && &post.slug == slug)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Slug already exists: {}",
+                slug
+            )));
+        }
+        let Some(post) = data
+            .items
+            .get_mut(&id)
+            .filter(|post| post.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        };
+        if version.is_some_and(|version| version != post.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
+        }
+        let ts = chrono::offset::Utc::now();
+        let lifecycle = Lifecycle::of(post).patch(req.status, req.publish_at, ts)?;
+        if let Some(slug) = req.slug {
+            post.slug = slug;
+        }
+        if let Some(title) = req.title {
+            post.title = title;
         }
+        if let Some(content) = req.content {
+            post.content = content;
+        }
+        post.status = lifecycle.status;
+        post.publish_at = lifecycle.publish_at;
+        post.published_at = lifecycle.published_at;
+        post.version += 1;
+        post.updated = ts;
+
+        let post = post.clone();
+        data.index.insert(&post);
+        data.record_revision(&post, Some(editor_id));
+        Ok(post)
     }
 
-    async fn delete_post(&self, id: i64) -> anyhow::Result<()> {
+    async fn delete_post(&self, id: i64, version: Option<i64>) -> ServiceResult<()> {
         let mut data = self.data.lock().await;
-        match data.items.remove(&id) {
-            Some(_) => Ok(()),
-            None => Err(anyhow::anyhow!("Post not found: {}", id)),
+        let Some(post) = data
+            .items
+            .get_mut(&id)
+            .filter(|post| post.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        };
+        if version.is_some_and(|version| version != post.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
         }
+        let ts = chrono::offset::Utc::now();
+        post.deleted_at = Some(ts);
+        post.version += 1;
+        post.updated = ts;
+        Ok(())
     }
-}
 
-impl PostService for PgSqlPostService {
-    async fn get_all_posts(&self) -> anyhow::Result<Vec<Post>> {
-        let res = sqlx::query!(
-            r#"
-            SELECT id, author_id, title, slug, content, status, created, updated
-            FROM posts
-            "#,
-        )
-        .fetch_all(&self.pool)
-        .await?;
-        let list = res
-            .into_iter()
-            .map(|row| Post {
-                id: row.id,
-                author_id: row.author_id,
-                title: row.title,
-                slug: row.slug,
-                content: row.content,
-                status: PostStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-            })
-            .collect();
+    async fn restore_post(&self, id: i64) -> ServiceResult<Post> {
+        let mut data = self.data.lock().await;
+        let Some(post) = data
+            .items
+            .get_mut(&id)
+            .filter(|post| post.deleted_at.is_some())
+        else {
+            return Err(ServiceError::NotFound(format!(
+                "Post not found in trash: {}",
+                id
+            )));
+        };
+        post.deleted_at = None;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
+        Ok(post.clone())
+    }
 
-        Ok(list)
+    async fn purge_post(&self, id: i64) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        if data
+            .items
+            .get(&id)
+            .is_none_or(|post| post.deleted_at.is_none())
+        {
+            return Err(ServiceError::NotFound(format!(
+                "Post not found in trash: {}",
+                id
+            )));
+        }
+        data.items.remove(&id);
+        data.index.remove(id);
+        data.revisions.remove(&id);
+        Ok(())
     }
 
-    async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post> {
-        let res = sqlx::query!(
-            r#"
-            SELECT id, author_id,title, slug, content, status, created, updated
-            FROM posts
-            WHERE id = $1
-            "#,
-            id
-        );
-        res.fetch_one(&self.pool)
-            .await
-            .map(|row| Post {
-                id: row.id,
-                author_id: row.author_id,
-                title: row.title,
-                slug: row.slug,
-                content: row.content,
-                status: PostStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-            })
-            .map_err(|e| anyhow::anyhow!(e).context(format!("Post not found: {}", id)))
+    async fn count_posts_by_author(&self, author_id: i64) -> ServiceResult<i64> {
+        let data = self.data.lock().await;
+        Ok(data
+            .items
+            .values()
+            .filter(|post| post.author_id == author_id)
+            .count() as i64)
     }
 
-    async fn get_post_by_slug(&self, name: &str) -> anyhow::Result<Post> {
-        let res = sqlx::query!(
-            r#"
-            SELECT id, author_id,title, slug, content, status, created, updated
-            FROM posts
-            WHERE slug = $1
-            "#,
-            name
-        );
-        res.fetch_one(&self.pool)
-            .await
-            .map(|row| Post {
-                id: row.id,
-                author_id: row.author_id,
-                title: row.title,
-                slug: row.slug,
-                content: row.content,
-                status: PostStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-            })
-            .map_err(|e| anyhow::anyhow!(e).context(format!("Post not found: {}", name)))
+    async fn trash_posts_by_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let mut data = self.data.lock().await;
+        let ts = chrono::offset::Utc::now();
+        let mut count = 0;
+        for post in data
+            .items
+            .values_mut()
+            .filter(|post| post.author_id == author_id && post.deleted_at.is_none())
+        {
+            post.deleted_at = Some(ts);
+            post.version += 1;
+            post.updated = ts;
+            count += 1;
+        }
+        Ok(count)
     }
 
-    async fn create_post(&self, req: CreatePostRequest) -> anyhow::Result<Post> {
-        let res = sqlx::query!(
-            r#"
-            INSERT INTO posts (author_id, title, slug, content, status, created, updated)
-            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())   
-            RETURNING id
-            "#,
-            req.author_id,
-            req.title,
-            req.slug,
-            req.content,
-            i32::from(req.status),
-        );
-        let res = res.fetch_one(&self.pool).await?;
-        let id = res.id;
-        self.get_post_by_id(id).await
-    }
-
-    async fn update_post(&self, id: i64, req: UpdatePostRequest) -> anyhow::Result<Post> {
-        match self.get_post_by_id(id).await {
-            Ok(_post) => {
-                let res = sqlx::query!(
-                    r#"
-                    UPDATE posts
-                    SET author_id = $1, title = $2, slug = $3, content = $4, status = $5, updated = NOW()
-                    WHERE id = $6
-                    "#,
-                    req.author_id,
-                    req.title,
-                    req.slug,
-                    req.content,
-                    i32::from(req.status),
-                    id
-                );
-                res.execute(&self.pool).await?;
-                self.get_post_by_id(id).await
-            }
-            Err(_) => {
-                anyhow::bail!("Post not found: {}", id);
-            }
+    async fn purge_posts_by_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let mut data = self.data.lock().await;
+        let ids: Vec<i64> = data
+            .items
+            .values()
+            .filter(|post| post.author_id == author_id)
+            .map(|post| post.id)
+            .collect();
+        for id in &ids {
+            data.items.remove(id);
+            data.index.remove(*id);
+            data.revisions.remove(id);
         }
+        Ok(ids.len() as u64)
     }
 
-    async fn delete_post(&self, id: i64) -> anyhow::Result<()> {
-        let res = sqlx::query!(
-            r#"
-            DELETE FROM posts
-            WHERE id = $1
-            "#,
+    async fn reassign_posts(&self, from_author_id: i64, to_author_id: i64) -> ServiceResult<u64> {
+        let mut data = self.data.lock().await;
+        let ts = chrono::offset::Utc::now();
+        let mut count = 0;
+        for post in data
+            .items
+            .values_mut()
+            .filter(|post| post.author_id == from_author_id)
+        {
+            post.author_id = to_author_id;
+            post.version += 1;
+            post.updated = ts;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn publish_due_posts(&self, now: DateTime<Utc>) -> ServiceResult<u64> {
+        let mut data = self.data.lock().await;
+        let mut published = Vec::new();
+        for post in data.items.values_mut().filter(|post| {
+            post.deleted_at.is_none()
+                && post.status == PostStatus::Scheduled
+                && post.publish_at.is_some_and(|ts| ts <= now)
+        }) {
+            post.status = PostStatus::Published;
+            post.published_at = post.published_at.or(post.publish_at);
+            post.publish_at = None;
+            post.version += 1;
+            post.updated = now;
+            published.push(post.clone());
+        }
+        for post in &published {
+            data.record_revision(post, None);
+        }
+        Ok(published.len() as u64)
+    }
+
+    async fn list_revisions(&self, id: i64) -> ServiceResult<Vec<PostRevision>> {
+        let data = self.data.lock().await;
+        if data
+            .items
+            .get(&id)
+            .is_none_or(|post| post.deleted_at.is_some())
+        {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        }
+        let mut revisions = data.revisions.get(&id).cloned().unwrap_or_default();
+        revisions.reverse();
+        Ok(revisions)
+    }
+
+    async fn get_revision(&self, id: i64, revision: i64) -> ServiceResult<PostRevision> {
+        let data = self.data.lock().await;
+        if data
+            .items
+            .get(&id)
+            .is_none_or(|post| post.deleted_at.is_some())
+        {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        }
+        data.revisions
+            .get(&id)
+            .and_then(|revisions| revisions.iter().find(|r| r.revision == revision))
+            .cloned()
+            .ok_or_else(|| {
+                ServiceError::NotFound(format!("Revision {} of post {} not found", revision, id))
+            })
+    }
+    async fn list_tags(&self) -> ServiceResult<Vec<TagCount>> {
+        let data = self.data.lock().await;
+        let mut tags: Vec<TagCount> = data
+            .tags
+            .values()
+            .map(|tag| TagCount {
+                tag: tag.clone(),
+                post_count: data
+                    .items
+                    .values()
+                    .filter(|post| {
+                        post.deleted_at.is_none()
+                            && post.status == PostStatus::Published
+                            && post.tags.contains(&tag.slug)
+                    })
+                    .count() as i64,
+            })
+            .collect();
+        tags.sort_by(|a, b| a.tag.slug.cmp(&b.tag.slug));
+        Ok(tags)
+    }
+
+    async fn get_tag(&self, id: i64) -> ServiceResult<Tag> {
+        let data = self.data.lock().await;
+        match data.tags.get(&id) {
+            Some(tag) => Ok(tag.clone()),
+            None => Err(ServiceError::NotFound(format!("Tag not found: {}", id))),
+        }
+    }
+
+    async fn create_tag(&self, req: CreateTagRequest) -> ServiceResult<Tag> {
+        let mut data = self.data.lock().await;
+        if data.tags.values().any(|tag| tag.slug == req.slug) {
+            return Err(ServiceError::Conflict(format!(
+                "Tag slug already exists: {}",
+                req.slug
+            )));
+        }
+        data.tag_counter += 1;
+        let tag = Tag {
+            id: data.tag_counter,
+            name: req.name,
+            slug: req.slug,
+        };
+        data.tags.insert(tag.id, tag.clone());
+        Ok(tag)
+    }
+
+    async fn update_tag(&self, id: i64, req: UpdateTagRequest) -> ServiceResult<Tag> {
+        let mut data = self.data.lock().await;
+        if data
+            .tags
+            .values()
+            .any(|tag| tag.id != id && tag.slug == req.slug)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Tag slug already exists: {}",
+                req.slug
+            )));
+        }
+        let Some(tag) = data.tags.get_mut(&id) else {
+            return Err(ServiceError::NotFound(format!("Tag not found: {}", id)));
+        };
+        let old_slug = std::mem::replace(&mut tag.slug, req.slug);
+        tag.name = req.name;
+        let tag = tag.clone();
+        //文章上保存的是标签 slug，需要同步修改
+        for post in data.items.values_mut() {
+            if let Some(slug) = post.tags.iter_mut().find(|slug| **slug == old_slug) {
+                *slug = tag.slug.clone();
+                post.tags.sort();
+            }
+        }
+        Ok(tag)
+    }
+
+    async fn delete_tag(&self, id: i64) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        let Some(tag) = data.tags.remove(&id) else {
+            return Err(ServiceError::NotFound(format!("Tag not found: {}", id)));
+        };
+        for post in data.items.values_mut() {
+            post.tags.retain(|slug| *slug != tag.slug);
+        }
+        Ok(())
+    }
+
+    async fn set_post_tags(
+        &self,
+        id: i64,
+        tags: Vec<String>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut data = self.data.lock().await;
+        let tags = data.resolve_tags(&tags)?;
+        let Some(post) = data
+            .items
+            .get_mut(&id)
+            .filter(|post| post.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        };
+        if version.is_some_and(|version| version != post.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
+        }
+        post.tags = tags;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
+        Ok(post.clone())
+    }
+
+    async fn list_categories(&self) -> ServiceResult<Vec<Category>> {
+        let data = self.data.lock().await;
+        let mut categories: Vec<Category> = data.categories.values().cloned().collect();
+        categories.sort_by_key(|category| category.id);
+        Ok(categories)
+    }
+
+    async fn get_category(&self, id: i64) -> ServiceResult<Category> {
+        let data = self.data.lock().await;
+        match data.categories.get(&id) {
+            Some(category) => Ok(category.clone()),
+            None => Err(ServiceError::NotFound(format!(
+                "Category not found: {}",
+                id
+            ))),
+        }
+    }
+
+    async fn create_category(&self, req: CreateCategoryRequest) -> ServiceResult<Category> {
+        let mut data = self.data.lock().await;
+        if data
+            .categories
+            .values()
+            .any(|category| category.slug == req.slug)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Category slug already exists: {}",
+                req.slug
+            )));
+        }
+        data.check_category(req.parent_id)?;
+        data.category_counter += 1;
+        let category = Category {
+            id: data.category_counter,
+            parent_id: req.parent_id,
+            name: req.name,
+            slug: req.slug,
+        };
+        data.categories.insert(category.id, category.clone());
+        Ok(category)
+    }
+
+    async fn update_category(
+        &self,
+        id: i64,
+        req: UpdateCategoryRequest,
+    ) -> ServiceResult<Category> {
+        let mut data = self.data.lock().await;
+        if data
+            .categories
+            .values()
+            .any(|category| category.id != id && category.slug == req.slug)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Category slug already exists: {}",
+                req.slug
+            )));
+        }
+        data.check_category(req.parent_id)?;
+        let categories: Vec<Category> = data.categories.values().cloned().collect();
+        if creates_cycle(&categories, id, req.parent_id) {
+            return Err(ServiceError::Validation(
+                "A category cannot be moved under itself or one of its subcategories".to_string(),
+            ));
+        }
+        let Some(category) = data.categories.get_mut(&id) else {
+            return Err(ServiceError::NotFound(format!(
+                "Category not found: {}",
+                id
+            )));
+        };
+        category.parent_id = req.parent_id;
+        category.name = req.name;
+        category.slug = req.slug;
+        Ok(category.clone())
+    }
+
+    async fn delete_category(&self, id: i64) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        if !data.categories.contains_key(&id) {
+            return Err(ServiceError::NotFound(format!(
+                "Category not found: {}",
+                id
+            )));
+        }
+        if data
+            .categories
+            .values()
+            .any(|category| category.parent_id == Some(id))
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Category has subcategories: {}",
+                id
+            )));
+        }
+        data.categories.remove(&id);
+        for post in data.items.values_mut() {
+            if post.category_id == Some(id) {
+                post.category_id = None;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_post_category(
+        &self,
+        id: i64,
+        category_id: Option<i64>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut data = self.data.lock().await;
+        data.check_category(category_id)?;
+        let Some(post) = data
+            .items
+            .get_mut(&id)
+            .filter(|post| post.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("Post not found: {}", id)));
+        };
+        if version.is_some_and(|version| version != post.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "Post has been modified: {}",
+                id
+            )));
+        }
+        post.category_id = category_id;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
+        Ok(post.clone())
+    }
+}
+
+#[async_trait]
+impl PostService for PgSqlPostService {
+    async fn list_posts(&self, filter: PostFilter, page: PageRequest) -> ServiceResult<Page<Post>> {
+        let mut count = QueryBuilder::<Postgres>::new(COUNT_POSTS);
+        filter.push_sql(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::<Postgres>::new(SELECT_POSTS);
+        filter.push_sql(&mut query);
+        page.push_sql(&mut query);
+        let mut rows = query
+            .build()
+            .try_map(Self::map_row)
+            .fetch_all(&self.pool)
+            .await?;
+        self.attach_tags(rows.iter_mut()).await?;
+
+        Ok(page.page(rows, total))
+    }
+
+    async fn get_post_by_id(&self, id: i64, visibility: Visibility) -> ServiceResult<Post> {
+        let (status, author_id) = visibility.bind_values();
+        let res = sqlx::query!(
+            r#"
+            SELECT id, author_id, title, slug, content, status, publish_at, published_at,
+                version, created, updated, deleted_at, category_id
+            FROM posts
+            WHERE id = $1 AND deleted_at IS NULL
+                AND ($2::int IS NULL OR status = $2 OR author_id = $3)
+            "#,
+            id,
+            status,
+            author_id
+        );
+        let row = res
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Post not found: {}", id)))?;
+        let mut post = Post {
+            id: row.id,
+            author_id: row.author_id,
+            title: row.title,
+            slug: row.slug,
+            content: row.content,
+            status: PostStatus::try_from(row.status)?,
+            publish_at: row.publish_at,
+            published_at: row.published_at,
+            version: row.version,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            category_id: row.category_id,
+            tags: Vec::new(),
+            deleted_at: row.deleted_at,
+        };
+        self.attach_tags([&mut post]).await?;
+        Ok(post)
+    }
+
+    async fn get_post_by_slug(&self, name: &str, visibility: Visibility) -> ServiceResult<Post> {
+        let (status, author_id) = visibility.bind_values();
+        let res = sqlx::query!(
+            r#"
+            SELECT id, author_id, title, slug, content, status, publish_at, published_at,
+                version, created, updated, deleted_at, category_id
+            FROM posts
+            WHERE slug = $1 AND deleted_at IS NULL
+                AND ($2::int IS NULL OR status = $2 OR author_id = $3)
+            "#,
+            name,
+            status,
+            author_id
+        );
+        let row = res
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Post not found: {}", name)))?;
+        let mut post = Post {
+            id: row.id,
+            author_id: row.author_id,
+            title: row.title,
+            slug: row.slug,
+            content: row.content,
+            status: PostStatus::try_from(row.status)?,
+            publish_at: row.publish_at,
+            published_at: row.published_at,
+            version: row.version,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            category_id: row.category_id,
+            tags: Vec::new(),
+            deleted_at: row.deleted_at,
+        };
+        self.attach_tags([&mut post]).await?;
+        Ok(post)
+    }
+
+    async fn search_posts(
+        &self,
+        query: &SearchQuery,
+        filter: PostFilter,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<SearchResults> {
+        let tsquery = query.to_tsquery();
+        let mut count = QueryBuilder::<Postgres>::new(PG_COUNT_SEARCH);
+        count
+            .push_bind(&tsquery)
+            .push(") query WHERE search_vector @@ query");
+        filter.push_sql(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut search = QueryBuilder::<Postgres>::new(PG_SEARCH_POSTS);
+        search
+            .push_bind(&tsquery)
+            .push(") query WHERE search_vector @@ query");
+        filter.push_sql(&mut search);
+        search
+            .push(" ORDER BY rank DESC, id ASC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+        let mut hits = search
+            .build()
+            .try_map(|row: PgRow| {
+                Ok(SearchHit {
+                    rank: row.try_get("rank")?,
+                    snippet: row.try_get("snippet")?,
+                    post: Self::map_row(row)?,
+                })
+            })
+            .fetch_all(&self.pool)
+            .await?;
+        self.attach_tags(hits.iter_mut().map(|hit| &mut hit.post))
+            .await?;
+
+        Ok(SearchResults { hits, total })
+    }
+
+    async fn create_post(&self, author_id: i64, req: CreatePostRequest) -> ServiceResult<Post> {
+        let lifecycle =
+            Lifecycle::NEW.transition(req.status, req.publish_at, chrono::offset::Utc::now())?;
+        let mut tx = self.pool.begin().await?;
+        if let Some(category_id) = req.category_id {
+            Self::check_category(&mut tx, category_id).await?;
+        }
+        let res = sqlx::query!(
+            r#"
+            INSERT INTO posts (author_id, title, slug, content, status, publish_at, published_at,
+                category_id, created, updated)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+            RETURNING id
+            "#,
+            author_id,
+            req.title,
+            req.slug,
+            req.content,
+            i32::from(lifecycle.status),
+            lifecycle.publish_at,
+            lifecycle.published_at,
+            req.category_id,
+        );
+        let res = res.fetch_one(&mut *tx).await?;
+        let id = res.id;
+        Self::replace_tags(&mut tx, id, &req.tags).await?;
+        Self::record_revision(&mut tx, id, Some(author_id)).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn update_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: UpdatePostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut tx = self.pool.begin().await?;
+        let lifecycle = Self::lock_lifecycle(&mut tx, id, version)
+            .await?
+            .transition(req.status, req.publish_at, chrono::offset::Utc::now())?;
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET title = $1, slug = $2, content = $3, status = $4, publish_at = $5,
+                published_at = $6, version = version + 1, updated = NOW()
+            WHERE id = $7 AND deleted_at IS NULL AND ($8::bigint IS NULL OR version = $8)
+            "#,
+            req.title,
+            req.slug,
+            req.content,
+            i32::from(lifecycle.status),
+            lifecycle.publish_at,
+            lifecycle.published_at,
+            id,
+            version
+        );
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        Self::record_revision(&mut tx, id, Some(editor_id)).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn patch_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: PatchPostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut tx = self.pool.begin().await?;
+        let lifecycle = Self::lock_lifecycle(&mut tx, id, version).await?.patch(
+            req.status,
+            req.publish_at,
+            chrono::offset::Utc::now(),
+        )?;
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET title = coalesce($1, title),
+                slug = coalesce($2, slug),
+                content = coalesce($3, content),
+                status = $4,
+                publish_at = $5,
+                published_at = $6,
+                version = version + 1,
+                updated = NOW()
+            WHERE id = $7 AND deleted_at IS NULL AND ($8::bigint IS NULL OR version = $8)
+            "#,
+            req.title,
+            req.slug,
+            req.content,
+            i32::from(lifecycle.status),
+            lifecycle.publish_at,
+            lifecycle.published_at,
+            id,
+            version
+        );
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        Self::record_revision(&mut tx, id, Some(editor_id)).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn delete_post(&self, id: i64, version: Option<i64>) -> ServiceResult<()> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET deleted_at = NOW(), version = version + 1, updated = NOW()
+            WHERE id = $1 AND deleted_at IS NULL AND ($2::bigint IS NULL OR version = $2)
+            "#,
+            id,
+            version
+        );
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        Ok(())
+    }
+
+    async fn restore_post(&self, id: i64) -> ServiceResult<Post> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET deleted_at = NULL, version = version + 1, updated = NOW()
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            "#,
+            id
+        );
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "Post not found in trash: {}",
+                id
+            )));
+        }
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn purge_post(&self, id: i64) -> ServiceResult<()> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM posts
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            "#,
+            id
+        );
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "Post not found in trash: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn count_posts_by_author(&self, author_id: i64) -> ServiceResult<i64> {
+        let res = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM posts WHERE author_id = $1"#,
+            author_id
+        );
+        Ok(res.fetch_one(&self.pool).await?)
+    }
+
+    async fn trash_posts_by_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET deleted_at = NOW(), version = version + 1, updated = NOW()
+            WHERE author_id = $1 AND deleted_at IS NULL
+            "#,
+            author_id
+        );
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+
+    async fn purge_posts_by_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM posts
+            WHERE author_id = $1
+            "#,
+            author_id
+        );
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+
+    async fn reassign_posts(&self, from_author_id: i64, to_author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET author_id = $2, version = version + 1, updated = NOW()
+            WHERE author_id = $1
+            "#,
+            from_author_id,
+            to_author_id
+        );
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+
+    async fn publish_due_posts(&self, now: DateTime<Utc>) -> ServiceResult<u64> {
+        let mut tx = self.pool.begin().await?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            UPDATE posts
+            SET status = $1, published_at = coalesce(published_at, publish_at), publish_at = NULL,
+                version = version + 1, updated = NOW()
+            WHERE status = $2 AND publish_at <= $3 AND deleted_at IS NULL
+            RETURNING id
+            "#,
+            i32::from(PostStatus::Published),
+            i32::from(PostStatus::Scheduled),
+            now
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for id in &ids {
+            Self::record_revision(&mut tx, *id, None).await?;
+        }
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
+
+    async fn list_revisions(&self, id: i64) -> ServiceResult<Vec<PostRevision>> {
+        self.get_post_by_id(id, Visibility::All).await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT post_id, revision, editor_id, title, slug, content, status, created
+            FROM post_revisions
+            WHERE post_id = $1
+            ORDER BY revision DESC
+            "#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(PostRevision {
+                    post_id: row.post_id,
+                    revision: row.revision,
+                    editor_id: row.editor_id,
+                    title: row.title,
+                    slug: row.slug,
+                    content: row.content,
+                    status: PostStatus::try_from(row.status)?,
+                    created: row.created.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    async fn get_revision(&self, id: i64, revision: i64) -> ServiceResult<PostRevision> {
+        let row = sqlx::query!(
+            r#"
+            SELECT r.post_id, r.revision, r.editor_id, r.title, r.slug, r.content, r.status,
+                r.created
+            FROM post_revisions r
+            JOIN posts p ON p.id = r.post_id AND p.deleted_at IS NULL
+            WHERE r.post_id = $1 AND r.revision = $2
+            "#,
+            id,
+            revision
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            ServiceError::or_not_found(e, format!("Revision {} of post {} not found", revision, id))
+        })?;
+        Ok(PostRevision {
+            post_id: row.post_id,
+            revision: row.revision,
+            editor_id: row.editor_id,
+            title: row.title,
+            slug: row.slug,
+            content: row.content,
+            status: PostStatus::try_from(row.status)?,
+            created: row.created.unwrap_or_default(),
+        })
+    }
+
+    async fn list_tags(&self) -> ServiceResult<Vec<TagCount>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT t.id, t.name, t.slug, COUNT(p.id) AS "post_count!"
+            FROM tags t
+            LEFT JOIN post_tags pt ON pt.tag_id = t.id
+            LEFT JOIN posts p ON p.id = pt.post_id AND p.deleted_at IS NULL AND p.status = $1
+            GROUP BY t.id, t.name, t.slug
+            ORDER BY t.slug
+            "#,
+            i32::from(PostStatus::Published)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| TagCount {
+                tag: Tag {
+                    id: row.id,
+                    name: row.name,
+                    slug: row.slug,
+                },
+                post_count: row.post_count,
+            })
+            .collect())
+    }
+
+    async fn get_tag(&self, id: i64) -> ServiceResult<Tag> {
+        sqlx::query_as!(Tag, "SELECT id, name, slug FROM tags WHERE id = $1", id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Tag not found: {}", id)))
+    }
+
+    async fn create_tag(&self, req: CreateTagRequest) -> ServiceResult<Tag> {
+        let tag = sqlx::query_as!(
+            Tag,
+            r#"
+            INSERT INTO tags (name, slug)
+            VALUES ($1, $2)
+            RETURNING id, name, slug
+            "#,
+            req.name,
+            req.slug
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(tag)
+    }
+
+    async fn update_tag(&self, id: i64, req: UpdateTagRequest) -> ServiceResult<Tag> {
+        sqlx::query_as!(
+            Tag,
+            r#"
+            UPDATE tags
+            SET name = $1, slug = $2
+            WHERE id = $3
+            RETURNING id, name, slug
+            "#,
+            req.name,
+            req.slug,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServiceError::or_not_found(e, format!("Tag not found: {}", id)))
+    }
+
+    async fn delete_tag(&self, id: i64) -> ServiceResult<()> {
+        let res = sqlx::query!("DELETE FROM tags WHERE id = $1", id);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Tag not found: {}", id)));
+        }
+        Ok(())
+    }
+
+    async fn set_post_tags(
+        &self,
+        id: i64,
+        tags: Vec<String>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut tx = self.pool.begin().await?;
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET version = version + 1, updated = NOW()
+            WHERE id = $1 AND deleted_at IS NULL AND ($2::bigint IS NULL OR version = $2)
+            "#,
+            id,
+            version
+        );
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(self.write_failed(id).await);
+        }
+        Self::replace_tags(&mut tx, id, &tags).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn list_categories(&self) -> ServiceResult<Vec<Category>> {
+        let categories = sqlx::query_as!(
+            Category,
+            "SELECT id, parent_id, name, slug FROM categories ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(categories)
+    }
+
+    async fn get_category(&self, id: i64) -> ServiceResult<Category> {
+        sqlx::query_as!(
+            Category,
+            "SELECT id, parent_id, name, slug FROM categories WHERE id = $1",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ServiceError::or_not_found(e, format!("Category not found: {}", id)))
+    }
+
+    async fn create_category(&self, req: CreateCategoryRequest) -> ServiceResult<Category> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(parent_id) = req.parent_id {
+            Self::check_category(&mut tx, parent_id).await?;
+        }
+        let category = sqlx::query_as!(
+            Category,
+            r#"
+            INSERT INTO categories (parent_id, name, slug)
+            VALUES ($1, $2, $3)
+            RETURNING id, parent_id, name, slug
+            "#,
+            req.parent_id,
+            req.name,
+            req.slug
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(category)
+    }
+
+    async fn update_category(
+        &self,
+        id: i64,
+        req: UpdateCategoryRequest,
+    ) -> ServiceResult<Category> {
+        let mut tx = self.pool.begin().await?;
+        //锁住所有分类直到提交，并发移动的分类不能各自通过检查后共同形成环
+        let categories = sqlx::query_as!(
+            Category,
+            "SELECT id, parent_id, name, slug FROM categories ORDER BY id FOR UPDATE"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if let Some(parent_id) = req.parent_id {
+            Self::check_category(&mut tx, parent_id).await?;
+        }
+        if creates_cycle(&categories, id, req.parent_id) {
+            return Err(ServiceError::Validation(
+                "A category cannot be moved under itself or one of its subcategories".to_string(),
+            ));
+        }
+        let category = sqlx::query_as!(
+            Category,
+            r#"
+            UPDATE categories
+            SET parent_id = $1, name = $2, slug = $3
+            WHERE id = $4
+            RETURNING id, parent_id, name, slug
+            "#,
+            req.parent_id,
+            req.name,
+            req.slug,
             id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::or_not_found(e, format!("Category not found: {}", id)))?;
+        tx.commit().await?;
+        Ok(category)
+    }
+
+    async fn delete_category(&self, id: i64) -> ServiceResult<()> {
+        let children = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM categories WHERE parent_id = $1"#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if children > 0 {
+            return Err(ServiceError::Conflict(format!(
+                "Category has subcategories: {}",
+                id
+            )));
+        }
+        let res = sqlx::query!("DELETE FROM categories WHERE id = $1", id);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "Category not found: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn set_post_category(
+        &self,
+        id: i64,
+        category_id: Option<i64>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(category_id) = category_id {
+            Self::check_category(&mut tx, category_id).await?;
+        }
+        let res = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET category_id = $1, version = version + 1, updated = NOW()
+            WHERE id = $2 AND deleted_at IS NULL AND ($3::bigint IS NULL OR version = $3)
+            "#,
+            category_id,
+            id,
+            version
         );
-        res.execute(&self.pool).await?;
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(self.write_failed(id).await);
+        }
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+}
+
+#[async_trait]
+impl PostService for SqlitePostService {
+    async fn list_posts(&self, filter: PostFilter, page: PageRequest) -> ServiceResult<Page<Post>> {
+        let mut count = QueryBuilder::<Sqlite>::new(COUNT_POSTS);
+        filter.push_sql(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::<Sqlite>::new(SELECT_POSTS);
+        filter.push_sql(&mut query);
+        page.push_sql(&mut query);
+        let mut rows = query
+            .build()
+            .try_map(Self::map_row)
+            .fetch_all(&self.pool)
+            .await?;
+        self.attach_tags(rows.iter_mut()).await?;
+
+        Ok(page.page(rows, total))
+    }
+
+    async fn get_post_by_id(&self, id: i64, visibility: Visibility) -> ServiceResult<Post> {
+        let (status, author_id) = visibility.bind_values();
+        let res = sqlx::query(
+            r#"
+            SELECT id, author_id, title, slug, content, status, publish_at, published_at,
+                version, created, updated, deleted_at, category_id
+            FROM posts
+            WHERE id = $1 AND deleted_at IS NULL
+                AND ($2 IS NULL OR status = $2 OR author_id = $3)
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(author_id);
+        let mut post = res
+            .try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Post not found: {}", id)))?;
+        self.attach_tags([&mut post]).await?;
+        Ok(post)
+    }
+
+    async fn get_post_by_slug(&self, name: &str, visibility: Visibility) -> ServiceResult<Post> {
+        let (status, author_id) = visibility.bind_values();
+        let res = sqlx::query(
+            r#"
+            SELECT id, author_id, title, slug, content, status, publish_at, published_at,
+                version, created, updated, deleted_at, category_id
+            FROM posts
+            WHERE slug = $1 AND deleted_at IS NULL
+                AND ($2 IS NULL OR status = $2 OR author_id = $3)
+            "#,
+        )
+        .bind(name)
+        .bind(status)
+        .bind(author_id);
+        let mut post = res
+            .try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Post not found: {}", name)))?;
+        self.attach_tags([&mut post]).await?;
+        Ok(post)
+    }
+
+    async fn search_posts(
+        &self,
+        query: &SearchQuery,
+        filter: PostFilter,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<SearchResults> {
+        let fts = query.to_fts5();
+        let mut count = QueryBuilder::<Sqlite>::new(SQLITE_COUNT_SEARCH);
+        count.push_bind(&fts);
+        filter.push_sql(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut search = QueryBuilder::<Sqlite>::new(SQLITE_SEARCH_POSTS);
+        search.push_bind(&fts);
+        filter.push_sql(&mut search);
+        search
+            .push(" ORDER BY rank DESC, posts.id ASC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+        let mut hits = search
+            .build()
+            .try_map(|row: SqliteRow| {
+                Ok(SearchHit {
+                    rank: row.try_get::<f64, _>("rank")? as f32,
+                    snippet: row.try_get("snippet")?,
+                    post: Self::map_row(row)?,
+                })
+            })
+            .fetch_all(&self.pool)
+            .await?;
+        self.attach_tags(hits.iter_mut().map(|hit| &mut hit.post))
+            .await?;
+
+        Ok(SearchResults { hits, total })
+    }
+
+    async fn create_post(&self, author_id: i64, req: CreatePostRequest) -> ServiceResult<Post> {
+        let ts = chrono::offset::Utc::now();
+        let lifecycle = Lifecycle::NEW.transition(req.status, req.publish_at, ts)?;
+        let mut tx = self.pool.begin().await?;
+        if let Some(category_id) = req.category_id {
+            Self::check_category(&mut tx, category_id).await?;
+        }
+        let res = sqlx::query(
+            r#"
+            INSERT INTO posts (author_id, title, slug, content, status, publish_at, published_at,
+                category_id, created, updated)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9)
+            RETURNING id
+            "#,
+        )
+        .bind(author_id)
+        .bind(req.title)
+        .bind(req.slug)
+        .bind(req.content)
+        .bind(i32::from(lifecycle.status))
+        .bind(lifecycle.publish_at)
+        .bind(lifecycle.published_at)
+        .bind(req.category_id)
+        .bind(ts);
+        let res = res.fetch_one(&mut *tx).await?;
+        let id: i64 = res.try_get("id")?;
+        Self::replace_tags(&mut tx, id, &req.tags).await?;
+        Self::record_revision(&mut tx, id, Some(author_id)).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn update_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: UpdatePostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let ts = chrono::offset::Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let lifecycle = Self::read_lifecycle(&mut tx, id, version)
+            .await?
+            .transition(req.status, req.publish_at, ts)?;
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET title = $1, slug = $2, content = $3, status = $4, publish_at = $5,
+                published_at = $6, version = version + 1, updated = $7
+            WHERE id = $8 AND deleted_at IS NULL AND ($9 IS NULL OR version = $9)
+            "#,
+        )
+        .bind(req.title)
+        .bind(req.slug)
+        .bind(req.content)
+        .bind(i32::from(lifecycle.status))
+        .bind(lifecycle.publish_at)
+        .bind(lifecycle.published_at)
+        .bind(ts)
+        .bind(id)
+        .bind(version);
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        Self::record_revision(&mut tx, id, Some(editor_id)).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn patch_post(
+        &self,
+        id: i64,
+        editor_id: i64,
+        req: PatchPostRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let ts = chrono::offset::Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let lifecycle = Self::read_lifecycle(&mut tx, id, version).await?.patch(
+            req.status,
+            req.publish_at,
+            ts,
+        )?;
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET title = coalesce($1, title),
+                slug = coalesce($2, slug),
+                content = coalesce($3, content),
+                status = $4,
+                publish_at = $5,
+                published_at = $6,
+                version = version + 1,
+                updated = $7
+            WHERE id = $8 AND deleted_at IS NULL AND ($9 IS NULL OR version = $9)
+            "#,
+        )
+        .bind(req.title)
+        .bind(req.slug)
+        .bind(req.content)
+        .bind(i32::from(lifecycle.status))
+        .bind(lifecycle.publish_at)
+        .bind(lifecycle.published_at)
+        .bind(ts)
+        .bind(id)
+        .bind(version);
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        Self::record_revision(&mut tx, id, Some(editor_id)).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn delete_post(&self, id: i64, version: Option<i64>) -> ServiceResult<()> {
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET deleted_at = $1, version = version + 1, updated = $1
+            WHERE id = $2 AND deleted_at IS NULL AND ($3 IS NULL OR version = $3)
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(version);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        Ok(())
+    }
+
+    async fn restore_post(&self, id: i64) -> ServiceResult<Post> {
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET deleted_at = NULL, version = version + 1, updated = $1
+            WHERE id = $2 AND deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(id);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "Post not found in trash: {}",
+                id
+            )));
+        }
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn purge_post(&self, id: i64) -> ServiceResult<()> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM posts
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(id);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "Post not found in trash: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn count_posts_by_author(&self, author_id: i64) -> ServiceResult<i64> {
+        let res =
+            sqlx::query_scalar("SELECT COUNT(*) FROM posts WHERE author_id = $1").bind(author_id);
+        Ok(res.fetch_one(&self.pool).await?)
+    }
+
+    async fn trash_posts_by_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET deleted_at = $1, version = version + 1, updated = $1
+            WHERE author_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(author_id);
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+
+    async fn purge_posts_by_author(&self, author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM posts
+            WHERE author_id = $1
+            "#,
+        )
+        .bind(author_id);
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+
+    async fn reassign_posts(&self, from_author_id: i64, to_author_id: i64) -> ServiceResult<u64> {
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET author_id = $1, version = version + 1, updated = $2
+            WHERE author_id = $3
+            "#,
+        )
+        .bind(to_author_id)
+        .bind(chrono::offset::Utc::now())
+        .bind(from_author_id);
+        Ok(res.execute(&self.pool).await?.rows_affected())
+    }
+
+    async fn publish_due_posts(&self, now: DateTime<Utc>) -> ServiceResult<u64> {
+        let mut tx = self.pool.begin().await?;
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            UPDATE posts
+            SET status = $1, published_at = coalesce(published_at, publish_at), publish_at = NULL,
+                version = version + 1, updated = $2
+            WHERE status = $3 AND publish_at <= $2 AND deleted_at IS NULL
+            RETURNING id
+            "#,
+        )
+        .bind(i32::from(PostStatus::Published))
+        .bind(now)
+        .bind(i32::from(PostStatus::Scheduled))
+        .fetch_all(&mut *tx)
+        .await?;
+        for id in &ids {
+            Self::record_revision(&mut tx, *id, None).await?;
+        }
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
+
+    async fn list_revisions(&self, id: i64) -> ServiceResult<Vec<PostRevision>> {
+        self.get_post_by_id(id, Visibility::All).await?;
+        let res = sqlx::query(
+            r#"
+            SELECT post_id, revision, editor_id, title, slug, content, status, created
+            FROM post_revisions
+            WHERE post_id = $1
+            ORDER BY revision DESC
+            "#,
+        )
+        .bind(id);
+        Ok(res
+            .try_map(Self::map_revision_row)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_revision(&self, id: i64, revision: i64) -> ServiceResult<PostRevision> {
+        let res = sqlx::query(
+            r#"
+            SELECT r.post_id, r.revision, r.editor_id, r.title, r.slug, r.content, r.status,
+                r.created
+            FROM post_revisions r
+            JOIN posts p ON p.id = r.post_id AND p.deleted_at IS NULL
+            WHERE r.post_id = $1 AND r.revision = $2
+            "#,
+        )
+        .bind(id)
+        .bind(revision);
+        res.try_map(Self::map_revision_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| {
+                ServiceError::or_not_found(
+                    e,
+                    format!("Revision {} of post {} not found", revision, id),
+                )
+            })
+    }
+
+    async fn list_tags(&self) -> ServiceResult<Vec<TagCount>> {
+        let res = sqlx::query(
+            r#"
+            SELECT t.id, t.name, t.slug, COUNT(p.id) AS post_count
+            FROM tags t
+            LEFT JOIN post_tags pt ON pt.tag_id = t.id
+            LEFT JOIN posts p ON p.id = pt.post_id AND p.deleted_at IS NULL AND p.status = $1
+            GROUP BY t.id, t.name, t.slug
+            ORDER BY t.slug
+            "#,
+        )
+        .bind(i32::from(PostStatus::Published));
+        Ok(res
+            .try_map(|row: SqliteRow| {
+                Ok(TagCount {
+                    post_count: row.try_get("post_count")?,
+                    tag: Self::map_tag_row(row)?,
+                })
+            })
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_tag(&self, id: i64) -> ServiceResult<Tag> {
+        let res = sqlx::query("SELECT id, name, slug FROM tags WHERE id = $1").bind(id);
+        res.try_map(Self::map_tag_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Tag not found: {}", id)))
+    }
+
+    async fn create_tag(&self, req: CreateTagRequest) -> ServiceResult<Tag> {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO tags (name, slug, created)
+            VALUES ($1, $2, $3)
+            RETURNING id, name, slug
+            "#,
+        )
+        .bind(req.name)
+        .bind(req.slug)
+        .bind(chrono::offset::Utc::now());
+        Ok(res.try_map(Self::map_tag_row).fetch_one(&self.pool).await?)
+    }
+
+    async fn update_tag(&self, id: i64, req: UpdateTagRequest) -> ServiceResult<Tag> {
+        let res = sqlx::query(
+            r#"
+            UPDATE tags
+            SET name = $1, slug = $2
+            WHERE id = $3
+            RETURNING id, name, slug
+            "#,
+        )
+        .bind(req.name)
+        .bind(req.slug)
+        .bind(id);
+        res.try_map(Self::map_tag_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Tag not found: {}", id)))
+    }
+
+    async fn delete_tag(&self, id: i64) -> ServiceResult<()> {
+        let res = sqlx::query("DELETE FROM tags WHERE id = $1").bind(id);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Tag not found: {}", id)));
+        }
         Ok(())
     }
+
+    async fn set_post_tags(
+        &self,
+        id: i64,
+        tags: Vec<String>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut tx = self.pool.begin().await?;
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET version = version + 1, updated = $1
+            WHERE id = $2 AND deleted_at IS NULL AND ($3 IS NULL OR version = $3)
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(version);
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(self.write_failed(id).await);
+        }
+        Self::replace_tags(&mut tx, id, &tags).await?;
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+
+    async fn list_categories(&self) -> ServiceResult<Vec<Category>> {
+        let res = sqlx::query("SELECT id, parent_id, name, slug FROM categories ORDER BY id");
+        Ok(res
+            .try_map(Self::map_category_row)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_category(&self, id: i64) -> ServiceResult<Category> {
+        let res =
+            sqlx::query("SELECT id, parent_id, name, slug FROM categories WHERE id = $1").bind(id);
+        res.try_map(Self::map_category_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Category not found: {}", id)))
+    }
+
+    async fn create_category(&self, req: CreateCategoryRequest) -> ServiceResult<Category> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(parent_id) = req.parent_id {
+            Self::check_category(&mut tx, parent_id).await?;
+        }
+        let res = sqlx::query(
+            r#"
+            INSERT INTO categories (parent_id, name, slug, created)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, parent_id, name, slug
+            "#,
+        )
+        .bind(req.parent_id)
+        .bind(req.name)
+        .bind(req.slug)
+        .bind(chrono::offset::Utc::now());
+        let category = res
+            .try_map(Self::map_category_row)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(category)
+    }
+
+    async fn update_category(
+        &self,
+        id: i64,
+        req: UpdateCategoryRequest,
+    ) -> ServiceResult<Category> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(parent_id) = req.parent_id {
+            Self::check_category(&mut tx, parent_id).await?;
+        }
+        //在事务中读取分类，其他连接在此之后提交的修改会让本事务的写入失败，而不是基于旧的分类树提交
+        let categories =
+            sqlx::query("SELECT id, parent_id, name, slug FROM categories ORDER BY id")
+                .try_map(Self::map_category_row)
+                .fetch_all(&mut *tx)
+                .await?;
+        if creates_cycle(&categories, id, req.parent_id) {
+            return Err(ServiceError::Validation(
+                "A category cannot be moved under itself or one of its subcategories".to_string(),
+            ));
+        }
+        let res = sqlx::query(
+            r#"
+            UPDATE categories
+            SET parent_id = $1, name = $2, slug = $3
+            WHERE id = $4
+            RETURNING id, parent_id, name, slug
+            "#,
+        )
+        .bind(req.parent_id)
+        .bind(req.name)
+        .bind(req.slug)
+        .bind(id);
+        let category = res
+            .try_map(Self::map_category_row)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Category not found: {}", id)))?;
+        tx.commit().await?;
+        Ok(category)
+    }
+
+    async fn delete_category(&self, id: i64) -> ServiceResult<()> {
+        let children: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE parent_id = $1")
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+        if children > 0 {
+            return Err(ServiceError::Conflict(format!(
+                "Category has subcategories: {}",
+                id
+            )));
+        }
+        let res = sqlx::query("DELETE FROM categories WHERE id = $1").bind(id);
+        if res.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "Category not found: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn set_post_category(
+        &self,
+        id: i64,
+        category_id: Option<i64>,
+        version: Option<i64>,
+    ) -> ServiceResult<Post> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(category_id) = category_id {
+            Self::check_category(&mut tx, category_id).await?;
+        }
+        let res = sqlx::query(
+            r#"
+            UPDATE posts
+            SET category_id = $1, version = version + 1, updated = $2
+            WHERE id = $3 AND deleted_at IS NULL AND ($4 IS NULL OR version = $4)
+            "#,
+        )
+        .bind(category_id)
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(version);
+        if res.execute(&mut *tx).await?.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(self.write_failed(id).await);
+        }
+        tx.commit().await?;
+        self.get_post_by_id(id, Visibility::All).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    const STATUSES: [PostStatus; 5] = [
+        PostStatus::Draft,
+        PostStatus::PendingReview,
+        PostStatus::Scheduled,
+        PostStatus::Published,
+        PostStatus::Archived,
+    ];
+
+    fn lifecycle(status: PostStatus) -> Lifecycle {
+        Lifecycle {
+            status,
+            publish_at: None,
+            published_at: None,
+        }
+    }
+
+    /// 变更为定时发布时给出将来的时间，其他状态不给出
+    fn publish_at(status: PostStatus, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (status == PostStatus::Scheduled).then(|| now + Duration::hours(1))
+    }
+
+    #[test]
+    fn allowed_status_transitions() {
+        use PostStatus::*;
+        let allowed = [
+            (Draft, PendingReview),
+            (Draft, Scheduled),
+            (Draft, Published),
+            (PendingReview, Draft),
+            (PendingReview, Scheduled),
+            (PendingReview, Published),
+            (Scheduled, Draft),
+            (Scheduled, PendingReview),
+            (Scheduled, Published),
+            (Published, Draft),
+            (Published, Archived),
+            (Archived, Draft),
+            (Archived, Published),
+        ];
+        let now = Utc::now();
+        for from in STATUSES {
+            for to in STATUSES {
+                let expected = from == to || allowed.contains(&(from, to));
+                assert_eq!(
+                    from.can_transition_to(to),
+                    expected,
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+                let result = lifecycle(from).transition(to, publish_at(to, now), now);
+                match result {
+                    Ok(next) => {
+                        assert!(expected, "{:?} -> {:?} was allowed", from, to);
+                        assert_eq!(next.status, to);
+                    }
+                    Err(ServiceError::Validation(message)) => {
+                        assert!(!expected, "{:?} -> {:?}: {}", from, to, message);
+                        assert!(message.starts_with("Cannot change post status"));
+                    }
+                    Err(error) => panic!("{:?} -> {:?}: {:?}", from, to, error),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scheduling_needs_a_future_publish_at() {
+        let now = Utc::now();
+        let draft = lifecycle(PostStatus::Draft);
+        for publish_at in [None, Some(now), Some(now - Duration::hours(1))] {
+            assert!(matches!(
+                draft.transition(PostStatus::Scheduled, publish_at, now),
+                Err(ServiceError::Validation(_))
+            ));
+        }
+        //只有定时发布可以设置发布时间
+        assert!(matches!(
+            draft.transition(PostStatus::Published, Some(now + Duration::hours(1)), now),
+            Err(ServiceError::Validation(_))
+        ));
+
+        //已过期但未修改的原定时间仍然有效
+        let scheduled = Lifecycle {
+            status: PostStatus::Scheduled,
+            publish_at: Some(now - Duration::minutes(1)),
+            published_at: None,
+        };
+        let next = scheduled.patch(None, None, now).unwrap();
+        assert_eq!(next.publish_at, scheduled.publish_at);
+        let next = scheduled.patch(Some(PostStatus::Draft), None, now).unwrap();
+        assert_eq!(next.publish_at, None);
+    }
+
+    #[test]
+    fn first_publication_time_is_kept() {
+        let now = Utc::now();
+        let published = lifecycle(PostStatus::Draft)
+            .transition(PostStatus::Published, None, now)
+            .unwrap();
+        assert_eq!(published.published_at, Some(now));
+
+        let later = now + Duration::days(1);
+        let republished = published
+            .transition(PostStatus::Archived, None, later)
+            .and_then(|archived| archived.transition(PostStatus::Published, None, later))
+            .unwrap();
+        assert_eq!(republished.published_at, Some(now));
+    }
 }
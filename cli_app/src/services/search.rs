@@ -0,0 +1,401 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::model::Post;
+
+/// 检索条件中的一项，多项之间为“与”的关系
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchTerm {
+    Word(String),
+    /// `term*`，匹配以该前缀开头的词
+    Prefix(String),
+    /// `"a b c"`，匹配按顺序相邻出现的词
+    Phrase(Vec<String>),
+}
+
+/// 全文检索条件，支持普通词、`"短语"` 和 `前缀*`
+#[derive(Clone, Debug)]
+pub struct SearchQuery {
+    terms: Vec<SearchTerm>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> anyhow::Result<Self> {
+        let mut terms = Vec::new();
+        let mut rest = query;
+        while let Some(start) = rest.find('"') {
+            Self::push_words(&rest[..start], &mut terms);
+            let after = &rest[start + 1..];
+            let end = after.find('"').unwrap_or(after.len());
+            Self::push_phrase(tokenize(&after[..end]), &mut terms);
+            rest = after.get(end + 1..).unwrap_or("");
+        }
+        Self::push_words(rest, &mut terms);
+
+        if terms.is_empty() {
+            anyhow::bail!("Search query must contain at least one word");
+        }
+        Ok(Self { terms })
+    }
+
+    fn push_words(text: &str, terms: &mut Vec<SearchTerm>) {
+        for word in text.split_whitespace() {
+            let tokens = tokenize(word);
+            if word.ends_with('*') && tokens.len() == 1 {
+                terms.push(SearchTerm::Prefix(tokens[0].text.clone()));
+            } else {
+                //`foo-bar` 这类被拆成多个词的输入按短语处理
+                Self::push_phrase(tokens, terms);
+            }
+        }
+    }
+
+    fn push_phrase(tokens: Vec<Token>, terms: &mut Vec<SearchTerm>) {
+        let mut words: Vec<String> = tokens.into_iter().map(|t| t.text).collect();
+        match words.len() {
+            0 => {}
+            1 => terms.push(SearchTerm::Word(words.remove(0))),
+            _ => terms.push(SearchTerm::Phrase(words)),
+        }
+    }
+
+    pub fn terms(&self) -> &[SearchTerm] {
+        &self.terms
+    }
+
+    /// Postgres `to_tsquery` 表达式，词已经过分词，只包含字母和数字
+    pub fn to_tsquery(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                SearchTerm::Word(word) => format!("'{}'", word),
+                SearchTerm::Prefix(prefix) => format!("'{}':*", prefix),
+                SearchTerm::Phrase(words) => format!(
+                    "({})",
+                    words
+                        .iter()
+                        .map(|w| format!("'{}'", w))
+                        .collect::<Vec<_>>()
+                        .join(" <-> ")
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+
+    /// SQLite FTS5 `MATCH` 表达式
+    pub fn to_fts5(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                SearchTerm::Word(word) => format!("\"{}\"", word),
+                SearchTerm::Prefix(prefix) => format!("\"{}\"*", prefix),
+                SearchTerm::Phrase(words) => format!("\"{}\"", words.join(" ")),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 分词结果，`start`/`end` 为原文中的字节位置
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 以非字母数字字符分隔并转为小写
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push(Token {
+                    text: text[s..i].to_lowercase(),
+                    start: s,
+                    end: i,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(Token {
+            text: text[s..].to_lowercase(),
+            start: s,
+            end: text.len(),
+        });
+    }
+    tokens
+}
+
+/// 命中的文章、相关度和高亮摘要
+pub struct SearchHit {
+    pub post: Post,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    pub total: i64,
+}
+
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+const SNIPPET_WORDS: usize = 32;
+const TITLE_WEIGHT: f32 = 2.0;
+
+#[derive(Default)]
+struct Postings {
+    title: Vec<usize>,
+    content: Vec<usize>,
+}
+
+/// 内存后端使用的倒排索引，记录每个词在标题和正文中出现的位置
+#[derive(Default)]
+pub struct InvertedIndex {
+    terms: BTreeMap<String, HashMap<i64, Postings>>,
+}
+
+impl InvertedIndex {
+    pub fn insert(&mut self, post: &Post) {
+        self.remove(post.id);
+        for (i, token) in tokenize(&post.title).into_iter().enumerate() {
+            let postings = self.terms.entry(token.text).or_default();
+            postings.entry(post.id).or_default().title.push(i);
+        }
+        for (i, token) in tokenize(&post.content).into_iter().enumerate() {
+            let postings = self.terms.entry(token.text).or_default();
+            postings.entry(post.id).or_default().content.push(i);
+        }
+    }
+
+    pub fn remove(&mut self, id: i64) {
+        self.terms.retain(|_, postings| {
+            postings.remove(&id);
+            !postings.is_empty()
+        });
+    }
+
+    /// 返回满足所有检索项的文章及其得分，标题中的命中权重更高
+    pub fn search(&self, query: &SearchQuery) -> HashMap<i64, f32> {
+        let mut result: Option<HashMap<i64, f32>> = None;
+        for term in query.terms() {
+            let scores = self.term_scores(term);
+            result = Some(match result {
+                None => scores,
+                Some(acc) => acc
+                    .into_iter()
+                    .filter_map(|(id, score)| scores.get(&id).map(|s| (id, score + s)))
+                    .collect(),
+            });
+        }
+        result.unwrap_or_default()
+    }
+
+    fn term_scores(&self, term: &SearchTerm) -> HashMap<i64, f32> {
+        let mut scores: HashMap<i64, f32> = HashMap::new();
+        match term {
+            SearchTerm::Word(word) => {
+                if let Some(postings) = self.terms.get(word) {
+                    for (id, p) in postings {
+                        *scores.entry(*id).or_default() += score(p.title.len(), p.content.len());
+                    }
+                }
+            }
+            SearchTerm::Prefix(prefix) => {
+                for (_, postings) in self
+                    .terms
+                    .range(prefix.clone()..)
+                    .take_while(|(word, _)| word.starts_with(prefix.as_str()))
+                {
+                    for (id, p) in postings {
+                        *scores.entry(*id).or_default() += score(p.title.len(), p.content.len());
+                    }
+                }
+            }
+            SearchTerm::Phrase(words) => {
+                let Some(lists) = words
+                    .iter()
+                    .map(|w| self.terms.get(w))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return scores;
+                };
+                for (id, first) in lists[0] {
+                    let Some(rest) = lists[1..]
+                        .iter()
+                        .map(|l| l.get(id))
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        continue;
+                    };
+                    let title = phrase_count(&first.title, rest.iter().map(|p| &p.title));
+                    let content = phrase_count(&first.content, rest.iter().map(|p| &p.content));
+                    if title + content > 0 {
+                        scores.insert(*id, score(title, content));
+                    }
+                }
+            }
+        }
+        scores
+    }
+}
+
+fn score(title: usize, content: usize) -> f32 {
+    title as f32 * TITLE_WEIGHT + content as f32
+}
+
+/// 统计短语出现的次数，即第 i 个词出现在首词位置之后第 i 位
+fn phrase_count<'a>(first: &[usize], rest: impl Iterator<Item = &'a Vec<usize>> + Clone) -> usize {
+    first
+        .iter()
+        .filter(|&&start| {
+            rest.clone()
+                .enumerate()
+                .all(|(i, positions)| positions.contains(&(start + i + 1)))
+        })
+        .count()
+}
+
+/// 截取正文中第一个命中附近的片段，并用 `<mark>` 标记命中的词
+pub fn highlight(text: &str, query: &SearchQuery) -> String {
+    let mut words: HashSet<&str> = HashSet::new();
+    let mut prefixes: Vec<&str> = Vec::new();
+    for term in query.terms() {
+        match term {
+            SearchTerm::Word(word) => {
+                words.insert(word);
+            }
+            SearchTerm::Prefix(prefix) => prefixes.push(prefix),
+            SearchTerm::Phrase(phrase) => words.extend(phrase.iter().map(String::as_str)),
+        }
+    }
+    let matches = |token: &Token| {
+        words.contains(token.text.as_str()) || prefixes.iter().any(|p| token.text.starts_with(p))
+    };
+
+    let tokens = tokenize(text);
+    let first = tokens.iter().position(matches).unwrap_or(0);
+    let from = first.saturating_sub(SNIPPET_WORDS / 4);
+    let to = (from + SNIPPET_WORDS).min(tokens.len());
+    if from >= to {
+        return String::new();
+    }
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let mut pos = tokens[from].start;
+    for token in &tokens[from..to] {
+        snippet.push_str(&text[pos..token.start]);
+        if matches(token) {
+            snippet.push_str(HIGHLIGHT_START);
+            snippet.push_str(&text[token.start..token.end]);
+            snippet.push_str(HIGHLIGHT_END);
+        } else {
+            snippet.push_str(&text[token.start..token.end]);
+        }
+        pos = token.end;
+    }
+    if to < tokens.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str) -> SearchTerm {
+        SearchTerm::Word(text.to_string())
+    }
+
+    fn phrase(words: &[&str]) -> SearchTerm {
+        SearchTerm::Phrase(words.iter().map(|w| w.to_string()).collect())
+    }
+
+    fn prefix(text: &str) -> SearchTerm {
+        SearchTerm::Prefix(text.to_string())
+    }
+
+    fn terms(query: &str) -> Vec<SearchTerm> {
+        SearchQuery::parse(query).unwrap().terms().to_vec()
+    }
+
+    #[test]
+    fn parses_words_phrases_and_prefixes() {
+        assert_eq!(
+            terms(r#"Rust "Async  Runtime" tok*"#),
+            [word("rust"), phrase(&["async", "runtime"]), prefix("tok")]
+        );
+        //被标点拆开的词按短语处理，跨多个词的 `*` 不当作前缀
+        assert_eq!(terms("foo-bar"), [phrase(&["foo", "bar"])]);
+        assert_eq!(terms("foo-bar*"), [phrase(&["foo", "bar"])]);
+        assert_eq!(terms(r#""single""#), [word("single")]);
+        assert_eq!(terms(r#"a""b"#), [word("a"), word("b")]);
+    }
+
+    #[test]
+    fn unterminated_quote_runs_to_the_end() {
+        assert_eq!(
+            terms(r#"hello "big world"#),
+            [word("hello"), phrase(&["big", "world"])]
+        );
+        assert_eq!(terms(r#"say "hi"#), [word("say"), word("hi")]);
+        assert_eq!(terms(r#"say ""#), [word("say")]);
+    }
+
+    #[test]
+    fn query_without_words_is_rejected() {
+        for query in ["", "   ", "\"", "\"\"", "*", "-- !"] {
+            assert!(SearchQuery::parse(query).is_err(), "accepted {:?}", query);
+        }
+    }
+
+    #[test]
+    fn builds_backend_expressions() {
+        let query = SearchQuery::parse(r#"rust "async runtime" tok*"#).unwrap();
+        assert_eq!(
+            query.to_tsquery(),
+            "'rust' & ('async' <-> 'runtime') & 'tok':*"
+        );
+        assert_eq!(query.to_fts5(), r#""rust" "async runtime" "tok"*"#);
+        //引号等特殊字符在分词时已经去掉
+        let query = SearchQuery::parse(r#"it's "a"b"#).unwrap();
+        assert_eq!(query.to_tsquery(), "('it' <-> 's') & 'a' & 'b'");
+        assert_eq!(query.to_fts5(), r#""it s" "a" "b""#);
+    }
+
+    #[test]
+    fn counts_adjacent_phrase_positions() {
+        let first = vec![0, 5, 9];
+        let second = vec![1, 7, 10];
+        let third = vec![2, 11];
+        assert_eq!(phrase_count(&first, [&second, &third].into_iter()), 2);
+        assert_eq!(phrase_count(&first, [&third].into_iter()), 0);
+        assert_eq!(phrase_count(&first, std::iter::empty()), 3);
+    }
+
+    #[test]
+    fn highlights_matching_words() {
+        let query = SearchQuery::parse(r#"tok* "Big World""#).unwrap();
+        assert_eq!(
+            highlight("Hello big world, tokens and Tokio!", &query),
+            "Hello <mark>big</mark> <mark>world</mark>, <mark>tokens</mark> and <mark>Tokio</mark>"
+        );
+        let text = (0..40).map(|i| format!("w{}", i)).collect::<Vec<_>>();
+        let query = SearchQuery::parse("w20").unwrap();
+        let snippet = highlight(&text.join(" "), &query);
+        assert!(snippet.starts_with("…w12 "), "{}", snippet);
+        assert!(snippet.contains("<mark>w20</mark>"), "{}", snippet);
+        assert!(snippet.ends_with("w39"), "{}", snippet);
+        assert_eq!(highlight("", &query), "");
+    }
+}
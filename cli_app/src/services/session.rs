@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres, Row, Sqlite, sqlite::SqliteRow};
+use tokio::sync::Mutex;
+
+use crate::{
+    model::Session,
+    services::error::{ServiceError, ServiceResult},
+    utils::token,
+};
+
+pub struct InMemorySessionService {
+    data: Mutex<HashMap<String, Session>>,
+}
+
+impl Default for InMemorySessionService {
+    fn default() -> Self {
+        InMemorySessionService {
+            data: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+pub struct PgSqlSessionService {
+    pub pool: Pool<Postgres>,
+}
+
+impl PgSqlSessionService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+pub struct SqliteSessionService {
+    pub pool: Pool<Sqlite>,
+}
+
+impl SqliteSessionService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    fn map_row(row: SqliteRow) -> Result<Session, sqlx::Error> {
+        Ok(Session {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            refresh_token_hash: row.try_get("refresh_token_hash")?,
+            expires_at: row.try_get("expires_at")?,
+            revoked_at: row.try_get("revoked_at")?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+            updated: row.try_get::<Option<_>, _>("updated")?.unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+pub trait SessionService: Send + Sync {
+    async fn create_session(
+        &self,
+        user_id: i64,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session>;
+    async fn get_session(&self, id: &str) -> ServiceResult<Session>;
+    async fn get_session_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> ServiceResult<Session>;
+    /// 替换刷新令牌，仅当会话仍使用 `current_hash` 且未撤销时成功，防止同一刷新令牌被重复使用
+    async fn rotate_refresh_token(
+        &self,
+        id: &str,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session>;
+    async fn revoke_session(&self, id: &str) -> ServiceResult<()>;
+    /// 撤销用户的所有会话，返回被撤销的会话数量
+    async fn revoke_user_sessions(&self, user_id: i64) -> ServiceResult<u64>;
+}
+
+#[async_trait]
+impl SessionService for InMemorySessionService {
+    async fn create_session(
+        &self,
+        user_id: i64,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session> {
+        let mut data = self.data.lock().await;
+        let ts = Utc::now();
+        let session = Session {
+            id: token::generate_token(16),
+            user_id,
+            refresh_token_hash: refresh_token_hash.to_string(),
+            expires_at,
+            revoked_at: None,
+            created: ts,
+            updated: ts,
+        };
+        data.insert(session.id.clone(), session.clone());
+        Ok(session)
+    }
+
+    async fn get_session(&self, id: &str) -> ServiceResult<Session> {
+        let data = self.data.lock().await;
+        match data.get(id) {
+            None => Err(ServiceError::NotFound(format!("Session not found:{}", id))),
+            Some(session) => Ok(session.clone()),
+        }
+    }
+
+    async fn get_session_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> ServiceResult<Session> {
+        let data = self.data.lock().await;
+        match data
+            .values()
+            .find(|session| session.refresh_token_hash == refresh_token_hash)
+        {
+            None => Err(ServiceError::NotFound("Session not found".to_string())),
+            Some(session) => Ok(session.clone()),
+        }
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: &str,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session> {
+        let mut data = self.data.lock().await;
+        let Some(session) = data.get_mut(id) else {
+            return Err(ServiceError::NotFound(format!("Session not found:{}", id)));
+        };
+        if session.revoked_at.is_some() || session.refresh_token_hash != current_hash {
+            return Err(ServiceError::Conflict(
+                "Refresh token has already been used".to_string(),
+            ));
+        }
+        session.refresh_token_hash = new_hash.to_string();
+        session.expires_at = expires_at;
+        session.updated = Utc::now();
+        Ok(session.clone())
+    }
+
+    async fn revoke_session(&self, id: &str) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        let Some(session) = data.get_mut(id) else {
+            return Err(ServiceError::NotFound(format!("Session not found:{}", id)));
+        };
+        let ts = Utc::now();
+        session.revoked_at.get_or_insert(ts);
+        session.updated = ts;
+        Ok(())
+    }
+
+    async fn revoke_user_sessions(&self, user_id: i64) -> ServiceResult<u64> {
+        let mut data = self.data.lock().await;
+        let ts = Utc::now();
+        let mut count = 0;
+        for session in data
+            .values_mut()
+            .filter(|session| session.user_id == user_id && session.revoked_at.is_none())
+        {
+            session.revoked_at = Some(ts);
+            session.updated = ts;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl SessionService for PgSqlSessionService {
+    async fn create_session(
+        &self,
+        user_id: i64,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session> {
+        let id = token::generate_token(16);
+        let query = sqlx::query!(
+            r#"
+                insert into sessions(id,user_id,refresh_token_hash,expires_at,created,updated)
+                values($1,$2,$3,$4,Now(),Now())
+            "#,
+            id,
+            user_id,
+            refresh_token_hash,
+            expires_at
+        );
+        query.execute(&self.pool).await?;
+        self.get_session(&id).await
+    }
+
+    async fn get_session(&self, id: &str) -> ServiceResult<Session> {
+        let res = sqlx::query!(
+            r#"
+            SELECT id, user_id, refresh_token_hash, expires_at, revoked_at, created, updated
+            FROM sessions
+            WHERE id = $1
+            "#,
+            id
+        );
+        res.fetch_one(&self.pool)
+            .await
+            .map(|row| Session {
+                id: row.id,
+                user_id: row.user_id,
+                refresh_token_hash: row.refresh_token_hash,
+                expires_at: row.expires_at,
+                revoked_at: row.revoked_at,
+                created: row.created.unwrap_or_default(),
+                updated: row.updated.unwrap_or_default(),
+            })
+            .map_err(|e| ServiceError::or_not_found(e, format!("Session not found:{}", id)))
+    }
+
+    async fn get_session_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> ServiceResult<Session> {
+        let res = sqlx::query!(
+            r#"
+            SELECT id, user_id, refresh_token_hash, expires_at, revoked_at, created, updated
+            FROM sessions
+            WHERE refresh_token_hash = $1
+            "#,
+            refresh_token_hash
+        );
+        res.fetch_one(&self.pool)
+            .await
+            .map(|row| Session {
+                id: row.id,
+                user_id: row.user_id,
+                refresh_token_hash: row.refresh_token_hash,
+                expires_at: row.expires_at,
+                revoked_at: row.revoked_at,
+                created: row.created.unwrap_or_default(),
+                updated: row.updated.unwrap_or_default(),
+            })
+            .map_err(|e| ServiceError::or_not_found(e, "Session not found"))
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: &str,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session> {
+        let query = sqlx::query!(
+            r#"
+                update sessions
+                set refresh_token_hash = $1, expires_at = $2, updated = Now()
+                where id = $3 and refresh_token_hash = $4 and revoked_at is null
+            "#,
+            new_hash,
+            expires_at,
+            id,
+            current_hash
+        );
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::Conflict(
+                "Refresh token has already been used".to_string(),
+            ));
+        }
+        self.get_session(id).await
+    }
+
+    async fn revoke_session(&self, id: &str) -> ServiceResult<()> {
+        let query = sqlx::query!(
+            r#"
+                update sessions
+                set revoked_at = coalesce(revoked_at, Now()), updated = Now()
+                where id = $1
+            "#,
+            id
+        );
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Session not found:{}", id)));
+        }
+        Ok(())
+    }
+
+    async fn revoke_user_sessions(&self, user_id: i64) -> ServiceResult<u64> {
+        let query = sqlx::query!(
+            r#"
+                update sessions
+                set revoked_at = Now(), updated = Now()
+                where user_id = $1 and revoked_at is null
+            "#,
+            user_id
+        );
+        Ok(query.execute(&self.pool).await?.rows_affected())
+    }
+}
+
+#[async_trait]
+impl SessionService for SqliteSessionService {
+    async fn create_session(
+        &self,
+        user_id: i64,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session> {
+        let id = token::generate_token(16);
+        let ts = Utc::now();
+        let query = sqlx::query(
+            r#"
+                insert into sessions(id,user_id,refresh_token_hash,expires_at,created,updated)
+                values($1,$2,$3,$4,$5,$5)
+            "#,
+        )
+        .bind(&id)
+        .bind(user_id)
+        .bind(refresh_token_hash)
+        .bind(expires_at)
+        .bind(ts);
+        query.execute(&self.pool).await?;
+        self.get_session(&id).await
+    }
+
+    async fn get_session(&self, id: &str) -> ServiceResult<Session> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, user_id, refresh_token_hash, expires_at, revoked_at, created, updated
+            FROM sessions
+            WHERE id = $1
+            "#,
+        )
+        .bind(id);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("Session not found:{}", id)))
+    }
+
+    async fn get_session_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> ServiceResult<Session> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, user_id, refresh_token_hash, expires_at, revoked_at, created, updated
+            FROM sessions
+            WHERE refresh_token_hash = $1
+            "#,
+        )
+        .bind(refresh_token_hash);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, "Session not found"))
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: &str,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ServiceResult<Session> {
+        let query = sqlx::query(
+            r#"
+                update sessions
+                set refresh_token_hash = $1, expires_at = $2, updated = $3
+                where id = $4 and refresh_token_hash = $5 and revoked_at is null
+            "#,
+        )
+        .bind(new_hash)
+        .bind(expires_at)
+        .bind(Utc::now())
+        .bind(id)
+        .bind(current_hash);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::Conflict(
+                "Refresh token has already been used".to_string(),
+            ));
+        }
+        self.get_session(id).await
+    }
+
+    async fn revoke_session(&self, id: &str) -> ServiceResult<()> {
+        let query = sqlx::query(
+            r#"
+                update sessions
+                set revoked_at = coalesce(revoked_at, $1), updated = $1
+                where id = $2
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(id);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!("Session not found:{}", id)));
+        }
+        Ok(())
+    }
+
+    async fn revoke_user_sessions(&self, user_id: i64) -> ServiceResult<u64> {
+        let query = sqlx::query(
+            r#"
+                update sessions
+                set revoked_at = $1, updated = $1
+                where user_id = $2 and revoked_at is null
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(user_id);
+        Ok(query.execute(&self.pool).await?.rows_affected())
+    }
+}
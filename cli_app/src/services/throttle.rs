@@ -0,0 +1,227 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    model::{Lockout, LockoutKind},
+    settings::LoginProtection,
+};
+
+type Key = (LockoutKind, String);
+
+struct Attempts {
+    failures: u32,
+    last_failure: DateTime<Utc>,
+}
+
+/// 按用户名和来源 IP 记录登录失败次数。
+/// 记录只保存在进程内存中，多实例部署时各实例分别计数。
+#[derive(Default)]
+pub struct LoginThrottle {
+    attempts: Mutex<HashMap<Key, Attempts>>,
+}
+
+impl LoginThrottle {
+    /// 返回允许再次尝试登录的时间，未被限制时返回 `None`
+    pub fn check(&self, keys: &[Key], settings: &LoginProtection) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        let mut attempts = self.attempts.lock().unwrap();
+        prune(&mut attempts, settings, now);
+        keys.iter()
+            .filter_map(|key| attempts.get(key).map(|a| retry_after(key.0, a, settings)))
+            .filter(|ts| *ts > now)
+            .max()
+    }
+
+    pub fn record_failure(&self, keys: &[Key], settings: &LoginProtection) {
+        let now = Utc::now();
+        let mut attempts = self.attempts.lock().unwrap();
+        prune(&mut attempts, settings, now);
+        for key in keys {
+            let entry = attempts.entry(key.clone()).or_insert(Attempts {
+                failures: 0,
+                last_failure: now,
+            });
+            entry.failures += 1;
+            entry.last_failure = now;
+        }
+    }
+
+    /// 登录成功后清除该用户名的失败记录，IP 的记录保留到过期
+    pub fn record_success(&self, username: &str) {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.remove(&(LockoutKind::Username, username.to_string()));
+    }
+
+    pub fn lockouts(&self, settings: &LoginProtection) -> Vec<Lockout> {
+        let now = Utc::now();
+        let mut attempts = self.attempts.lock().unwrap();
+        prune(&mut attempts, settings, now);
+        let mut lockouts: Vec<Lockout> = attempts
+            .iter()
+            .map(|((kind, value), a)| Lockout {
+                kind: *kind,
+                value: value.clone(),
+                failures: a.failures,
+                last_failure: a.last_failure,
+                retry_after: retry_after(*kind, a, settings),
+                locked_until: (a.failures >= max_failures(*kind, settings))
+                    .then(|| retry_after(*kind, a, settings)),
+            })
+            .collect();
+        lockouts.sort_by_key(|l| std::cmp::Reverse(l.last_failure));
+        lockouts
+    }
+
+    /// 清除失败记录，记录不存在时返回 `false`
+    pub fn clear(&self, kind: LockoutKind, value: &str) -> bool {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.remove(&(kind, value.to_string())).is_some()
+    }
+}
+
+fn max_failures(kind: LockoutKind, settings: &LoginProtection) -> u32 {
+    match kind {
+        LockoutKind::Username => settings.max_failures,
+        LockoutKind::Ip => settings.max_ip_failures,
+    }
+}
+
+/// 用户名每次失败后退避；同一 IP 可能有多个用户，只在达到上限后锁定
+fn retry_after(
+    kind: LockoutKind,
+    attempts: &Attempts,
+    settings: &LoginProtection,
+) -> DateTime<Utc> {
+    let delay = if attempts.failures >= max_failures(kind, settings) {
+        settings.lockout_seconds
+    } else if kind == LockoutKind::Ip {
+        0
+    } else {
+        let factor = 1i64 << attempts.failures.saturating_sub(1).min(30);
+        settings
+            .backoff_seconds
+            .saturating_mul(factor)
+            .min(settings.lockout_seconds)
+    };
+    attempts.last_failure + Duration::seconds(delay)
+}
+
+/// 超过锁定时间没有再失败的记录视为过期
+fn prune(attempts: &mut HashMap<Key, Attempts>, settings: &LoginProtection, now: DateTime<Utc>) {
+    let window = Duration::seconds(settings.lockout_seconds);
+    attempts.retain(|_, a| a.last_failure + window > now);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> LoginProtection {
+        LoginProtection {
+            max_failures: 5,
+            max_ip_failures: 20,
+            backoff_seconds: 2,
+            lockout_seconds: 60,
+        }
+    }
+
+    fn delay(kind: LockoutKind, failures: u32) -> i64 {
+        let attempts = Attempts {
+            failures,
+            last_failure: Utc::now(),
+        };
+        (retry_after(kind, &attempts, &settings()) - attempts.last_failure).num_seconds()
+    }
+
+    fn key(kind: LockoutKind, value: &str) -> Key {
+        (kind, value.to_string())
+    }
+
+    #[test]
+    fn username_backoff_doubles_until_the_lockout() {
+        let delays: Vec<i64> = (1..=6).map(|n| delay(LockoutKind::Username, n)).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 60, 60]);
+        //退避时间不超过锁定时间，失败次数很大时也不会溢出
+        let settings = LoginProtection {
+            max_failures: u32::MAX,
+            ..settings()
+        };
+        let attempts = Attempts {
+            failures: 200,
+            last_failure: Utc::now(),
+        };
+        let retry = retry_after(LockoutKind::Username, &attempts, &settings);
+        assert_eq!((retry - attempts.last_failure).num_seconds(), 60);
+    }
+
+    #[test]
+    fn ip_is_only_limited_after_max_failures() {
+        assert_eq!(delay(LockoutKind::Ip, 1), 0);
+        assert_eq!(delay(LockoutKind::Ip, 19), 0);
+        assert_eq!(delay(LockoutKind::Ip, 20), 60);
+    }
+
+    #[test]
+    fn failures_are_counted_and_cleared() {
+        let throttle = LoginThrottle::default();
+        let settings = settings();
+        let keys = [
+            key(LockoutKind::Username, "alice"),
+            key(LockoutKind::Ip, "127.0.0.1"),
+        ];
+        assert!(throttle.check(&keys, &settings).is_none());
+
+        throttle.record_failure(&keys, &settings);
+        assert!(throttle.check(&keys, &settings).is_some());
+        let lockouts = throttle.lockouts(&settings);
+        assert_eq!(lockouts.len(), 2);
+        assert!(lockouts.iter().all(|l| l.locked_until.is_none()));
+
+        //成功登录只清除用户名的记录
+        throttle.record_success("alice");
+        assert!(throttle.check(&keys, &settings).is_none());
+        assert_eq!(throttle.lockouts(&settings).len(), 1);
+        assert!(throttle.clear(LockoutKind::Ip, "127.0.0.1"));
+        assert!(!throttle.clear(LockoutKind::Ip, "127.0.0.1"));
+    }
+
+    #[test]
+    fn lockout_expires_after_lockout_seconds() {
+        let throttle = LoginThrottle::default();
+        let settings = settings();
+        let now = Utc::now();
+        {
+            let mut attempts = throttle.attempts.lock().unwrap();
+            attempts.insert(
+                key(LockoutKind::Username, "alice"),
+                Attempts {
+                    failures: 5,
+                    last_failure: now - Duration::seconds(59),
+                },
+            );
+            attempts.insert(
+                key(LockoutKind::Username, "bob"),
+                Attempts {
+                    failures: 5,
+                    last_failure: now - Duration::seconds(61),
+                },
+            );
+        }
+
+        let locked = throttle
+            .check(&[key(LockoutKind::Username, "alice")], &settings)
+            .unwrap();
+        assert!(locked > now && locked <= now + Duration::seconds(1));
+        assert!(
+            throttle
+                .check(&[key(LockoutKind::Username, "bob")], &settings)
+                .is_none()
+        );
+        //过期的记录会被清除
+        let lockouts = throttle.lockouts(&settings);
+        assert_eq!(lockouts.len(), 1);
+        assert_eq!(lockouts[0].value, "alice");
+        assert!(lockouts[0].locked_until.is_some());
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres, Row, Sqlite, sqlite::SqliteRow};
+use tokio::sync::Mutex;
+
+use crate::{model::TwoFactor, services::error::ServiceResult};
+
+/// 内存中的恢复码，键为恢复码哈希
+struct RecoveryCode {
+    user_id: i64,
+    used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct InMemoryTwoFactorStore {
+    settings: HashMap<i64, TwoFactor>,
+    recovery_codes: HashMap<String, RecoveryCode>,
+}
+
+pub struct InMemoryTwoFactorService {
+    data: Mutex<InMemoryTwoFactorStore>,
+}
+
+impl Default for InMemoryTwoFactorService {
+    fn default() -> Self {
+        InMemoryTwoFactorService {
+            data: Mutex::new(InMemoryTwoFactorStore::default()),
+        }
+    }
+}
+
+pub struct PgSqlTwoFactorService {
+    pub pool: Pool<Postgres>,
+}
+
+impl PgSqlTwoFactorService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+pub struct SqliteTwoFactorService {
+    pub pool: Pool<Sqlite>,
+}
+
+impl SqliteTwoFactorService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    fn map_row(row: SqliteRow) -> Result<TwoFactor, sqlx::Error> {
+        Ok(TwoFactor {
+            user_id: row.try_get("user_id")?,
+            secret: row.try_get("secret")?,
+            enabled_at: row.try_get("enabled_at")?,
+            last_step: row.try_get("last_step")?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+pub trait TwoFactorService: Send + Sync {
+    async fn get_two_factor(&self, user_id: i64) -> ServiceResult<Option<TwoFactor>>;
+    /// 登记新的加密密钥，替换之前未启用的密钥，已启用时返回 `false`
+    async fn start_enrollment(&self, user_id: i64, secret: &str) -> ServiceResult<bool>;
+    /// 启用两步验证并替换恢复码，没有待启用的密钥时返回 `false`
+    async fn enable_two_factor(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ServiceResult<bool>;
+    /// 记录已使用的时间步，该时间步不晚于上次使用的时间步时返回 `false`
+    async fn use_step(&self, user_id: i64, step: i64) -> ServiceResult<bool>;
+    /// 将恢复码标记为已使用，恢复码不存在或已使用时返回 `false`
+    async fn use_recovery_code(&self, user_id: i64, code_hash: &str) -> ServiceResult<bool>;
+    /// 关闭两步验证，同时删除密钥和恢复码
+    async fn disable_two_factor(&self, user_id: i64) -> ServiceResult<()>;
+}
+
+#[async_trait]
+impl TwoFactorService for InMemoryTwoFactorService {
+    async fn get_two_factor(&self, user_id: i64) -> ServiceResult<Option<TwoFactor>> {
+        let data = self.data.lock().await;
+        Ok(data.settings.get(&user_id).cloned())
+    }
+
+    async fn start_enrollment(&self, user_id: i64, secret: &str) -> ServiceResult<bool> {
+        let mut data = self.data.lock().await;
+        if data
+            .settings
+            .get(&user_id)
+            .is_some_and(TwoFactor::is_enabled)
+        {
+            return Ok(false);
+        }
+        data.settings.insert(
+            user_id,
+            TwoFactor {
+                user_id,
+                secret: secret.to_string(),
+                enabled_at: None,
+                last_step: None,
+                created: Utc::now(),
+            },
+        );
+        Ok(true)
+    }
+
+    async fn enable_two_factor(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ServiceResult<bool> {
+        let mut data = self.data.lock().await;
+        let Some(settings) = data
+            .settings
+            .get_mut(&user_id)
+            .filter(|settings| !settings.is_enabled())
+        else {
+            return Ok(false);
+        };
+        settings.enabled_at = Some(Utc::now());
+        settings.last_step = Some(step);
+        data.recovery_codes
+            .retain(|_, code| code.user_id != user_id);
+        for hash in recovery_code_hashes {
+            data.recovery_codes.insert(
+                hash.clone(),
+                RecoveryCode {
+                    user_id,
+                    used_at: None,
+                },
+            );
+        }
+        Ok(true)
+    }
+
+    async fn use_step(&self, user_id: i64, step: i64) -> ServiceResult<bool> {
+        let mut data = self.data.lock().await;
+        match data.settings.get_mut(&user_id).filter(|settings| {
+            settings.is_enabled() && settings.last_step.is_none_or(|last| last < step)
+        }) {
+            None => Ok(false),
+            Some(settings) => {
+                settings.last_step = Some(step);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn use_recovery_code(&self, user_id: i64, code_hash: &str) -> ServiceResult<bool> {
+        let mut data = self.data.lock().await;
+        match data
+            .recovery_codes
+            .get_mut(code_hash)
+            .filter(|code| code.user_id == user_id && code.used_at.is_none())
+        {
+            None => Ok(false),
+            Some(code) => {
+                code.used_at = Some(Utc::now());
+                Ok(true)
+            }
+        }
+    }
+
+    async fn disable_two_factor(&self, user_id: i64) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        data.settings.remove(&user_id);
+        data.recovery_codes
+            .retain(|_, code| code.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TwoFactorService for PgSqlTwoFactorService {
+    async fn get_two_factor(&self, user_id: i64) -> ServiceResult<Option<TwoFactor>> {
+        let res = sqlx::query!(
+            r#"
+            SELECT user_id, secret, enabled_at, last_step, created
+            FROM user_totp
+            WHERE user_id = $1
+            "#,
+            user_id
+        );
+        Ok(res.fetch_optional(&self.pool).await?.map(|row| TwoFactor {
+            user_id: row.user_id,
+            secret: row.secret,
+            enabled_at: row.enabled_at,
+            last_step: row.last_step,
+            created: row.created.unwrap_or_default(),
+        }))
+    }
+
+    async fn start_enrollment(&self, user_id: i64, secret: &str) -> ServiceResult<bool> {
+        let query = sqlx::query!(
+            r#"
+                insert into user_totp(user_id,secret,created)
+                values($1,$2,Now())
+                on conflict (user_id) do update
+                set secret = excluded.secret, last_step = null, created = excluded.created
+                where user_totp.enabled_at is null
+            "#,
+            user_id,
+            secret
+        );
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    async fn enable_two_factor(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ServiceResult<bool> {
+        let mut tx = self.pool.begin().await?;
+        let enabled = sqlx::query!(
+            r#"
+                update user_totp
+                set enabled_at = Now(), last_step = $2
+                where user_id = $1 and enabled_at is null
+            "#,
+            user_id,
+            step
+        )
+        .execute(&mut *tx)
+        .await?;
+        if enabled.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query!("delete from recovery_codes where user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        for hash in recovery_code_hashes {
+            sqlx::query!(
+                r#"
+                    insert into recovery_codes(code_hash,user_id,created)
+                    values($1,$2,Now())
+                "#,
+                hash,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn use_step(&self, user_id: i64, step: i64) -> ServiceResult<bool> {
+        //条件更新保证同一时间步的动态码只能使用一次
+        let query = sqlx::query!(
+            r#"
+                update user_totp
+                set last_step = $2
+                where user_id = $1 and enabled_at is not null
+                    and (last_step is null or last_step < $2)
+            "#,
+            user_id,
+            step
+        );
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    async fn use_recovery_code(&self, user_id: i64, code_hash: &str) -> ServiceResult<bool> {
+        let query = sqlx::query!(
+            r#"
+                update recovery_codes
+                set used_at = Now()
+                where code_hash = $1 and user_id = $2 and used_at is null
+            "#,
+            code_hash,
+            user_id
+        );
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    async fn disable_two_factor(&self, user_id: i64) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("delete from recovery_codes where user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("delete from user_totp where user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TwoFactorService for SqliteTwoFactorService {
+    async fn get_two_factor(&self, user_id: i64) -> ServiceResult<Option<TwoFactor>> {
+        let res = sqlx::query(
+            r#"
+            SELECT user_id, secret, enabled_at, last_step, created
+            FROM user_totp
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id);
+        Ok(res
+            .try_map(Self::map_row)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn start_enrollment(&self, user_id: i64, secret: &str) -> ServiceResult<bool> {
+        let query = sqlx::query(
+            r#"
+                insert into user_totp(user_id,secret,created)
+                values($1,$2,$3)
+                on conflict (user_id) do update
+                set secret = excluded.secret, last_step = null, created = excluded.created
+                where user_totp.enabled_at is null
+            "#,
+        )
+        .bind(user_id)
+        .bind(secret)
+        .bind(Utc::now());
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    async fn enable_two_factor(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ServiceResult<bool> {
+        let ts = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let enabled = sqlx::query(
+            r#"
+                update user_totp
+                set enabled_at = $1, last_step = $2
+                where user_id = $3 and enabled_at is null
+            "#,
+        )
+        .bind(ts)
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        if enabled.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query("delete from recovery_codes where user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        for hash in recovery_code_hashes {
+            sqlx::query(
+                r#"
+                    insert into recovery_codes(code_hash,user_id,created)
+                    values($1,$2,$3)
+                "#,
+            )
+            .bind(hash)
+            .bind(user_id)
+            .bind(ts)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn use_step(&self, user_id: i64, step: i64) -> ServiceResult<bool> {
+        //条件更新保证同一时间步的动态码只能使用一次
+        let query = sqlx::query(
+            r#"
+                update user_totp
+                set last_step = $2
+                where user_id = $1 and enabled_at is not null
+                    and (last_step is null or last_step < $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(step);
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    async fn use_recovery_code(&self, user_id: i64, code_hash: &str) -> ServiceResult<bool> {
+        let query = sqlx::query(
+            r#"
+                update recovery_codes
+                set used_at = $1
+                where code_hash = $2 and user_id = $3 and used_at is null
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(code_hash)
+        .bind(user_id);
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    async fn disable_two_factor(&self, user_id: i64) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("delete from recovery_codes where user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("delete from user_totp where user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -1,14 +1,102 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use sqlx::{Pool, Postgres};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    Database, Encode, Pool, Postgres, QueryBuilder, Row, Sqlite, Transaction, Type,
+    postgres::PgRow, sqlite::SqliteRow,
+};
 use tokio::sync::Mutex;
 
 use crate::{
-    api::request::user::{CreateUserRequest, UpdateUserRequest},
-    model::{User, UserStatus},
-    utils::password,
+    api::request::user::{CreateUserRequest, PatchUserRequest, UpdateUserRequest},
+    model::{Role, User, UserStatus},
+    services::{
+        comment::CommentService,
+        error::{ServiceError, ServiceResult},
+        pagination::{Page, PageRequest},
+        post::PostService,
+    },
+    utils::password::PasswordHasher,
 };
 
+/// 用户列表的过滤条件，`created_after` 包含边界，`created_before` 不包含
+#[derive(Clone, Default)]
+pub struct UserFilter {
+    /// 为 `true` 时只返回回收站中的用户，否则只返回未删除的用户
+    pub trashed: bool,
+    pub status: Option<UserStatus>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl UserFilter {
+    fn matches(&self, user: &User) -> bool {
+        user.deleted_at.is_some() == self.trashed
+            && self.status.is_none_or(|status| user.status == status)
+            && self.created_after.is_none_or(|ts| user.created >= ts)
+            && self.created_before.is_none_or(|ts| user.created < ts)
+    }
+
+    fn push_sql<'a, DB>(&self, builder: &mut QueryBuilder<'a, DB>)
+    where
+        DB: Database,
+        i32: Encode<'a, DB> + Type<DB>,
+        DateTime<Utc>: Encode<'a, DB> + Type<DB>,
+    {
+        builder.push(if self.trashed {
+            " AND deleted_at IS NOT NULL"
+        } else {
+            " AND deleted_at IS NULL"
+        });
+        if let Some(status) = self.status {
+            builder.push(" AND status = ").push_bind(i32::from(status));
+        }
+        if let Some(ts) = self.created_after {
+            builder.push(" AND created >= ").push_bind(ts);
+        }
+        if let Some(ts) = self.created_before {
+            builder.push(" AND created < ").push_bind(ts);
+        }
+    }
+}
+
+/// 删除用户时对其名下文章的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostsDisposal {
+    /// 名下还有文章（包括回收站中的文章）时拒绝删除
+    Block,
+    /// 文章随用户移入回收站，永久删除用户时文章也被永久删除
+    Cascade,
+    /// 文章转给指定的用户
+    Reassign(i64),
+}
+
+fn posts_remain(count: i64) -> ServiceError {
+    ServiceError::Conflict(format!(
+        "User still owns {} posts, use posts=cascade or posts=reassign",
+        count
+    ))
+}
+
+/// 接收文章的用户不存在属于请求参数错误，而不是被删除的用户不存在
+fn reassign_target_missing(id: i64) -> ServiceError {
+    ServiceError::Validation(format!("User not found:{}", id))
+}
+
+const SELECT_USERS: &str = r#"
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
+            FROM users
+            WHERE 1 = 1"#;
+const COUNT_USERS: &str = "SELECT COUNT(*) FROM users WHERE 1 = 1";
+
+/// 新密码先按强度策略检查再计算哈希
+fn hash_new_password(hasher: &PasswordHasher, password: &str) -> ServiceResult<String> {
+    hasher.check_policy(password)?;
+    Ok(hasher.hash(password)?)
+}
+
 pub struct InmemoryUserStore {
     pub counter: i64,
     pub items: HashMap<i64, User>,
@@ -16,195 +104,755 @@ pub struct InmemoryUserStore {
 
 pub struct InMemoryUserService {
     data: Mutex<InmemoryUserStore>,
+    hasher: PasswordHasher,
+    /// 删除用户时处理其名下的文章
+    posts: Arc<dyn PostService>,
+    /// 永久删除用户时保留其评论并清除作者
+    comments: Arc<dyn CommentService>,
 }
 
-impl Default for InMemoryUserService {
-    fn default() -> Self {
+impl InmemoryUserStore {
+    /// 与数据库的 `lower(email)` 唯一索引保持一致，比较时不区分大小写
+    fn check_email(&self, id: Option<i64>, email: Option<&str>) -> ServiceResult<()> {
+        let Some(email) = email else {
+            return Ok(());
+        };
+        if self.items.values().any(|user| {
+            Some(user.id) != id
+                && user
+                    .email
+                    .as_deref()
+                    .is_some_and(|other| other.eq_ignore_ascii_case(email))
+        }) {
+            return Err(ServiceError::Conflict(format!(
+                "Email already exists:{}",
+                email
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl InMemoryUserService {
+    pub fn new(
+        hasher: PasswordHasher,
+        posts: Arc<dyn PostService>,
+        comments: Arc<dyn CommentService>,
+    ) -> Self {
         InMemoryUserService {
             data: Mutex::new(InmemoryUserStore {
                 counter: 0,
                 items: HashMap::new(),
             }),
+            hasher,
+            posts,
+            comments,
+        }
+    }
+
+    /// 调用方持有用户存储的锁，文章处理失败时用户保持不变
+    async fn dispose_posts(
+        &self,
+        data: &InmemoryUserStore,
+        id: i64,
+        posts: PostsDisposal,
+        purge: bool,
+    ) -> ServiceResult<()> {
+        match posts {
+            PostsDisposal::Block => {
+                let count = self.posts.count_posts_by_author(id).await?;
+                if count > 0 {
+                    return Err(posts_remain(count));
+                }
+            }
+            PostsDisposal::Cascade if purge => {
+                self.posts.purge_posts_by_author(id).await?;
+            }
+            PostsDisposal::Cascade => {
+                self.posts.trash_posts_by_author(id).await?;
+            }
+            PostsDisposal::Reassign(to) => {
+                if data
+                    .items
+                    .get(&to)
+                    .is_none_or(|user| user.deleted_at.is_some())
+                {
+                    return Err(reassign_target_missing(to));
+                }
+                self.posts.reassign_posts(id, to).await?;
+            }
         }
+        Ok(())
     }
 }
 
 pub struct PgSqlUserService {
     pub pool: Pool<Postgres>,
+    hasher: PasswordHasher,
 }
 
 impl PgSqlUserService {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool<Postgres>, hasher: PasswordHasher) -> Self {
+        Self { pool, hasher }
+    }
+
+    /// 在删除用户的事务中处理其名下的文章
+    async fn dispose_posts(
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        posts: PostsDisposal,
+        purge: bool,
+    ) -> ServiceResult<()> {
+        match posts {
+            PostsDisposal::Block => {
+                let count = sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) AS "count!" FROM posts WHERE author_id = $1"#,
+                    id
+                )
+                .fetch_one(&mut **tx)
+                .await?;
+                if count > 0 {
+                    return Err(posts_remain(count));
+                }
+            }
+            PostsDisposal::Cascade if purge => {
+                sqlx::query!("delete from posts where author_id = $1", id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            PostsDisposal::Cascade => {
+                sqlx::query!(
+                    r#"
+                        update posts
+                        set deleted_at = Now(), version = version + 1, updated = Now()
+                        where author_id = $1 and deleted_at is null
+                    "#,
+                    id
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            PostsDisposal::Reassign(to) => {
+                //锁定接收文章的用户，避免其在事务提交前被删除
+                let found = sqlx::query_scalar!(
+                    "select id from users where id = $1 and deleted_at is null for update",
+                    to
+                )
+                .fetch_optional(&mut **tx)
+                .await?;
+                if found.is_none() {
+                    return Err(reassign_target_missing(to));
+                }
+                sqlx::query!(
+                    r#"
+                        update posts
+                        set author_id = $2, version = version + 1, updated = Now()
+                        where author_id = $1
+                    "#,
+                    id,
+                    to
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn map_row(row: PgRow) -> Result<User, sqlx::Error> {
+        Ok(User {
+            id: row.try_get("id")?,
+            username: row.try_get("username")?,
+            email: row.try_get("email")?,
+            password: row.try_get("password")?,
+            status: UserStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            role: Role::try_from(row.try_get::<i32, _>("role")?)?,
+            version: row.try_get("version")?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+            updated: row.try_get::<Option<_>, _>("updated")?.unwrap_or_default(),
+            last_login: row.try_get("last_login")?,
+            deleted_at: row.try_get("deleted_at")?,
+        })
+    }
+
+    /// 条件写入没有更新任何行时，区分记录不存在和版本不一致
+    async fn write_failed(&self, id: i64) -> ServiceError {
+        match self.get_user_by_id(id).await {
+            Ok(_) => ServiceError::PreconditionFailed(format!("User has been modified:{}", id)),
+            Err(err) => err,
+        }
+    }
+}
+
+pub struct SqliteUserService {
+    pub pool: Pool<Sqlite>,
+    hasher: PasswordHasher,
+}
+
+impl SqliteUserService {
+    pub fn new(pool: Pool<Sqlite>, hasher: PasswordHasher) -> Self {
+        Self { pool, hasher }
+    }
+
+    /// 在删除用户的事务中处理其名下的文章
+    async fn dispose_posts(
+        tx: &mut Transaction<'_, Sqlite>,
+        id: i64,
+        posts: PostsDisposal,
+        purge: bool,
+    ) -> ServiceResult<()> {
+        match posts {
+            PostsDisposal::Block => {
+                let count: i64 =
+                    sqlx::query_scalar("SELECT COUNT(*) FROM posts WHERE author_id = $1")
+                        .bind(id)
+                        .fetch_one(&mut **tx)
+                        .await?;
+                if count > 0 {
+                    return Err(posts_remain(count));
+                }
+            }
+            PostsDisposal::Cascade if purge => {
+                sqlx::query("delete from posts where author_id = $1")
+                    .bind(id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            PostsDisposal::Cascade => {
+                let ts = chrono::offset::Utc::now();
+                sqlx::query(
+                    r#"
+                        update posts
+                        set deleted_at = $1, version = version + 1, updated = $1
+                        where author_id = $2 and deleted_at is null
+                    "#,
+                )
+                .bind(ts)
+                .bind(id)
+                .execute(&mut **tx)
+                .await?;
+            }
+            PostsDisposal::Reassign(to) => {
+                let found: Option<i64> =
+                    sqlx::query_scalar("select id from users where id = $1 and deleted_at is null")
+                        .bind(to)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                if found.is_none() {
+                    return Err(reassign_target_missing(to));
+                }
+                sqlx::query(
+                    r#"
+                        update posts
+                        set author_id = $1, version = version + 1, updated = $2
+                        where author_id = $3
+                    "#,
+                )
+                .bind(to)
+                .bind(chrono::offset::Utc::now())
+                .bind(id)
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn map_row(row: SqliteRow) -> Result<User, sqlx::Error> {
+        Ok(User {
+            id: row.try_get("id")?,
+            username: row.try_get("username")?,
+            email: row.try_get("email")?,
+            password: row.try_get("password")?,
+            status: UserStatus::try_from(row.try_get::<i32, _>("status")?)?,
+            role: Role::try_from(row.try_get::<i32, _>("role")?)?,
+            version: row.try_get("version")?,
+            created: row.try_get::<Option<_>, _>("created")?.unwrap_or_default(),
+            updated: row.try_get::<Option<_>, _>("updated")?.unwrap_or_default(),
+            last_login: row.try_get("last_login")?,
+            deleted_at: row.try_get("deleted_at")?,
+        })
+    }
+
+    /// 条件写入没有更新任何行时，区分记录不存在和版本不一致
+    async fn write_failed(&self, id: i64) -> ServiceError {
+        match self.get_user_by_id(id).await {
+            Ok(_) => ServiceError::PreconditionFailed(format!("User has been modified:{}", id)),
+            Err(err) => err,
+        }
     }
 }
 
-#[allow(async_fn_in_trait)]
-pub trait UserService {
-    async fn get_all_users(&self) -> anyhow::Result<Vec<User>>;
-    async fn get_user_by_id(&self, id: i64) -> anyhow::Result<User>;
-    async fn get_user_by_username(&self, username: &str) -> anyhow::Result<User>;
+#[async_trait]
+pub trait UserService: Send + Sync {
+    async fn list_users(&self, filter: UserFilter, page: PageRequest) -> ServiceResult<Page<User>>;
+    async fn get_user_by_id(&self, id: i64) -> ServiceResult<User>;
+    async fn get_user_by_username(&self, username: &str) -> ServiceResult<User>;
+    /// 按邮箱查找未删除的用户，不区分大小写
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<User>;
 
-    async fn create_user(&self, request: CreateUserRequest) -> anyhow::Result<User>;
-    async fn update_user(&self, id: i64, request: UpdateUserRequest) -> anyhow::Result<User>;
-    async fn delete_user(&self, id: i64) -> anyhow::Result<()>;
+    async fn create_user(&self, request: CreateUserRequest) -> ServiceResult<User>;
+    /// 写操作的 `version` 为客户端期望的版本，给出且与当前版本不一致时返回 `PreconditionFailed`
+    async fn update_user(
+        &self,
+        id: i64,
+        request: UpdateUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User>;
+    /// 只更新请求中给出的字段，未提供密码时保留原密码
+    async fn patch_user(
+        &self,
+        id: i64,
+        request: PatchUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User>;
+    /// 将用户移入回收站，回收站中的用户不会出现在列表和 `get_*` 查询中，
+    /// 其名下的文章按 `posts` 在同一事务中处理
+    async fn delete_user(
+        &self,
+        id: i64,
+        posts: PostsDisposal,
+        version: Option<i64>,
+    ) -> ServiceResult<()>;
+    async fn get_trashed_user(&self, id: i64) -> ServiceResult<User>;
+    /// 从回收站恢复用户
+    async fn restore_user(&self, id: i64) -> ServiceResult<User>;
+    /// 永久删除回收站中的用户，其名下的文章按 `posts` 在同一事务中处理
+    async fn purge_user(&self, id: i64, posts: PostsDisposal) -> ServiceResult<()>;
+    async fn set_role(&self, id: i64, role: Role) -> ServiceResult<User>;
+    /// 记录最近一次登录时间，不改变用户版本，避免登录使他人持有的 ETag 失效
+    async fn record_login(&self, id: i64) -> ServiceResult<()>;
+    /// 校验用户的密码，成功且哈希参数已过时时用当前参数重新计算哈希，不改变用户版本
+    async fn verify_password(&self, user: &User, password: &str) -> ServiceResult<bool>;
+    /// 检查新密码是否符合强度策略，写入密码的操作也会检查
+    fn check_password_policy(&self, password: &str) -> ServiceResult<()>;
 }
 
+#[async_trait]
 impl UserService for InMemoryUserService {
-    async fn get_all_users(&self) -> anyhow::Result<Vec<User>> {
+    async fn list_users(&self, filter: UserFilter, page: PageRequest) -> ServiceResult<Page<User>> {
         let data = self.data.lock().await;
-        Ok(data.items.values().map(|user| (*user).clone()).collect())
+        let users = data
+            .items
+            .values()
+            .filter(|user| filter.matches(user))
+            .cloned()
+            .collect();
+        Ok(page.paginate(users))
     }
 
-    async fn get_user_by_id(&self, id: i64) -> anyhow::Result<User> {
+    async fn get_user_by_id(&self, id: i64) -> ServiceResult<User> {
         let data = self.data.lock().await;
-        match data.items.get(&id) {
-            None => Err(anyhow::anyhow!("User not found:{}", id)),
+        match data.items.get(&id).filter(|user| user.deleted_at.is_none()) {
+            None => Err(ServiceError::NotFound(format!("User not found:{}", id))),
             Some(user) => Ok(user.clone()),
         }
     }
 
-    async fn get_user_by_username(&self, username: &str) -> anyhow::Result<User> {
+    async fn get_user_by_username(&self, username: &str) -> ServiceResult<User> {
         let data = self.data.lock().await;
         for (_id, user) in data.items.iter() {
-            if user.username == username {
+            if user.username == username && user.deleted_at.is_none() {
                 return Ok(user.clone());
             }
         }
-        anyhow::bail!("User not found:{}", username)
+        Err(ServiceError::NotFound(format!(
+            "User not found:{}",
+            username
+        )))
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<User> {
+        let data = self.data.lock().await;
+        data.items
+            .values()
+            .find(|user| {
+                user.deleted_at.is_none()
+                    && user
+                        .email
+                        .as_deref()
+                        .is_some_and(|other| other.eq_ignore_ascii_case(email))
+            })
+            .cloned()
+            .ok_or_else(|| ServiceError::NotFound(format!("User not found:{}", email)))
     }
 
-    async fn create_user(&self, request: CreateUserRequest) -> anyhow::Result<User> {
+    async fn create_user(&self, request: CreateUserRequest) -> ServiceResult<User> {
         let mut data = self.data.lock().await;
+        if data
+            .items
+            .values()
+            .any(|user| user.username == request.username)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Username already exists:{}",
+                request.username
+            )));
+        }
+        data.check_email(None, request.email.as_deref())?;
         data.counter += 1;
         let ts = chrono::offset::Utc::now();
         let user = User {
             id: data.counter,
             username: request.username,
-            password: password::encrypt_password(&request.password)?,
+            email: request.email,
+            password: hash_new_password(&self.hasher, &request.password)?,
             status: request.status,
+            role: Role::default(),
+            version: 1,
             created: ts,
             updated: ts,
             last_login: None,
+            deleted_at: None,
         };
         data.items.insert(user.id, user);
 
         match data.items.get(&data.counter) {
-            None => anyhow::bail!("User not found:{}", data.counter),
+            None => {
+                return Err(ServiceError::NotFound(format!(
+                    "User not found:{}",
+                    data.counter
+                )));
+            }
             Some(user) => Ok(user.clone()),
         }
     }
 
-    async fn update_user(&self, id: i64, request: UpdateUserRequest) -> anyhow::Result<User> {
+    async fn update_user(
+        &self,
+        id: i64,
+        request: UpdateUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User> {
         let mut data = self.data.lock().await;
-        let user = data.items.get_mut(&id).unwrap();
-
-        let last_login = user.last_login;
+        if data
+            .items
+            .values()
+            .any(|user| user.id != id && user.username == request.username)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Username already exists:{}",
+                request.username
+            )));
+        }
+        data.check_email(Some(id), request.email.as_deref())?;
+        let Some(user) = data
+            .items
+            .get_mut(&id)
+            .filter(|user| user.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("User not found:{}", id)));
+        };
+        if version.is_some_and(|version| version != user.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "User has been modified:{}",
+                id
+            )));
+        }
 
         user.username = request.username;
-        user.password = password::encrypt_password(&request.password)?;
+        user.email = request.email;
+        user.password = hash_new_password(&self.hasher, &request.password)?;
         user.status = request.status;
+        user.version += 1;
         user.updated = chrono::offset::Utc::now();
-        user.last_login = last_login;
 
-        match data.items.get(&data.counter) {
-            None => anyhow::bail!("User not found:{}", request.id),
+        Ok(user.clone())
+    }
+
+    async fn patch_user(
+        &self,
+        id: i64,
+        request: PatchUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User> {
+        let mut data = self.data.lock().await;
+        if let Some(username) = &request.username
+            && data
+                .items
+                .values()
+                .any(|user| user.id != id && &user.username == username)
+        {
+            return Err(ServiceError::Conflict(format!(
+                "Username already exists:{}",
+                username
+            )));
+        }
+        data.check_email(Some(id), request.email.as_deref())?;
+        let Some(user) = data
+            .items
+            .get_mut(&id)
+            .filter(|user| user.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("User not found:{}", id)));
+        };
+        if version.is_some_and(|version| version != user.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "User has been modified:{}",
+                id
+            )));
+        }
+
+        if let Some(username) = request.username {
+            user.username = username;
+        }
+        if let Some(email) = request.email {
+            user.email = Some(email);
+        }
+        if let Some(password) = request.password {
+            user.password = hash_new_password(&self.hasher, &password)?;
+        }
+        if let Some(status) = request.status {
+            user.status = status;
+        }
+        user.version += 1;
+        user.updated = chrono::offset::Utc::now();
+
+        Ok(user.clone())
+    }
+
+    async fn delete_user(
+        &self,
+        id: i64,
+        posts: PostsDisposal,
+        version: Option<i64>,
+    ) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        let Some(user) = data.items.get(&id).filter(|user| user.deleted_at.is_none()) else {
+            return Err(ServiceError::NotFound(format!("User not found:{}", id)));
+        };
+        if version.is_some_and(|version| version != user.version) {
+            return Err(ServiceError::PreconditionFailed(format!(
+                "User has been modified:{}",
+                id
+            )));
+        }
+        self.dispose_posts(&data, id, posts, false).await?;
+        if let Some(user) = data.items.get_mut(&id) {
+            let ts = chrono::offset::Utc::now();
+            user.deleted_at = Some(ts);
+            user.version += 1;
+            user.updated = ts;
+        }
+        Ok(())
+    }
+
+    async fn get_trashed_user(&self, id: i64) -> ServiceResult<User> {
+        let data = self.data.lock().await;
+        match data.items.get(&id).filter(|user| user.deleted_at.is_some()) {
+            None => Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            ))),
             Some(user) => Ok(user.clone()),
         }
     }
 
-    async fn delete_user(&self, id: i64) -> anyhow::Result<()> {
+    async fn restore_user(&self, id: i64) -> ServiceResult<User> {
+        let mut data = self.data.lock().await;
+        let Some(user) = data
+            .items
+            .get_mut(&id)
+            .filter(|user| user.deleted_at.is_some())
+        else {
+            return Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            )));
+        };
+        user.deleted_at = None;
+        user.version += 1;
+        user.updated = chrono::offset::Utc::now();
+        Ok(user.clone())
+    }
+
+    async fn purge_user(&self, id: i64, posts: PostsDisposal) -> ServiceResult<()> {
+        let mut data = self.data.lock().await;
+        if data
+            .items
+            .get(&id)
+            .is_none_or(|user| user.deleted_at.is_none())
+        {
+            return Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            )));
+        }
+        self.dispose_posts(&data, id, posts, true).await?;
+        //与数据库中 comments.author_id 的 ON DELETE SET NULL 保持一致
+        self.comments.clear_comment_author(id).await?;
+        data.items.remove(&id);
+        Ok(())
+    }
+
+    async fn set_role(&self, id: i64, role: Role) -> ServiceResult<User> {
+        let mut data = self.data.lock().await;
+        let Some(user) = data
+            .items
+            .get_mut(&id)
+            .filter(|user| user.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("User not found:{}", id)));
+        };
+        user.role = role;
+        user.version += 1;
+        user.updated = chrono::offset::Utc::now();
+        Ok(user.clone())
+    }
+
+    async fn record_login(&self, id: i64) -> ServiceResult<()> {
         let mut data = self.data.lock().await;
-        match data.items.remove(&id) {
-            None => anyhow::bail!("User not found:{}", id),
-            Some(_) => Ok(()),
+        let Some(user) = data
+            .items
+            .get_mut(&id)
+            .filter(|user| user.deleted_at.is_none())
+        else {
+            return Err(ServiceError::NotFound(format!("User not found:{}", id)));
+        };
+        user.last_login = Some(chrono::offset::Utc::now());
+        Ok(())
+    }
+
+    fn check_password_policy(&self, password: &str) -> ServiceResult<()> {
+        Ok(self.hasher.check_policy(password)?)
+    }
+
+    async fn verify_password(&self, user: &User, password: &str) -> ServiceResult<bool> {
+        if self.hasher.verify(password, &user.password).is_err() {
+            return Ok(false);
+        }
+        if self.hasher.needs_rehash(&user.password) {
+            let hash = self.hasher.hash(password)?;
+            let mut data = self.data.lock().await;
+            //只在哈希未被其他请求修改时替换
+            if let Some(stored) = data
+                .items
+                .get_mut(&user.id)
+                .filter(|stored| stored.password == user.password)
+            {
+                stored.password = hash;
+            }
         }
+        Ok(true)
     }
 }
 
+#[async_trait]
 impl UserService for PgSqlUserService {
-    async fn get_all_users(&self) -> anyhow::Result<Vec<User>> {
+    async fn list_users(&self, filter: UserFilter, page: PageRequest) -> ServiceResult<Page<User>> {
+        let mut count = QueryBuilder::<Postgres>::new(COUNT_USERS);
+        filter.push_sql(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::<Postgres>::new(SELECT_USERS);
+        filter.push_sql(&mut query);
+        page.push_sql(&mut query);
+        let rows = query
+            .build()
+            .try_map(Self::map_row)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(page.page(rows, total))
+    }
+
+    async fn get_user_by_id(&self, id: i64) -> ServiceResult<User> {
         let res = sqlx::query!(
             r#"
-            SELECT id, username, password, status, created, updated, last_login
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
             FROM users
-            "#
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
         );
-        res.fetch_all(&self.pool)
+        let row = res
+            .fetch_one(&self.pool)
             .await
-            .map(|rows| {
-                rows.into_iter()
-                    .map(|row| User {
-                        id: row.id,
-                        username: row.username,
-                        password: row.password,
-                        status: UserStatus::from(row.status),
-                        created: row.created.unwrap_or_default(),
-                        updated: row.updated.unwrap_or_default(),
-                        last_login: row.last_login,
-                    })
-                    .collect()
-            })
-            .map_err(|e| anyhow::anyhow!(e))
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found:{}", id)))?;
+        Ok(User {
+            id: row.id,
+            username: row.username,
+            email: row.email,
+            password: row.password,
+            status: UserStatus::try_from(row.status)?,
+            role: Role::try_from(row.role)?,
+            version: row.version,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            last_login: row.last_login,
+            deleted_at: row.deleted_at,
+        })
     }
 
-    async fn get_user_by_id(&self, id: i64) -> anyhow::Result<User> {
+    async fn get_user_by_username(&self, username: &str) -> ServiceResult<User> {
         let res = sqlx::query!(
             r#"
-            SELECT id, username, password, status, created, updated, last_login
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
             FROM users
-            WHERE id = $1
+            WHERE username = $1 AND deleted_at IS NULL
             "#,
-            id
+            username
         );
-        res.fetch_one(&self.pool)
+        let row = res
+            .fetch_one(&self.pool)
             .await
-            .map(|row| User {
-                id: row.id,
-                username: row.username,
-                password: row.password,
-                status: UserStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-                last_login: row.last_login,
-            })
-            .map_err(|e| anyhow::anyhow!(e).context(format!("Failed to get user by id:{}", id)))
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found:{}", username)))?;
+        Ok(User {
+            id: row.id,
+            username: row.username,
+            email: row.email,
+            password: row.password,
+            status: UserStatus::try_from(row.status)?,
+            role: Role::try_from(row.role)?,
+            version: row.version,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            last_login: row.last_login,
+            deleted_at: row.deleted_at,
+        })
     }
 
-    async fn get_user_by_username(&self, username: &str) -> anyhow::Result<User> {
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<User> {
         let res = sqlx::query!(
             r#"
-            SELECT id, username, password, status, created, updated, last_login
+            SELECT id, username, email, password, status, role, version, created, updated,
+                last_login, deleted_at
             FROM users
-            WHERE username = $1
+            WHERE lower(email) = lower($1) AND deleted_at IS NULL
             "#,
-            username
+            email
         );
-        res.fetch_one(&self.pool)
+        let row = res
+            .fetch_one(&self.pool)
             .await
-            .map(|row| User {
-                id: row.id,
-                username: row.username,
-                password: row.password,
-                status: UserStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-                last_login: row.last_login,
-            })
-            .map_err(|e| {
-                anyhow::anyhow!(e).context(format!("Failed to get user by username:{}", username))
-            })
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found:{}", email)))?;
+        Ok(User {
+            id: row.id,
+            username: row.username,
+            email: row.email,
+            password: row.password,
+            status: UserStatus::try_from(row.status)?,
+            role: Role::try_from(row.role)?,
+            version: row.version,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            last_login: row.last_login,
+            deleted_at: row.deleted_at,
+        })
     }
 
-    async fn create_user(&self, request: CreateUserRequest) -> anyhow::Result<User> {
+    async fn create_user(&self, request: CreateUserRequest) -> ServiceResult<User> {
         let query = sqlx::query!(
             r#"
-                insert into users(username,password,status,created,updated,last_login) 
-                values($1,$2,$3,Now(),Now(),null)
+                insert into users(username,email,password,status,created,updated,last_login) 
+                values($1,$2,$3,$4,Now(),Now(),null)
                 returning id
             "#,
             request.username,
-            password::encrypt_password(&request.password)?,
+            request.email,
+            hash_new_password(&self.hasher, &request.password)?,
             i32::from(request.status)
         );
 
@@ -214,38 +862,520 @@ impl UserService for PgSqlUserService {
         Ok(user)
     }
 
-    async fn update_user(&self, id: i64, request: UpdateUserRequest) -> anyhow::Result<User> {
-        match self.get_user_by_id(id).await {
-            Ok(user) => {
-                let query = sqlx::query!(
-                    r#"
-                        update users
-                        set username = $1, password = $2, status = $3, updated = Now(),last_login=$4 
-                        where id = $5
-                    "#,
-                    request.username,
-                    password::encrypt_password(&request.password)?,
-                    i32::from(request.status),
-                    user.updated,
-                    id
-                );
-                query.execute(&self.pool).await?;
-                let user = self.get_user_by_id(id).await?;
-                Ok(user)
-            }
-            Err(_) => anyhow::bail!("User not found,id = {}", id),
+    async fn update_user(
+        &self,
+        id: i64,
+        request: UpdateUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User> {
+        let query = sqlx::query!(
+            r#"
+                update users
+                set username = $1, email = $2, password = $3, status = $4,
+                    version = version + 1, updated = Now()
+                where id = $5 and deleted_at is null and ($6::bigint is null or version = $6)
+            "#,
+            request.username,
+            request.email,
+            hash_new_password(&self.hasher, &request.password)?,
+            i32::from(request.status),
+            id,
+            version
+        );
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
         }
+        self.get_user_by_id(id).await
+    }
+
+    async fn patch_user(
+        &self,
+        id: i64,
+        request: PatchUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User> {
+        let password = request
+            .password
+            .as_deref()
+            .map(|password| hash_new_password(&self.hasher, password))
+            .transpose()?;
+        let query = sqlx::query!(
+            r#"
+                update users
+                set username = coalesce($1, username),
+                    email = coalesce($2, email),
+                    password = coalesce($3, password),
+                    status = coalesce($4, status),
+                    version = version + 1,
+                    updated = Now()
+                where id = $5 and deleted_at is null and ($6::bigint is null or version = $6)
+            "#,
+            request.username,
+            request.email,
+            password,
+            request.status.map(i32::from),
+            id,
+            version
+        );
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn delete_user(
+        &self,
+        id: i64,
+        posts: PostsDisposal,
+        version: Option<i64>,
+    ) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        let query = sqlx::query!(
+            r#"
+                update users
+                set deleted_at = Now(), version = version + 1, updated = Now()
+                where id = $1 and deleted_at is null and ($2::bigint is null or version = $2)
+            "#,
+            id,
+            version
+        );
+        if query.execute(&mut *tx).await?.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(self.write_failed(id).await);
+        }
+        Self::dispose_posts(&mut tx, id, posts, false).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_trashed_user(&self, id: i64) -> ServiceResult<User> {
+        let res = sqlx::query!(
+            r#"
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
+            FROM users
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            "#,
+            id
+        );
+        let row = res.fetch_one(&self.pool).await.map_err(|e| {
+            ServiceError::or_not_found(e, format!("User not found in trash:{}", id))
+        })?;
+        Ok(User {
+            id: row.id,
+            username: row.username,
+            email: row.email,
+            password: row.password,
+            status: UserStatus::try_from(row.status)?,
+            role: Role::try_from(row.role)?,
+            version: row.version,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            last_login: row.last_login,
+            deleted_at: row.deleted_at,
+        })
     }
 
-    async fn delete_user(&self, id: i64) -> anyhow::Result<()> {
+    async fn restore_user(&self, id: i64) -> ServiceResult<User> {
         let query = sqlx::query!(
             r#"
-                delete from users
-                where id = $1
+                update users
+                set deleted_at = null, version = version + 1, updated = Now()
+                where id = $1 and deleted_at is not null
             "#,
             id
         );
-        query.execute(&self.pool).await?;
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            )));
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn purge_user(&self, id: i64, posts: PostsDisposal) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        let trashed = sqlx::query_scalar!(
+            "select id from users where id = $1 and deleted_at is not null for update",
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if trashed.is_none() {
+            return Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            )));
+        }
+        //文章引用用户，需要先处理文章再删除用户
+        Self::dispose_posts(&mut tx, id, posts, true).await?;
+        sqlx::query!("delete from users where id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn set_role(&self, id: i64, role: Role) -> ServiceResult<User> {
+        let query = sqlx::query!(
+            r#"
+                update users
+                set role = $1, version = version + 1, updated = Now()
+                where id = $2 and deleted_at is null
+            "#,
+            i32::from(role),
+            id
+        );
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "User not found,id = {}",
+                id
+            )));
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn record_login(&self, id: i64) -> ServiceResult<()> {
+        let query = sqlx::query!(
+            r#"
+                update users
+                set last_login = Now()
+                where id = $1 and deleted_at is null
+            "#,
+            id
+        );
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "User not found,id = {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_password_policy(&self, password: &str) -> ServiceResult<()> {
+        Ok(self.hasher.check_policy(password)?)
+    }
+
+    async fn verify_password(&self, user: &User, password: &str) -> ServiceResult<bool> {
+        if self.hasher.verify(password, &user.password).is_err() {
+            return Ok(false);
+        }
+        if self.hasher.needs_rehash(&user.password) {
+            //只在哈希未被其他请求修改时替换
+            let query = sqlx::query!(
+                r#"
+                    update users
+                    set password = $1
+                    where id = $2 and password = $3
+                "#,
+                self.hasher.hash(password)?,
+                user.id,
+                user.password
+            );
+            query.execute(&self.pool).await?;
+        }
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl UserService for SqliteUserService {
+    async fn list_users(&self, filter: UserFilter, page: PageRequest) -> ServiceResult<Page<User>> {
+        let mut count = QueryBuilder::<Sqlite>::new(COUNT_USERS);
+        filter.push_sql(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::<Sqlite>::new(SELECT_USERS);
+        filter.push_sql(&mut query);
+        page.push_sql(&mut query);
+        let rows = query
+            .build()
+            .try_map(Self::map_row)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(page.page(rows, total))
+    }
+
+    async fn get_user_by_id(&self, id: i64) -> ServiceResult<User> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found:{}", id)))
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> ServiceResult<User> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
+            FROM users
+            WHERE username = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(username);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found:{}", username)))
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<User> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, username, email, password, status, role, version, created, updated,
+                last_login, deleted_at
+            FROM users
+            WHERE lower(email) = lower($1) AND deleted_at IS NULL
+            "#,
+        )
+        .bind(email);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found:{}", email)))
+    }
+
+    async fn create_user(&self, request: CreateUserRequest) -> ServiceResult<User> {
+        let ts = chrono::offset::Utc::now();
+        let query = sqlx::query(
+            r#"
+                insert into users(username,email,password,status,created,updated,last_login)
+                values($1,$2,$3,$4,$5,$5,null)
+                returning id
+            "#,
+        )
+        .bind(request.username)
+        .bind(request.email)
+        .bind(hash_new_password(&self.hasher, &request.password)?)
+        .bind(i32::from(request.status))
+        .bind(ts);
+
+        let res = query.fetch_one(&self.pool).await?;
+        let id: i64 = res.try_get("id")?;
+        let user = self.get_user_by_id(id).await?;
+        Ok(user)
+    }
+
+    async fn update_user(
+        &self,
+        id: i64,
+        request: UpdateUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User> {
+        let query = sqlx::query(
+            r#"
+                update users
+                set username = $1, email = $2, password = $3, status = $4,
+                    version = version + 1, updated = $5
+                where id = $6 and deleted_at is null and ($7 is null or version = $7)
+            "#,
+        )
+        .bind(request.username)
+        .bind(request.email)
+        .bind(hash_new_password(&self.hasher, &request.password)?)
+        .bind(i32::from(request.status))
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(version);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn patch_user(
+        &self,
+        id: i64,
+        request: PatchUserRequest,
+        version: Option<i64>,
+    ) -> ServiceResult<User> {
+        let password = request
+            .password
+            .as_deref()
+            .map(|password| hash_new_password(&self.hasher, password))
+            .transpose()?;
+        let query = sqlx::query(
+            r#"
+                update users
+                set username = coalesce($1, username),
+                    email = coalesce($2, email),
+                    password = coalesce($3, password),
+                    status = coalesce($4, status),
+                    version = version + 1,
+                    updated = $5
+                where id = $6 and deleted_at is null and ($7 is null or version = $7)
+            "#,
+        )
+        .bind(request.username)
+        .bind(request.email)
+        .bind(password)
+        .bind(request.status.map(i32::from))
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(version);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(self.write_failed(id).await);
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn delete_user(
+        &self,
+        id: i64,
+        posts: PostsDisposal,
+        version: Option<i64>,
+    ) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        let query = sqlx::query(
+            r#"
+                update users
+                set deleted_at = $1, version = version + 1, updated = $1
+                where id = $2 and deleted_at is null and ($3 is null or version = $3)
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .bind(version);
+        if query.execute(&mut *tx).await?.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(self.write_failed(id).await);
+        }
+        Self::dispose_posts(&mut tx, id, posts, false).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_trashed_user(&self, id: i64) -> ServiceResult<User> {
+        let res = sqlx::query(
+            r#"
+            SELECT id, username, email, password, status, role, version, created, updated, last_login,
+                deleted_at
+            FROM users
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(id);
+        res.try_map(Self::map_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ServiceError::or_not_found(e, format!("User not found in trash:{}", id)))
+    }
+
+    async fn restore_user(&self, id: i64) -> ServiceResult<User> {
+        let query = sqlx::query(
+            r#"
+                update users
+                set deleted_at = null, version = version + 1, updated = $1
+                where id = $2 and deleted_at is not null
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(id);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            )));
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn purge_user(&self, id: i64, posts: PostsDisposal) -> ServiceResult<()> {
+        let mut tx = self.pool.begin().await?;
+        let trashed: Option<i64> =
+            sqlx::query_scalar("select id from users where id = $1 and deleted_at is not null")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        if trashed.is_none() {
+            return Err(ServiceError::NotFound(format!(
+                "User not found in trash:{}",
+                id
+            )));
+        }
+        //文章引用用户，需要先处理文章再删除用户
+        Self::dispose_posts(&mut tx, id, posts, true).await?;
+        sqlx::query("delete from users where id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn set_role(&self, id: i64, role: Role) -> ServiceResult<User> {
+        let query = sqlx::query(
+            r#"
+                update users
+                set role = $1, version = version + 1, updated = $2
+                where id = $3 and deleted_at is null
+            "#,
+        )
+        .bind(i32::from(role))
+        .bind(chrono::offset::Utc::now())
+        .bind(id);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "User not found,id = {}",
+                id
+            )));
+        }
+        self.get_user_by_id(id).await
+    }
+
+    async fn record_login(&self, id: i64) -> ServiceResult<()> {
+        let query = sqlx::query(
+            r#"
+                update users
+                set last_login = $1
+                where id = $2 and deleted_at is null
+            "#,
+        )
+        .bind(chrono::offset::Utc::now())
+        .bind(id);
+        if query.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(ServiceError::NotFound(format!(
+                "User not found,id = {}",
+                id
+            )));
+        }
         Ok(())
     }
+
+    fn check_password_policy(&self, password: &str) -> ServiceResult<()> {
+        Ok(self.hasher.check_policy(password)?)
+    }
+
+    async fn verify_password(&self, user: &User, password: &str) -> ServiceResult<bool> {
+        if self.hasher.verify(password, &user.password).is_err() {
+            return Ok(false);
+        }
+        if self.hasher.needs_rehash(&user.password) {
+            //只在哈希未被其他请求修改时替换
+            let query = sqlx::query(
+                r#"
+                    update users
+                    set password = $1
+                    where id = $2 and password = $3
+                "#,
+            )
+            .bind(self.hasher.hash(password)?)
+            .bind(user.id)
+            .bind(&user.password);
+            query.execute(&self.pool).await?;
+        }
+        Ok(true)
+    }
 }
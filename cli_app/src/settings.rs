@@ -14,6 +14,253 @@ pub struct Database {
     pub url: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Postgres,
+    Memory,
+    Sqlite,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[allow(unused)]
+pub struct Storage {
+    #[serde(default)]
+    pub backend: StorageBackend,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SigningAlgorithm {
+    RS256,
+    ES256,
+    EdDSA,
+}
+
+/// 令牌签名密钥，公钥和私钥均为 PEM 文件路径。
+/// 只保留公钥的密钥仍可验证轮换前签发的令牌。
+///
+/// ```sh
+/// openssl genpkey -algorithm ed25519 -out jwt.pem
+/// openssl pkey -in jwt.pem -pubout -out jwt.pub.pem
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: SigningAlgorithm,
+    pub public_key: String,
+    pub private_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[allow(unused)]
+pub struct Jwt {
+    /// 签发令牌使用的密钥，未设置时使用第一个带私钥的密钥
+    pub signing_kid: Option<String>,
+    #[serde(default)]
+    pub keys: Vec<SigningKey>,
+}
+
+/// 登录失败保护：同一用户名每次失败后按 `backoff_seconds * 2^(n-1)` 退避，
+/// 用户名或 IP 的失败次数达到上限后锁定 `lockout_seconds` 秒
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LoginProtection {
+    pub max_failures: u32,
+    pub max_ip_failures: u32,
+    pub backoff_seconds: i64,
+    pub lockout_seconds: i64,
+}
+
+impl Default for LoginProtection {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            max_ip_failures: 20,
+            backoff_seconds: 1,
+            lockout_seconds: 900,
+        }
+    }
+}
+
+/// 定时发布：每隔 `interval_seconds` 秒检查一次到期的定时文章
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Scheduler {
+    pub interval_seconds: u64,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    Smtp,
+    /// 把邮件写成 `.eml` 文件放到 `directory` 目录，用于开发和测试
+    File,
+    /// 只把邮件写入日志，不真正发送
+    #[default]
+    Log,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// 先用明文连接再通过 STARTTLS 升级
+    #[default]
+    Starttls,
+    /// 直接建立 TLS 连接
+    Tls,
+    /// 不加密，只应在本机调试时使用
+    None,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Smtp {
+    pub host: String,
+    /// 未设置时使用所选 TLS 方式的默认端口
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+}
+
+/// 邮件发送方式，`transport` 为 `smtp`、`file` 或 `log`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Mail {
+    pub transport: MailTransport,
+    pub from: String,
+    pub smtp: Smtp,
+    pub directory: String,
+}
+
+impl Default for Mail {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::default(),
+            from: "noreply@localhost".to_string(),
+            smtp: Smtp::default(),
+            directory: "mail".to_string(),
+        }
+    }
+}
+
+/// 密码重置：令牌有效期为 `token_timeout_seconds` 秒，
+/// `link` 为邮件中的重置链接，其中的 `{token}` 会被替换为令牌，未设置时邮件中只包含令牌
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PasswordReset {
+    pub token_timeout_seconds: i64,
+    pub link: Option<String>,
+}
+
+impl Default for PasswordReset {
+    fn default() -> Self {
+        Self {
+            token_timeout_seconds: 3600,
+            link: None,
+        }
+    }
+}
+
+/// 新密码的强度要求，请求校验只限制最大长度，最小长度由 `min_length` 决定
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    /// 至少包含几类字符：小写字母、大写字母、数字、其他符号，0 表示不限制
+    pub min_character_classes: usize,
+    /// 禁止使用的常见密码，比较时不区分大小写
+    pub denylist: Vec<String>,
+    /// 每行一个密码的禁用列表文件，与 `denylist` 合并使用
+    pub denylist_file: Option<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            min_character_classes: 0,
+            denylist: [
+                "password",
+                "password1",
+                "12345678",
+                "123456789",
+                "1234567890",
+                "qwertyui",
+                "qwerty123",
+                "11111111",
+                "iloveyou",
+                "abc12345",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            denylist_file: None,
+        }
+    }
+}
+
+/// 密码哈希使用 argon2id，`memory_kib`、`iterations`、`parallelism` 对应 argon2 的 m、t、p 参数。
+/// 设置 `pepper` 后它作为 argon2 的密钥参与哈希，只保存在配置中；
+/// 参数或 `pepper` 变化后，旧的哈希在用户下次登录成功时自动更新
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Password {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub pepper: Option<String>,
+    pub policy: PasswordPolicy,
+}
+
+impl Default for Password {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+            pepper: None,
+            policy: PasswordPolicy::default(),
+        }
+    }
+}
+
+/// TOTP 两步验证：`encryption_key` 为 base64 编码的 32 字节密钥，用于加密保存的 TOTP 密钥，
+/// 未设置时无法启用两步验证；登录时签发的验证令牌有效期为 `challenge_timeout_seconds` 秒
+///
+/// ```sh
+/// openssl rand -base64 32
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TwoFactor {
+    /// 显示在身份验证器应用中的发行方名称
+    pub issuer: String,
+    pub encryption_key: Option<String>,
+    pub challenge_timeout_seconds: i64,
+    pub recovery_codes: usize,
+}
+
+impl Default for TwoFactor {
+    fn default() -> Self {
+        Self {
+            issuer: "cli_app".to_string(),
+            encryption_key: None,
+            challenge_timeout_seconds: 300,
+            recovery_codes: 10,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 #[allow(unused)]
 pub struct Logging {
@@ -36,9 +283,25 @@ pub struct Settings {
     #[serde(default)]
     pub database: Database,
     #[serde(default)]
+    pub storage: Storage,
+    #[serde(default)]
     pub logging: Logging,
-    pub token_secret: Option<String>,
+    #[serde(default)]
+    pub jwt: Jwt,
+    #[serde(default)]
+    pub login_protection: LoginProtection,
+    #[serde(default)]
+    pub scheduler: Scheduler,
+    #[serde(default)]
+    pub mail: Mail,
+    #[serde(default)]
+    pub password_reset: PasswordReset,
+    #[serde(default)]
+    pub password: Password,
+    #[serde(default)]
+    pub two_factor: TwoFactor,
     pub token_timeout_seconds: Option<i64>,
+    pub refresh_token_timeout_seconds: Option<i64>,
 }
 
 impl Settings {
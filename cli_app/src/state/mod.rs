@@ -2,24 +2,82 @@ use std::sync::Arc;
 
 use crate::{
     Settings,
-    services::{post::PgSqlPostService, user::PgSqlUserService},
+    services::{
+        comment::{CommentService, InMemoryCommentService},
+        mail::{self, Mailer},
+        password_reset::{InMemoryPasswordResetService, PasswordResetService},
+        post::{InMemoryPostService, PostService},
+        session::{InMemorySessionService, SessionService},
+        throttle::LoginThrottle,
+        two_factor::{InMemoryTwoFactorService, TwoFactorService},
+        user::{InMemoryUserService, UserService},
+    },
+    utils::{cipher::SecretCipher, jwt::KeyStore, password::PasswordHasher},
 };
 use anyhow::Ok;
 use arc_swap::ArcSwap;
-use sqlx::PgPool;
 
 pub struct ApplicationState {
     pub settings: ArcSwap<Settings>,
-    pub user_service: Arc<PgSqlUserService>,
-    pub post_service: Arc<PgSqlPostService>,
+    pub user_service: Arc<dyn UserService>,
+    pub post_service: Arc<dyn PostService>,
+    pub comment_service: Arc<dyn CommentService>,
+    pub session_service: Arc<dyn SessionService>,
+    pub password_reset_service: Arc<dyn PasswordResetService>,
+    pub two_factor_service: Arc<dyn TwoFactorService>,
+    pub mailer: Arc<dyn Mailer>,
+    /// 加密 TOTP 密钥，未配置 `two_factor.encryption_key` 时为空
+    pub totp_cipher: Option<SecretCipher>,
+    pub keys: KeyStore,
+    pub login_throttle: LoginThrottle,
 }
 
 impl ApplicationState {
-    pub fn new(settings: &Settings, pool: PgPool) -> anyhow::Result<Self> {
+    pub fn new(
+        settings: &Settings,
+        user_service: Arc<dyn UserService>,
+        post_service: Arc<dyn PostService>,
+        comment_service: Arc<dyn CommentService>,
+        session_service: Arc<dyn SessionService>,
+        password_reset_service: Arc<dyn PasswordResetService>,
+        two_factor_service: Arc<dyn TwoFactorService>,
+    ) -> anyhow::Result<Self> {
         Ok(Self {
             settings: ArcSwap::new(Arc::new((*settings).clone())),
-            user_service: Arc::new(PgSqlUserService::new(pool.clone())),
-            post_service: Arc::new(PgSqlPostService::new(pool.clone())),
+            user_service,
+            post_service,
+            comment_service,
+            session_service,
+            password_reset_service,
+            two_factor_service,
+            mailer: mail::from_settings(&settings.mail)?,
+            totp_cipher: settings
+                .two_factor
+                .encryption_key
+                .as_deref()
+                .map(SecretCipher::new)
+                .transpose()?,
+            keys: KeyStore::load(&settings.jwt)?,
+            login_throttle: LoginThrottle::default(),
         })
     }
+
+    /// 使用内存存储创建应用状态，不依赖数据库
+    pub fn in_memory(settings: &Settings) -> anyhow::Result<Self> {
+        let post_service: Arc<dyn PostService> = Arc::new(InMemoryPostService::default());
+        let comment_service: Arc<dyn CommentService> = Arc::new(InMemoryCommentService::default());
+        Self::new(
+            settings,
+            Arc::new(InMemoryUserService::new(
+                PasswordHasher::new(&settings.password)?,
+                post_service.clone(),
+                comment_service.clone(),
+            )),
+            post_service,
+            comment_service,
+            Arc::new(InMemorySessionService::default()),
+            Arc::new(InMemoryPasswordResetService::default()),
+            Arc::new(InMemoryTwoFactorService::default()),
+        )
+    }
 }
@@ -0,0 +1,62 @@
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use anyhow::Context;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rand::{RngCore, rngs::OsRng};
+
+const NONCE_LEN: usize = 12;
+
+/// 使用 AES-256-GCM 加密需要保存在数据库中的密钥，
+/// 密文格式为 base64(nonce || ciphertext)，`aad` 把密文绑定到所属记录
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    /// `key` 为 base64 编码的 32 字节密钥
+    pub fn new(key: &str) -> anyhow::Result<Self> {
+        let key = STANDARD
+            .decode(key.trim())
+            .context("Encryption key is not valid base64")?;
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|_| anyhow::anyhow!("Encryption key must be 32 bytes"))?;
+        Ok(Self { cipher })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> anyhow::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("加密失败"))?;
+        let mut encoded = nonce.to_vec();
+        encoded.extend(ciphertext);
+        Ok(STANDARD.encode(encoded))
+    }
+
+    pub fn decrypt(&self, encoded: &str, aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let bytes = STANDARD.decode(encoded).context("解密失败")?;
+        if bytes.len() < NONCE_LEN {
+            anyhow::bail!("解密失败");
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("解密失败"))
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+
+use anyhow::Context;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+};
+use pkcs1::{RsaPublicKey, der::Decode};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use spki::{ObjectIdentifier, SubjectPublicKeyInfoOwned, der::DecodePem};
+
+use crate::settings::{Jwt, SigningAlgorithm, SigningKey};
+
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const PRIME256V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+#[derive(Serialize, Deserialize)]
+struct Probe {
+    exp: usize,
+}
+
+struct VerifyingKey {
+    kid: String,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// 令牌签名密钥，使用一个密钥签发令牌，接受所有已配置的公钥验证，便于轮换密钥
+pub struct KeyStore {
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    signing_key: EncodingKey,
+    verifying: Vec<VerifyingKey>,
+    jwks: JwkSet,
+}
+
+impl KeyStore {
+    /// 从配置的 PEM 文件加载密钥，没有可用于签名的密钥时返回错误
+    pub fn load(settings: &Jwt) -> anyhow::Result<Self> {
+        if settings.keys.is_empty() {
+            anyhow::bail!("No JWT signing key is configured (jwt.keys)");
+        }
+
+        let mut verifying = Vec::new();
+        let mut jwks = Vec::new();
+        for key in &settings.keys {
+            if verifying.iter().any(|k: &VerifyingKey| k.kid == key.kid) {
+                anyhow::bail!("Duplicate JWT key id: {}", key.kid);
+            }
+            let (decoding, jwk) = load_public_key(key)
+                .with_context(|| format!("Failed to load public key of JWT key {}", key.kid))?;
+            verifying.push(VerifyingKey {
+                kid: key.kid.clone(),
+                algorithm: algorithm(key.algorithm),
+                key: decoding,
+            });
+            jwks.push(jwk);
+        }
+
+        let signing = match &settings.signing_kid {
+            Some(kid) => settings
+                .keys
+                .iter()
+                .find(|k| &k.kid == kid)
+                .with_context(|| format!("JWT signing key {} is not configured", kid))?,
+            None => settings
+                .keys
+                .iter()
+                .find(|k| k.private_key.is_some())
+                .context("No JWT key has a private key to sign tokens with")?,
+        };
+        let private_key = signing
+            .private_key
+            .as_ref()
+            .with_context(|| format!("JWT signing key {} has no private key", signing.kid))?;
+        let pem = fs::read(private_key)
+            .with_context(|| format!("Failed to read private key {}", private_key))?;
+        let signing_key = match signing.algorithm {
+            SigningAlgorithm::RS256 => EncodingKey::from_rsa_pem(&pem),
+            SigningAlgorithm::ES256 => EncodingKey::from_ec_pem(&pem),
+            SigningAlgorithm::EdDSA => EncodingKey::from_ed_pem(&pem),
+        }
+        .with_context(|| format!("Invalid private key {}", private_key))?;
+
+        let store = Self {
+            signing_kid: signing.kid.clone(),
+            signing_algorithm: algorithm(signing.algorithm),
+            signing_key,
+            verifying,
+            jwks: JwkSet { keys: jwks },
+        };
+
+        //确认私钥与公钥匹配
+        let probe = store.encode(&Probe { exp: usize::MAX })?;
+        store
+            .decode::<Probe>(&probe)
+            .with_context(|| format!("Private key of JWT key {} does not match", signing.kid))?;
+
+        Ok(store)
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> anyhow::Result<String> {
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = Some(self.signing_kid.clone());
+        Ok(encode(&header, claims, &self.signing_key)?)
+    }
+
+    /// 根据令牌头部的 `kid` 选择公钥并校验令牌
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> anyhow::Result<T> {
+        let header = decode_header(token)?;
+        let kid = header.kid.context("Token has no key id")?;
+        let key = self
+            .verifying
+            .iter()
+            .find(|k| k.kid == kid)
+            .with_context(|| format!("Unknown key id: {}", kid))?;
+        if header.alg != key.algorithm {
+            anyhow::bail!("Unexpected token algorithm: {:?}", header.alg);
+        }
+        Ok(decode::<T>(token, &key.key, &Validation::new(key.algorithm))?.claims)
+    }
+
+    /// 供其他服务验证令牌的公钥集合
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+fn algorithm(algorithm: SigningAlgorithm) -> Algorithm {
+    match algorithm {
+        SigningAlgorithm::RS256 => Algorithm::RS256,
+        SigningAlgorithm::ES256 => Algorithm::ES256,
+        SigningAlgorithm::EdDSA => Algorithm::EdDSA,
+    }
+}
+
+fn load_public_key(key: &SigningKey) -> anyhow::Result<(DecodingKey, Jwk)> {
+    let pem = fs::read_to_string(&key.public_key)
+        .with_context(|| format!("Failed to read public key {}", key.public_key))?;
+    let spki = SubjectPublicKeyInfoOwned::from_pem(pem.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid public key {}: {}", key.public_key, e))?;
+    let raw = spki.subject_public_key.raw_bytes();
+    let oid = spki.algorithm.oid;
+    let curve = spki
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|p| p.decode_as::<ObjectIdentifier>().ok());
+
+    let (decoding, key_algorithm, params) = match key.algorithm {
+        SigningAlgorithm::RS256 if oid == RSA_ENCRYPTION => {
+            let rsa = RsaPublicKey::from_der(raw)
+                .map_err(|e| anyhow::anyhow!("Invalid RSA public key: {}", e))?;
+            let params = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(rsa.modulus.as_bytes()),
+                e: URL_SAFE_NO_PAD.encode(rsa.public_exponent.as_bytes()),
+            });
+            (DecodingKey::from_rsa_der(raw), KeyAlgorithm::RS256, params)
+        }
+        SigningAlgorithm::ES256 if oid == EC_PUBLIC_KEY && curve == Some(PRIME256V1) => {
+            //未压缩的椭圆曲线点：0x04 || x || y
+            if raw.len() != 65 || raw[0] != 0x04 {
+                anyhow::bail!("Expected an uncompressed P-256 public key");
+            }
+            let params = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x: URL_SAFE_NO_PAD.encode(&raw[1..33]),
+                y: URL_SAFE_NO_PAD.encode(&raw[33..]),
+            });
+            (DecodingKey::from_ec_der(raw), KeyAlgorithm::ES256, params)
+        }
+        SigningAlgorithm::EdDSA if oid == ED25519 => {
+            let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(raw),
+            });
+            (DecodingKey::from_ed_der(raw), KeyAlgorithm::EdDSA, params)
+        }
+        _ => anyhow::bail!(
+            "Public key {} does not match algorithm {:?}",
+            key.public_key,
+            key.algorithm
+        ),
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(key.kid.clone()),
+            ..Default::default()
+        },
+        algorithm: params,
+    };
+    Ok((decoding, jwk))
+}
@@ -1 +1,5 @@
+pub mod cipher;
+pub mod jwt;
 pub mod password;
+pub mod token;
+pub mod totp;
@@ -1,23 +1,156 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
 use argon2::{
-    Argon2, PasswordHash, PasswordVerifier,
-    password_hash::{PasswordHasher, SaltString},
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHash, PasswordVerifier, Version,
+    password_hash::{PasswordHasher as _, SaltString},
 };
 use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::settings::{Password, PasswordPolicy};
+
+/// 新密码不符合强度策略
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct PolicyViolation(String);
+
+/// 按配置的 argon2 参数和 pepper 计算、校验密码哈希，并检查新密码的强度
+pub struct PasswordHasher {
+    params: Params,
+    pepper: Option<Vec<u8>>,
+    policy: PasswordPolicy,
+    denylist: HashSet<String>,
+}
+
+impl PasswordHasher {
+    pub fn new(settings: &Password) -> anyhow::Result<Self> {
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(settings.memory_kib)
+            .t_cost(settings.iterations)
+            .p_cost(settings.parallelism);
+        //带 pepper 的哈希在 keyid 中记录 pepper 的指纹，用于区分旧的不带 pepper 的哈希
+        let pepper = settings
+            .pepper
+            .as_ref()
+            .map(|pepper| pepper.as_bytes().to_vec());
+        if let Some(pepper) = &pepper {
+            builder.keyid(KeyId::new(&pepper_id(pepper)).map_err(|e| anyhow::anyhow!(e))?);
+        }
+        let params = builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid argon2 parameters:{}", e))?;
+
+        let mut denylist: HashSet<String> = settings
+            .policy
+            .denylist
+            .iter()
+            .map(|password| password.to_lowercase())
+            .collect();
+        if let Some(path) = &settings.policy.denylist_file {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read password denylist {}", path))?;
+            denylist.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_lowercase),
+            );
+        }
+
+        Ok(Self {
+            params,
+            pepper,
+            policy: settings.policy.clone(),
+            denylist,
+        })
+    }
+
+    fn argon2(&self, params: Params) -> anyhow::Result<Argon2<'_>> {
+        match &self.pepper {
+            Some(pepper) => {
+                Argon2::new_with_secret(pepper, Algorithm::Argon2id, Version::V0x13, params)
+                    .map_err(|e| anyhow::anyhow!(e))
+            }
+            None => Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params)),
+        }
+    }
+
+    /// 检查新密码是否符合强度策略，已有的密码不受影响
+    pub fn check_policy(&self, password: &str) -> Result<(), PolicyViolation> {
+        let policy = &self.policy;
+        if password.chars().count() < policy.min_length {
+            return Err(PolicyViolation(format!(
+                "Password must be at least {} characters",
+                policy.min_length
+            )));
+        }
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ];
+        if classes.iter().filter(|present| **present).count() < policy.min_character_classes {
+            return Err(PolicyViolation(format!(
+                "Password must contain at least {} of lowercase letters, uppercase letters, digits and symbols",
+                policy.min_character_classes
+            )));
+        }
+        if self.denylist.contains(&password.to_lowercase()) {
+            return Err(PolicyViolation("Password is too common".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 使用当前参数计算哈希
+    pub fn hash(&self, password: &str) -> anyhow::Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2(self.params.clone())?
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| anyhow::anyhow!("加密失败:{}", e))
+    }
+
+    /// 按哈希中记录的参数校验密码，没有 keyid 的旧哈希不使用 pepper
+    pub fn verify(&self, password: &str, hash: &str) -> anyhow::Result<()> {
+        let parsed_hash =
+            PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("解析hash失败:{}", e))?;
+        let params = Params::try_from(&parsed_hash).map_err(|e| anyhow::anyhow!(e))?;
+        let argon2 = if params.keyid().is_empty() {
+            Argon2::default()
+        } else if self.params.keyid() == params.keyid() {
+            self.argon2(params)?
+        } else {
+            anyhow::bail!("Password hash was created with a different pepper");
+        };
+        argon2
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .map_err(|_| anyhow::anyhow!("验证失败"))
+    }
 
-pub fn encrypt_password(password: &str) -> anyhow::Result<String> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    if let Ok(hash) = argon2.hash_password(password.as_bytes(), &salt) {
-        Ok(hash.to_string())
-    } else {
-        Err(anyhow::anyhow!("加密失败"))
+    /// 哈希的算法、参数或 pepper 与当前配置不一致时需要重新计算
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed_hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&parsed_hash) else {
+            return true;
+        };
+        parsed_hash.algorithm != Algorithm::Argon2id.ident()
+            || parsed_hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+            || params.keyid() != self.params.keyid()
     }
 }
 
-pub fn validate_password(password: &str, hash: &str) -> anyhow::Result<()> {
-    let argon2 = Argon2::default();
-    let parsed_hash = PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("解析hash失败:{}", e))?;
-    argon2
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .map_err(|_| anyhow::anyhow!("验证失败"))
+/// pepper 的指纹，取 SHA-256 的前 4 个字节
+fn pepper_id(pepper: &[u8]) -> [u8; 4] {
+    let digest = Sha256::digest(pepper);
+    [digest[0], digest[1], digest[2], digest[3]]
 }
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use rand::{RngCore, rngs::OsRng};
+use totp_rs::{Algorithm, TOTP};
+
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+/// 允许前后各一个时间步的时钟偏差
+const SKEW: i64 = 1;
+
+/// 生成 160 位的 TOTP 密钥
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; 20];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+fn totp(secret: &[u8], issuer: Option<&str>, account: &str) -> anyhow::Result<TOTP> {
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        0,
+        STEP,
+        secret.to_vec(),
+        issuer.map(String::from),
+        account.to_string(),
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid TOTP parameters: {}", e))
+}
+
+/// 返回 base32 编码的密钥和供身份验证器扫描的 `otpauth://` URI
+pub fn otpauth(secret: &[u8], issuer: &str, account: &str) -> anyhow::Result<(String, String)> {
+    let totp = totp(secret, Some(issuer), account)?;
+    Ok((totp.get_secret_base32(), totp.get_url()))
+}
+
+/// 返回与动态码匹配的时间步，不匹配时返回 `None`
+pub fn matching_step(secret: &[u8], code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let totp = totp(secret, None, "").ok()?;
+    let current = now.timestamp() / STEP as i64;
+    (current - SKEW..=current + SKEW)
+        .find(|step| *step >= 0 && totp.check(code, *step as u64 * STEP))
+}
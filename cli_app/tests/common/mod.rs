@@ -0,0 +1,198 @@
+//! 集成测试共用的应用和请求辅助函数，默认使用内存存储
+
+#![allow(dead_code)]
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::connect_info::MockConnectInfo,
+    http::{HeaderMap, HeaderName, Method, Request, StatusCode, header},
+};
+use cli_app::{
+    Settings,
+    model::Role,
+    settings::{SigningAlgorithm, SigningKey, StorageBackend},
+    state::ApplicationState,
+};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+pub const PASSWORD: &str = "correct-horse-1";
+
+pub struct TestApp {
+    pub state: Arc<ApplicationState>,
+    pub router: Router,
+}
+
+/// 读取 `config.json`，改用内存存储，JWT 密钥改用 `tests/fixtures` 中仅供测试的密钥对，
+/// 不依赖数据库和未纳入版本库的 `keys/` 目录
+pub fn settings() -> Settings {
+    let mut settings = Settings::new(Some("config.json"), "APP").expect("load settings");
+    settings.storage.backend = StorageBackend::Memory;
+    settings.jwt.signing_kid = None;
+    settings.jwt.keys = vec![SigningKey {
+        kid: "test".to_string(),
+        algorithm: SigningAlgorithm::EdDSA,
+        public_key: "tests/fixtures/jwt.pub.pem".to_string(),
+        private_key: Some("tests/fixtures/jwt.pem".to_string()),
+    }];
+    settings
+}
+
+/// 使用临时目录中 SQLite 数据库文件的配置，每个测试使用不同的文件
+pub fn sqlite_settings(name: &str) -> Settings {
+    let path = std::env::temp_dir().join(format!("cli_app-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut settings = settings();
+    settings.storage.backend = StorageBackend::Sqlite;
+    settings.database.url = Some(format!("sqlite://{}", path.display()));
+    settings
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::from_state(ApplicationState::in_memory(&settings()).expect("build state"))
+    }
+
+    /// 按配置选择存储后端，数据库后端会先执行所有迁移
+    pub async fn with_settings(settings: Settings) -> Self {
+        let state = cli_app::commands::build_state(&settings, true)
+            .await
+            .expect("build state");
+        Self::from_state(state)
+    }
+
+    fn from_state(state: ApplicationState) -> Self {
+        let state = Arc::new(state);
+        let router = cli_app::api::configure(state.clone()).layer(MockConnectInfo(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080),
+        ));
+        Self { state, router }
+    }
+
+    /// 发送请求，返回状态码和原始响应体
+    pub async fn send(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, String) {
+        let (status, _, text) = self.send_with_headers(method, uri, token, &[], body).await;
+        (status, text)
+    }
+
+    /// 发送带有额外请求头的请求，返回状态码、响应头和原始响应体
+    pub async fn send_with_headers(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        headers: &[(HeaderName, &str)],
+        body: Option<Value>,
+    ) -> (StatusCode, HeaderMap, String) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    /// 发送请求并断言响应成功且不包含密码，返回解析后的响应体
+    pub async fn send_ok(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> Value {
+        let (status, text) = self.send(method.clone(), uri, token, body).await;
+        assert!(
+            status.is_success(),
+            "{} {} returned {}: {}",
+            method,
+            uri,
+            status,
+            text
+        );
+        assert_no_password(&format!("{} {}", method, uri), &text);
+        if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).unwrap()
+        }
+    }
+
+    pub async fn create_user(&self, username: &str) -> i64 {
+        let body = json!({"username": username, "password": PASSWORD});
+        let response = self
+            .send_ok(Method::POST, "/v1/signup", None, Some(body))
+            .await;
+        response["data"]["id"].as_i64().unwrap()
+    }
+
+    pub async fn login(&self, username: &str) -> String {
+        let body = json!({"username": username, "password": PASSWORD});
+        let response = self
+            .send_ok(Method::POST, "/v1/login", None, Some(body))
+            .await;
+        response["token"].as_str().unwrap().to_string()
+    }
+
+    pub async fn admin_token(&self) -> String {
+        let id = self.create_user("admin").await;
+        self.state
+            .user_service
+            .set_role(id, Role::Admin)
+            .await
+            .unwrap();
+        self.login("admin").await
+    }
+}
+
+/// 响应中既不能有 `password` 字段，也不能出现 argon2 哈希
+pub fn assert_no_password(context: &str, text: &str) {
+    assert!(
+        !text.contains("$argon2"),
+        "{} leaked a password hash: {}",
+        context,
+        text
+    );
+    if let Ok(value) = serde_json::from_str::<Value>(text) {
+        assert!(
+            !has_password_key(&value),
+            "{} returned a password field: {}",
+            context,
+            text
+        );
+    }
+}
+
+pub fn has_password_key(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.iter().any(|(key, value)| {
+            key.to_ascii_lowercase().contains("password") || has_password_key(value)
+        }),
+        Value::Array(items) => items.iter().any(has_password_key),
+        _ => false,
+    }
+}
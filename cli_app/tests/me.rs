@@ -0,0 +1,108 @@
+//! 当前账户接口：修改密码、改名和删除账号
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+mod common;
+
+use common::{PASSWORD, TestApp, assert_no_password};
+
+#[tokio::test]
+async fn change_password_checks_current_password() {
+    let app = TestApp::new();
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let token = Some(token.as_str());
+
+    let body = json!({"current_password": "wrong-password", "new_password": "password2"});
+    let (status, text) = app
+        .send(Method::POST, "/v1/me/password", token, Some(body))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_no_password("POST /v1/me/password", &text);
+
+    let body = json!({"current_password": PASSWORD, "new_password": "password2"});
+    app.send_ok(Method::POST, "/v1/me/password", token, Some(body))
+        .await;
+    // 修改密码后旧令牌失效
+    let (status, _) = app.send(Method::GET, "/v1/me", token, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn readers_cannot_reassign_their_posts_when_deleting_themselves() {
+    let app = TestApp::new();
+    let bob = app.create_user("bob").await;
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let token = Some(token.as_str());
+
+    let uri = format!("/v1/me?posts=reassign&reassign_to={}", bob);
+    let (status, _) = app.send(Method::DELETE, &uri, token, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    app.send_ok(Method::GET, "/v1/me", token, None).await;
+
+    app.send_ok(Method::DELETE, "/v1/me?posts=cascade", token, None)
+        .await;
+    let (status, _) = app.send(Method::GET, "/v1/me", token, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logging_in_keeps_the_user_version() {
+    let app = TestApp::new();
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let token = Some(token.as_str());
+    let before = app.send_ok(Method::GET, "/v1/me", token, None).await;
+
+    // 其他会话登录后，之前读到的版本仍然可以用于条件更新
+    app.login("alice").await;
+    let after = app.send_ok(Method::GET, "/v1/me", token, None).await;
+    assert_eq!(before["data"]["version"], after["data"]["version"]);
+    assert_ne!(before["data"]["last_login"], after["data"]["last_login"]);
+}
+
+#[tokio::test]
+async fn renaming_signs_out_the_old_tokens() {
+    let app = TestApp::new();
+    let alice = app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let token = Some(token.as_str());
+
+    let body = json!({"username": "alice2", "current_password": PASSWORD});
+    app.send_ok(Method::PATCH, "/v1/me", token, Some(body))
+        .await;
+    // 旧用户名被他人重新注册后，旧令牌既不能访问原账号也不能访问新账号
+    let mallory = app.create_user("alice").await;
+    let (status, _) = app.send(Method::GET, "/v1/me", token, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let token = app.login("alice").await;
+    let response = app.send_ok(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(response["data"]["id"], mallory);
+    assert_ne!(mallory, alice);
+}
+
+#[tokio::test]
+async fn changing_the_email_requires_the_current_password() {
+    let app = TestApp::new();
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let token = Some(token.as_str());
+
+    let body = json!({"email": "mallory@example.com"});
+    let (status, _) = app.send(Method::PATCH, "/v1/me", token, Some(body)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let body = json!({"email": "mallory@example.com", "current_password": "wrong"});
+    let (status, _) = app.send(Method::PATCH, "/v1/me", token, Some(body)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let response = app.send_ok(Method::GET, "/v1/me", token, None).await;
+    assert_eq!(response["data"]["email"], serde_json::Value::Null);
+
+    let body = json!({"email": "alice@example.com", "current_password": PASSWORD});
+    let response = app
+        .send_ok(Method::PATCH, "/v1/me", token, Some(body))
+        .await;
+    assert_eq!(response["data"]["email"], "alice@example.com");
+}
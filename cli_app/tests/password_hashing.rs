@@ -0,0 +1,45 @@
+//! 密码哈希参数和 pepper 变化后旧哈希的校验与升级
+
+use cli_app::{settings::Password, utils::password::PasswordHasher};
+
+const PASSWORD: &str = "correct-horse-1";
+
+fn hasher(memory_kib: u32, pepper: Option<&str>) -> PasswordHasher {
+    let settings = Password {
+        memory_kib,
+        iterations: 1,
+        parallelism: 1,
+        pepper: pepper.map(String::from),
+        ..Password::default()
+    };
+    PasswordHasher::new(&settings).unwrap()
+}
+
+#[test]
+fn outdated_parameters_need_rehash() {
+    let old = hasher(1024, None);
+    let hash = old.hash(PASSWORD).unwrap();
+    assert!(!old.needs_rehash(&hash));
+
+    let new = hasher(2048, None);
+    new.verify(PASSWORD, &hash).unwrap();
+    assert!(new.needs_rehash(&hash));
+    assert!(!new.needs_rehash(&new.hash(PASSWORD).unwrap()));
+}
+
+#[test]
+fn pepper_is_required_for_peppered_hashes() {
+    let plain = hasher(1024, None);
+    let peppered = hasher(1024, Some("pepper"));
+
+    // 启用 pepper 前的哈希仍能校验，并在下次登录时升级
+    let hash = plain.hash(PASSWORD).unwrap();
+    peppered.verify(PASSWORD, &hash).unwrap();
+    assert!(peppered.needs_rehash(&hash));
+
+    let hash = peppered.hash(PASSWORD).unwrap();
+    peppered.verify(PASSWORD, &hash).unwrap();
+    assert!(peppered.verify("wrong-password", &hash).is_err());
+    assert!(plain.verify(PASSWORD, &hash).is_err());
+    assert!(hasher(1024, Some("other")).verify(PASSWORD, &hash).is_err());
+}
@@ -0,0 +1,45 @@
+//! 新密码的强度策略：请求只限制最大长度，其余由配置决定
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+mod common;
+
+use common::{PASSWORD, TestApp};
+
+#[tokio::test]
+async fn new_passwords_follow_the_policy() {
+    let app = TestApp::new();
+    let body = json!({"username": "alice", "password": "Password1"});
+    let (status, text) = app.send(Method::POST, "/v1/signup", None, Some(body)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("Password is too common"), "{}", text);
+
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let body = json!({"current_password": PASSWORD, "new_password": "12345678"});
+    let (status, _) = app
+        .send(Method::POST, "/v1/me/password", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn minimum_length_comes_from_the_policy() {
+    let mut settings = common::settings();
+    settings.password.policy.min_length = 6;
+    let app = TestApp::with_settings(settings).await;
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+
+    let body = json!({"current_password": PASSWORD, "new_password": "q7#kd"});
+    let (status, text) = app
+        .send(Method::POST, "/v1/me/password", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("at least 6 characters"), "{}", text);
+
+    let body = json!({"current_password": PASSWORD, "new_password": "q7#kdz"});
+    app.send_ok(Method::POST, "/v1/me/password", Some(&token), Some(body))
+        .await;
+}
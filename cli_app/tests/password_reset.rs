@@ -0,0 +1,105 @@
+//! 忘记密码：重置邮件和一次性重置令牌
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::http::{Method, StatusCode};
+use cli_app::settings::MailTransport;
+use serde_json::json;
+
+mod common;
+
+use common::{PASSWORD, TestApp};
+
+/// 邮件写入临时目录的应用，每个测试使用不同的目录
+async fn mail_app(name: &str) -> (TestApp, PathBuf) {
+    let directory =
+        std::env::temp_dir().join(format!("cli_app-mail-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let mut settings = common::settings();
+    settings.mail.transport = MailTransport::File;
+    settings.mail.directory = directory.to_string_lossy().into_owned();
+    (TestApp::with_settings(settings).await, directory)
+}
+
+/// 等待邮件写入目录，返回邮件内容
+async fn wait_for_mail(directory: &Path) -> String {
+    for _ in 0..50 {
+        if let Ok(mut entries) = std::fs::read_dir(directory)
+            && let Some(entry) = entries.next()
+        {
+            return std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no mail was written to {}", directory.display());
+}
+
+/// 请求重置邮件并从邮件中取出重置令牌
+async fn request_reset(app: &TestApp, directory: &Path, email: &str) -> String {
+    let body = json!({"email": email});
+    app.send_ok(Method::POST, "/v1/password/forgot", None, Some(body))
+        .await;
+    let mail = wait_for_mail(directory).await;
+    std::fs::remove_dir_all(directory).unwrap();
+    assert!(mail.contains("To: alice@example.com"), "{}", mail);
+    mail.lines()
+        .map(str::trim)
+        .find(|line| line.len() == 64 && line.chars().all(|c| c.is_ascii_hexdigit()))
+        .expect("mail contains the reset token")
+        .to_string()
+}
+
+async fn create_alice(app: &TestApp) {
+    let body = json!({"username": "alice", "email": "alice@example.com", "password": PASSWORD});
+    app.send_ok(Method::POST, "/v1/signup", None, Some(body))
+        .await;
+}
+
+#[tokio::test]
+async fn password_reset_token_can_only_be_used_once() {
+    let (app, directory) = mail_app("once").await;
+    create_alice(&app).await;
+    let token = app.login("alice").await;
+
+    // 未注册的邮箱同样返回成功
+    let body = json!({"email": "nobody@example.com"});
+    app.send_ok(Method::POST, "/v1/password/forgot", None, Some(body))
+        .await;
+    let reset_token = request_reset(&app, &directory, "Alice@Example.com").await;
+
+    let body = json!({"token": reset_token, "new_password": "password2"});
+    app.send_ok(Method::POST, "/v1/password/reset", None, Some(body.clone()))
+        .await;
+    let (status, _) = app
+        .send(Method::POST, "/v1/password/reset", None, Some(body))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 重置后旧会话失效，新密码可以登录
+    let (status, _) = app.send(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let body = json!({"username": "alice", "password": "password2"});
+    app.send_ok(Method::POST, "/v1/login", None, Some(body))
+        .await;
+}
+
+#[tokio::test]
+async fn rejected_password_keeps_the_reset_token() {
+    let (app, directory) = mail_app("policy").await;
+    create_alice(&app).await;
+    let reset_token = request_reset(&app, &directory, "alice@example.com").await;
+
+    let body = json!({"token": reset_token, "new_password": "password1"});
+    let (status, text) = app
+        .send(Method::POST, "/v1/password/reset", None, Some(body))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("Password is too common"), "{}", text);
+
+    let body = json!({"token": reset_token, "new_password": "password2"});
+    app.send_ok(Method::POST, "/v1/password/reset", None, Some(body))
+        .await;
+}
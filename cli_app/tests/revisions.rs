@@ -0,0 +1,102 @@
+//! 文章修订：恢复修订只回退内容，不改变发布状态；回收站中文章的修订不可读取
+
+use axum::http::{Method, StatusCode};
+use chrono::{Duration, Utc};
+use cli_app::{model::Role, services::error::ServiceError};
+use serde_json::{Value, json};
+
+mod common;
+
+use common::TestApp;
+
+async fn editor_token(app: &TestApp) -> String {
+    let id = app.create_user("editor").await;
+    app.state
+        .user_service
+        .set_role(id, Role::Editor)
+        .await
+        .unwrap();
+    app.login("editor").await
+}
+
+/// 创建文章后修改标题，返回文章和最早的修订号
+async fn edited_post(app: &TestApp, token: &str, body: Value) -> (Value, i64) {
+    let post = app
+        .send_ok(Method::POST, "/v1/posts", Some(token), Some(body))
+        .await;
+    let id = post["data"]["id"].as_i64().unwrap();
+    let body = json!({"title": "Edited"});
+    app.send_ok(
+        Method::PATCH,
+        &format!("/v1/posts/{}", id),
+        Some(token),
+        Some(body),
+    )
+    .await;
+    let revisions = app
+        .send_ok(
+            Method::GET,
+            &format!("/v1/posts/{}/revisions", id),
+            Some(token),
+            None,
+        )
+        .await;
+    let revisions = revisions["data"].as_array().unwrap();
+    let first = revisions.last().unwrap()["revision"].as_i64().unwrap();
+    (post["data"].clone(), first)
+}
+
+#[tokio::test]
+async fn restoring_a_scheduled_post_keeps_the_schedule() {
+    let app = TestApp::new();
+    let token = editor_token(&app).await;
+    let publish_at = (Utc::now() + Duration::days(1)).to_rfc3339();
+    let body = json!({"title": "Original", "slug": "scheduled", "content": "text", "status": "Scheduled", "publish_at": publish_at});
+    let (post, rev) = edited_post(&app, &token, body).await;
+
+    let uri = format!("/v1/posts/{}/revisions/{}/restore", post["id"], rev);
+    let restored = app.send_ok(Method::POST, &uri, Some(&token), None).await;
+    assert_eq!(restored["data"]["title"], "Original");
+    assert_eq!(restored["data"]["status"], "Scheduled");
+    assert_eq!(restored["data"]["publish_at"], post["publish_at"]);
+}
+
+#[tokio::test]
+async fn restoring_keeps_the_current_status() {
+    let app = TestApp::new();
+    let token = editor_token(&app).await;
+    let body = json!({"title": "Original", "slug": "draft", "content": "text", "status": "Draft"});
+    let (post, rev) = edited_post(&app, &token, body).await;
+    let uri = format!("/v1/posts/{}", post["id"]);
+    let body = json!({"status": "Published"});
+    app.send_ok(Method::PATCH, &uri, Some(&token), Some(body))
+        .await;
+
+    let uri = format!("/v1/posts/{}/revisions/{}/restore", post["id"], rev);
+    let (status, text) = app.send(Method::POST, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", text);
+    let restored: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(restored["data"]["title"], "Original");
+    assert_eq!(restored["data"]["status"], "Published");
+}
+
+#[tokio::test]
+async fn revisions_of_trashed_posts_are_not_found() {
+    let app = TestApp::new();
+    let token = editor_token(&app).await;
+    let body =
+        json!({"title": "Original", "slug": "trashed", "content": "text", "status": "Draft"});
+    let (post, rev) = edited_post(&app, &token, body).await;
+    let id = post["id"].as_i64().unwrap();
+    app.state.post_service.get_revision(id, rev).await.unwrap();
+
+    app.send_ok(
+        Method::DELETE,
+        &format!("/v1/posts/{}", id),
+        Some(&token),
+        None,
+    )
+    .await;
+    let res = app.state.post_service.get_revision(id, rev).await;
+    assert!(matches!(res, Err(ServiceError::NotFound(_))));
+}
@@ -0,0 +1,45 @@
+//! 公开注册不能指定账号状态，创建任意状态的用户需要 `users:write`
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+mod common;
+
+use common::{PASSWORD, TestApp};
+
+#[tokio::test]
+async fn signup_ignores_the_requested_status() {
+    let app = TestApp::new();
+    let body = json!({"username": "alice", "password": PASSWORD, "status": "Blocked"});
+    app.send_ok(Method::POST, "/v1/signup", None, Some(body))
+        .await;
+    // 被忽略的状态不会阻止登录
+    let token = app.login("alice").await;
+    let response = app.send_ok(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(response["data"]["status"], "Active");
+}
+
+#[tokio::test]
+async fn creating_users_requires_users_write() {
+    let app = TestApp::new();
+    let body = json!({"username": "mallory", "password": PASSWORD, "status": "Active"});
+    let (status, _) = app
+        .send(Method::POST, "/v1/users", None, Some(body.clone()))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let (status, _) = app
+        .send(Method::POST, "/v1/users", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let token = app.admin_token().await;
+    let body = json!({"username": "bob", "password": PASSWORD, "status": "Blocked"});
+    app.send_ok(Method::POST, "/v1/users", Some(&token), Some(body))
+        .await;
+    let body = json!({"username": "bob", "password": PASSWORD});
+    let (status, _) = app.send(Method::POST, "/v1/login", None, Some(body)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
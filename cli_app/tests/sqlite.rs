@@ -0,0 +1,415 @@
+//! SQLite 存储后端：按配置选择后端、迁移的回滚和重新应用，接口在数据库上的行为与内存存储一致
+
+use axum::http::{Method, StatusCode, header};
+use cli_app::{
+    commands::migrate,
+    model::Role,
+    services::{error::ServiceError, user::PostsDisposal},
+};
+use serde_json::{Value, json};
+
+mod common;
+
+use common::{PASSWORD, TestApp};
+
+#[tokio::test]
+async fn data_is_kept_in_the_configured_database() {
+    let settings = common::sqlite_settings("backend");
+    let app = TestApp::with_settings(settings.clone()).await;
+    let id = app.create_user("alice").await;
+    drop(app);
+
+    //重新连接同一个数据库文件，之前创建的用户仍然存在
+    let app = TestApp::with_settings(settings).await;
+    let token = app.login("alice").await;
+    let me = app.send_ok(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(me["data"]["id"], id);
+}
+
+/// 创建拥有 `role` 角色的用户，返回登录令牌
+async fn user_with_role(app: &TestApp, username: &str, role: Role) -> String {
+    let id = app.create_user(username).await;
+    app.state.user_service.set_role(id, role).await.unwrap();
+    app.login(username).await
+}
+
+/// 编辑发布一篇文章，返回编辑的令牌和文章 ID
+async fn published_post(app: &TestApp, username: &str, slug: &str) -> (String, i64) {
+    let token = user_with_role(app, username, Role::Editor).await;
+    let body = json!({"title": "Hello", "slug": slug, "content": "text", "status": "Published"});
+    let post = app
+        .send_ok(Method::POST, "/v1/posts", Some(&token), Some(body))
+        .await;
+    (token, post["data"]["id"].as_i64().unwrap())
+}
+
+#[tokio::test]
+async fn all_migrations_can_be_reverted_and_applied_again() {
+    let settings = common::sqlite_settings("migrations");
+    let url = settings.database.url.clone().unwrap();
+    let pool = migrate::connect_sqlite(&url).await.unwrap();
+    let migrator = &migrate::SQLITE_MIGRATOR;
+
+    migrator.run(&pool).await.unwrap();
+    migrator.undo(&pool, 0).await.unwrap();
+    migrator.run(&pool).await.unwrap();
+    drop(pool);
+
+    let app = TestApp::with_settings(settings).await;
+    published_post(&app, "alice", "hello").await;
+}
+
+#[tokio::test]
+async fn rebuilding_the_comments_table_keeps_comments_and_replies() {
+    let settings = common::sqlite_settings("comment-authors");
+    let url = settings.database.url.clone().unwrap();
+    let app = TestApp::with_settings(settings).await;
+    let (token, post_id) = published_post(&app, "alice", "hello").await;
+    let uri = format!("/v1/posts/{}/comments", post_id);
+    let body = json!({"content": "first"});
+    let first = app
+        .send_ok(Method::POST, &uri, Some(&token), Some(body))
+        .await;
+    let body = json!({"content": "reply", "parent_id": first["data"]["id"]});
+    let reply = app
+        .send_ok(Method::POST, &uri, Some(&token), Some(body))
+        .await;
+
+    //0013 在 SQLite 上通过重建 comments 表修改外键，回滚和重新应用都不能丢失评论或回复关系
+    let pool = migrate::connect_sqlite(&url).await.unwrap();
+    migrate::SQLITE_MIGRATOR.undo(&pool, 12).await.unwrap();
+    migrate::SQLITE_MIGRATOR.run(&pool).await.unwrap();
+    let broken = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert!(broken.is_empty());
+
+    let comments = app.send_ok(Method::GET, &uri, None, None).await;
+    let comments = comments["data"].as_array().unwrap();
+    assert_eq!(comments.len(), 2);
+    assert_eq!(comments[0]["id"], first["data"]["id"]);
+    assert_eq!(comments[1]["id"], reply["data"]["id"]);
+    assert_eq!(comments[1]["parent_id"], first["data"]["id"]);
+    assert_eq!(comments[1]["author_id"], first["data"]["author_id"]);
+}
+
+#[tokio::test]
+async fn failed_deletion_leaves_posts_untouched() {
+    let app = TestApp::with_settings(common::sqlite_settings("failed-deletion")).await;
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let (_, post_id) = published_post(&app, "alice", "hello").await;
+    let alice = app
+        .state
+        .user_service
+        .get_user_by_username("alice")
+        .await
+        .unwrap();
+    let post_uri = format!("/v1/posts/{}", post_id);
+
+    //文章在事务中处理，用户版本不匹配时移入回收站的文章随事务回滚
+    let res = app
+        .state
+        .user_service
+        .delete_user(alice.id, PostsDisposal::Cascade, Some(alice.version + 1))
+        .await;
+    assert!(matches!(res, Err(ServiceError::PreconditionFailed(_))));
+    app.send_ok(Method::GET, &post_uri, None, None).await;
+
+    let uri = format!("/v1/users/{}?posts=reassign&reassign_to=9999", alice.id);
+    let (status, _) = app.send(Method::DELETE, &uri, admin, None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let uri = format!("/v1/users/{}", alice.id);
+    let (status, _) = app.send(Method::DELETE, &uri, admin, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let post = app.send_ok(Method::GET, &post_uri, None, None).await;
+    assert_eq!(post["data"]["author_id"], alice.id);
+}
+
+#[tokio::test]
+async fn trashed_users_can_be_restored_or_purged_with_their_posts() {
+    let app = TestApp::with_settings(common::sqlite_settings("trash")).await;
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let (_, post_id) = published_post(&app, "alice", "hello").await;
+    let (_, other_id) = published_post(&app, "bob", "other").await;
+    let alice = app
+        .state
+        .user_service
+        .get_user_by_username("alice")
+        .await
+        .unwrap();
+    let bob = app
+        .state
+        .user_service
+        .get_user_by_username("bob")
+        .await
+        .unwrap();
+
+    let uri = format!("/v1/users/{}?posts=cascade", alice.id);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let (status, _) = app
+        .send(Method::GET, &format!("/v1/posts/{}", post_id), None, None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let uri = format!("/v1/trash/users/{}/restore", alice.id);
+    app.send_ok(Method::POST, &uri, admin, None).await;
+    let uri = format!("/v1/trash/posts/{}/restore", post_id);
+    app.send_ok(Method::POST, &uri, admin, None).await;
+
+    //永久删除时转移文章，或连同文章一起删除
+    let uri = format!("/v1/users/{}?posts=cascade", alice.id);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let uri = format!(
+        "/v1/trash/users/{}?posts=reassign&reassign_to={}",
+        alice.id, bob.id
+    );
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let uri = format!("/v1/trash/posts/{}/restore", post_id);
+    let post = app.send_ok(Method::POST, &uri, admin, None).await;
+    assert_eq!(post["data"]["author_id"], bob.id);
+
+    let uri = format!("/v1/users/{}?posts=cascade", bob.id);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let uri = format!("/v1/trash/users/{}?posts=cascade", bob.id);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    for id in [post_id, other_id] {
+        let uri = format!("/v1/trash/posts/{}/restore", id);
+        let (status, _) = app.send(Method::POST, &uri, admin, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn purging_a_user_keeps_their_comments() {
+    let app = TestApp::with_settings(common::sqlite_settings("comment-purge")).await;
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let (_, post_id) = published_post(&app, "alice", "hello").await;
+    let bob = app.create_user("bob").await;
+    let token = app.login("bob").await;
+    let uri = format!("/v1/posts/{}/comments", post_id);
+    let body = json!({"content": "first"});
+    let comment = app
+        .send_ok(Method::POST, &uri, Some(&token), Some(body))
+        .await;
+
+    app.send_ok(Method::DELETE, &format!("/v1/users/{}", bob), admin, None)
+        .await;
+    let purge_uri = format!("/v1/trash/users/{}", bob);
+    app.send_ok(Method::DELETE, &purge_uri, admin, None).await;
+    let comments = app.send_ok(Method::GET, &uri, None, None).await;
+    assert_eq!(comments["data"][0]["id"], comment["data"]["id"]);
+    assert_eq!(comments["data"][0]["author_id"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn authors_can_only_change_their_own_posts() {
+    let app = TestApp::with_settings(common::sqlite_settings("ownership")).await;
+    let alice = user_with_role(&app, "alice", Role::Author).await;
+    let bob = user_with_role(&app, "bob", Role::Author).await;
+    let body = json!({"title": "Hello", "slug": "hello", "content": "text", "status": "Draft"});
+    let post = app
+        .send_ok(Method::POST, "/v1/posts", Some(&alice), Some(body))
+        .await;
+    let uri = format!("/v1/posts/{}", post["data"]["id"]);
+
+    let body = json!({"title": "Taken over"});
+    let (status, _) = app.send(Method::PATCH, &uri, Some(&bob), Some(body)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.send(Method::DELETE, &uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let body = json!({"title": "Edited"});
+    let post = app
+        .send_ok(Method::PATCH, &uri, Some(&alice), Some(body))
+        .await;
+    assert_eq!(post["data"]["title"], "Edited");
+    app.send_ok(Method::DELETE, &uri, Some(&alice), None).await;
+}
+
+#[tokio::test]
+async fn unique_violations_are_reported_as_conflicts() {
+    let app = TestApp::with_settings(common::sqlite_settings("conflicts")).await;
+    let (token, _) = published_post(&app, "alice", "hello").await;
+
+    //SQLite 的唯一约束错误同样转换为 409 problem+json，并指出冲突的字段
+    let body = json!({"title": "Again", "slug": "hello", "content": "text", "status": "Draft"});
+    let (status, text) = app
+        .send(Method::POST, "/v1/posts", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let problem: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(problem["type"], "/problems/conflict");
+    assert_eq!(problem["detail"], "Slug already exists");
+
+    let body = json!({"username": "alice", "password": PASSWORD});
+    let (status, text) = app.send(Method::POST, "/v1/signup", None, Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let problem: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(problem["detail"], "Username already exists");
+}
+
+#[tokio::test]
+async fn rejected_posts_are_not_written() {
+    let app = TestApp::with_settings(common::sqlite_settings("validation")).await;
+    let token = user_with_role(&app, "alice", Role::Editor).await;
+
+    let body = json!({"title": "", "slug": "Not A Slug", "content": "text", "status": "Draft"});
+    let (status, text) = app
+        .send(Method::POST, "/v1/posts", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(problem["type"], "/problems/validation");
+
+    //标签和分类在数据库中检查，检查失败时事务回滚，文章不会写入
+    for body in [
+        json!({"title": "Hello", "slug": "hello", "content": "text", "status": "Draft", "tags": ["missing"]}),
+        json!({"title": "Hello", "slug": "hello", "content": "text", "status": "Draft", "category_id": 9999}),
+    ] {
+        let (status, _) = app
+            .send(Method::POST, "/v1/posts", Some(&token), Some(body))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    let body = json!({"title": "Hello", "slug": "hello", "content": "text", "status": "Draft"});
+    app.send_ok(Method::POST, "/v1/posts", Some(&token), Some(body))
+        .await;
+}
+
+#[tokio::test]
+async fn patch_only_changes_the_given_fields() {
+    let app = TestApp::with_settings(common::sqlite_settings("patch")).await;
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let (token, post_id) = published_post(&app, "alice", "hello").await;
+    let alice = app
+        .state
+        .user_service
+        .get_user_by_username("alice")
+        .await
+        .unwrap();
+
+    let uri = format!("/v1/posts/{}", post_id);
+    let body = json!({"title": "Edited"});
+    let post = app
+        .send_ok(Method::PATCH, &uri, Some(&token), Some(body))
+        .await;
+    assert_eq!(post["data"]["title"], "Edited");
+    assert_eq!(post["data"]["slug"], "hello");
+    assert_eq!(post["data"]["content"], "text");
+    assert_eq!(post["data"]["status"], "Published");
+
+    let uri = format!("/v1/users/{}", alice.id);
+    let body = json!({"email": "alice@example.com"});
+    let user = app.send_ok(Method::PATCH, &uri, admin, Some(body)).await;
+    assert_eq!(user["data"]["email"], "alice@example.com");
+    assert_eq!(user["data"]["username"], "alice");
+    assert_eq!(user["data"]["status"], "Active");
+    //没有修改密码，原密码仍然可以登录
+    app.login("alice").await;
+}
+
+#[tokio::test]
+async fn stale_etags_are_rejected() {
+    let app = TestApp::with_settings(common::sqlite_settings("etags")).await;
+    let (token, post_id) = published_post(&app, "alice", "hello").await;
+    let token = Some(token.as_str());
+    let uri = format!("/v1/posts/{}", post_id);
+
+    let (status, headers, _) = app
+        .send_with_headers(Method::GET, &uri, token, &[], None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+    let (status, _, _) = app
+        .send_with_headers(
+            Method::GET,
+            &uri,
+            token,
+            &[(header::IF_NONE_MATCH, &etag)],
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    //版本号在数据库中比较，先到的修改生效，持有旧 ETag 的修改返回 412
+    let if_match = [(header::IF_MATCH, etag.as_str())];
+    let body = json!({"title": "First"});
+    let (status, headers, _) = app
+        .send_with_headers(Method::PATCH, &uri, token, &if_match, Some(body))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(headers[header::ETAG], etag.as_str());
+    let body = json!({"title": "Second"});
+    let (status, _, _) = app
+        .send_with_headers(Method::PATCH, &uri, token, &if_match, Some(body))
+        .await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _, _) = app
+        .send_with_headers(Method::DELETE, &uri, token, &if_match, None)
+        .await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    let post = app.send_ok(Method::GET, &uri, token, None).await;
+    assert_eq!(post["data"]["title"], "First");
+}
+
+#[tokio::test]
+async fn posts_are_filtered_by_tag_and_category_tree() {
+    let app = TestApp::with_settings(common::sqlite_settings("taxonomy")).await;
+    let token = user_with_role(&app, "alice", Role::Editor).await;
+    let token = Some(token.as_str());
+    for slug in ["rust", "web"] {
+        let body = json!({"name": slug, "slug": slug});
+        app.send_ok(Method::POST, "/v1/tags", token, Some(body))
+            .await;
+    }
+    let body = json!({"name": "Tech", "slug": "tech"});
+    let tech = app
+        .send_ok(Method::POST, "/v1/categories", token, Some(body))
+        .await;
+    let body = json!({"name": "Languages", "slug": "languages", "parent_id": tech["data"]["id"]});
+    let languages = app
+        .send_ok(Method::POST, "/v1/categories", token, Some(body))
+        .await;
+    let body = json!({"title": "Rust", "slug": "rust", "content": "text", "status": "Published",
+        "tags": ["rust"], "category_id": languages["data"]["id"]});
+    let rust = app
+        .send_ok(Method::POST, "/v1/posts", token, Some(body))
+        .await;
+    let body = json!({"title": "Web", "slug": "web", "content": "text", "status": "Published", "tags": ["web"]});
+    app.send_ok(Method::POST, "/v1/posts", token, Some(body))
+        .await;
+
+    //分类过滤包含子分类中的文章
+    for uri in ["/v1/posts?tag=rust", "/v1/posts?category=tech"] {
+        let posts = app.send_ok(Method::GET, uri, None, None).await;
+        let posts = posts["data"].as_array().unwrap();
+        assert_eq!(posts.len(), 1, "{}", uri);
+        assert_eq!(posts[0]["id"], rust["data"]["id"]);
+    }
+
+    let uri = format!("/v1/categories/{}", tech["data"]["id"]);
+    let body = json!({"name": "Tech", "slug": "tech", "parent_id": languages["data"]["id"]});
+    let (status, _) = app.send(Method::PUT, &uri, token, Some(body)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app.send(Method::DELETE, &uri, token, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let tags = app.send_ok(Method::GET, "/v1/tags", None, None).await;
+    let tag = tags["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tag| tag["slug"] == "rust")
+        .unwrap();
+    assert_eq!(tag["post_count"], 1);
+    let uri = format!("/v1/tags/{}", tag["id"]);
+    app.send_ok(Method::DELETE, &uri, token, None).await;
+    let uri = format!("/v1/posts/{}", rust["data"]["id"]);
+    let post = app.send_ok(Method::GET, &uri, None, None).await;
+    assert_eq!(post["data"]["tags"], json!([]));
+}
@@ -0,0 +1,60 @@
+//! 两步验证：启用流程、两步登录和一次性恢复码
+
+use axum::http::{Method, StatusCode};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde_json::{Value, json};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+mod common;
+
+use common::{PASSWORD, TestApp};
+
+#[tokio::test]
+async fn two_factor_login_requires_a_single_use_code() {
+    let mut settings = common::settings();
+    settings.two_factor.encryption_key = Some(BASE64_STANDARD.encode([7u8; 32]));
+    let app = TestApp::with_settings(settings).await;
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+
+    let enrollment = app
+        .send_ok(Method::POST, "/v1/me/2fa/enroll", Some(&token), None)
+        .await;
+    let secret = Secret::Encoded(enrollment["secret"].as_str().unwrap().to_string())
+        .to_bytes()
+        .unwrap();
+    let totp = TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "alice".to_string()).unwrap();
+    let body = json!({"code": totp.generate_current().unwrap()});
+    let response = app
+        .send_ok(Method::POST, "/v1/me/2fa/enable", Some(&token), Some(body))
+        .await;
+    let recovery_code = response["recovery_codes"][0].as_str().unwrap().to_string();
+
+    // 密码正确时只返回两步验证令牌
+    let body = json!({"username": "alice", "password": PASSWORD});
+    let (status, text) = app.send(Method::POST, "/v1/login", None, Some(body)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let challenge: Value = serde_json::from_str(&text).unwrap();
+    assert!(challenge.get("token").is_none(), "{}", text);
+    let challenge_token = challenge["challenge_token"].as_str().unwrap();
+    let (status, _) = app
+        .send(Method::GET, "/v1/me", Some(challenge_token), None)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let body = json!({"challenge_token": challenge_token, "code": recovery_code});
+    let response = app
+        .send_ok(Method::POST, "/v1/login/2fa", None, Some(body.clone()))
+        .await;
+    app.send_ok(
+        Method::GET,
+        "/v1/me",
+        Some(response["token"].as_str().unwrap()),
+        None,
+    )
+    .await;
+    let (status, _) = app
+        .send(Method::POST, "/v1/login/2fa", None, Some(body))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,116 @@
+//! 删除用户时对其名下文章和评论的处理：失败的删除不能改动文章
+
+use axum::http::{Method, StatusCode};
+use cli_app::{
+    model::Role,
+    services::{error::ServiceError, user::PostsDisposal},
+};
+use serde_json::json;
+
+mod common;
+
+use common::TestApp;
+
+/// 创建一个拥有一篇文章的作者，返回用户 ID 和文章 ID
+async fn author_with_post(app: &TestApp) -> (i64, i64) {
+    let id = app.create_user("alice").await;
+    app.state
+        .user_service
+        .set_role(id, Role::Author)
+        .await
+        .unwrap();
+    let token = app.login("alice").await;
+    let body = json!({"title": "Hello", "slug": "hello", "content": "text", "status": "Draft"});
+    let post = app
+        .send_ok(Method::POST, "/v1/posts", Some(&token), Some(body))
+        .await;
+    (id, post["data"]["id"].as_i64().unwrap())
+}
+
+#[tokio::test]
+async fn failed_deletion_leaves_posts_untouched() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let (id, post_id) = author_with_post(&app).await;
+    let post_uri = format!("/v1/posts/{}", post_id);
+
+    let user = app.state.user_service.get_user_by_id(id).await.unwrap();
+    let err = app
+        .state
+        .user_service
+        .delete_user(id, PostsDisposal::Cascade, Some(user.version + 1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::PreconditionFailed(_)));
+    app.send_ok(Method::GET, &post_uri, admin, None).await;
+
+    let uri = format!("/v1/users/{}?posts=reassign&reassign_to=9999", id);
+    let (status, _) = app.send(Method::DELETE, &uri, admin, None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app
+        .send(Method::DELETE, &format!("/v1/users/{}", id), admin, None)
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    app.send_ok(Method::GET, &format!("/v1/users/{}", id), admin, None)
+        .await;
+    let post = app.send_ok(Method::GET, &post_uri, admin, None).await;
+    assert_eq!(post["data"]["author_id"], id);
+
+    let uri = format!("/v1/users/{}?posts=cascade", id);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let (status, _) = app.send(Method::GET, &post_uri, admin, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn purging_a_user_can_reassign_posts() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let bob = app.create_user("bob").await;
+    let (id, post_id) = author_with_post(&app).await;
+
+    let uri = format!("/v1/users/{}?posts=cascade", id);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let uri = format!("/v1/trash/users/{}?posts=block", id);
+    let (status, _) = app.send(Method::DELETE, &uri, admin, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let uri = format!("/v1/trash/users/{}?posts=reassign&reassign_to={}", id, bob);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let (status, _) = app.send(Method::DELETE, &uri, admin, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let uri = format!("/v1/trash/posts/{}/restore", post_id);
+    let post = app.send_ok(Method::POST, &uri, admin, None).await;
+    assert_eq!(post["data"]["author_id"], bob);
+}
+
+#[tokio::test]
+async fn purging_a_user_keeps_their_comments() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let (_, post_id) = author_with_post(&app).await;
+    let post_uri = format!("/v1/posts/{}", post_id);
+    let body = json!({"status": "Published"});
+    app.send_ok(Method::PATCH, &post_uri, admin, Some(body))
+        .await;
+
+    let bob = app.create_user("bob").await;
+    let token = app.login("bob").await;
+    let comments_uri = format!("{}/comments", post_uri);
+    let body = json!({"content": "first"});
+    let comment = app
+        .send_ok(Method::POST, &comments_uri, Some(&token), Some(body))
+        .await;
+    assert_eq!(comment["data"]["author_id"], bob);
+
+    app.send_ok(Method::DELETE, &format!("/v1/users/{}", bob), admin, None)
+        .await;
+    let uri = format!("/v1/trash/users/{}", bob);
+    app.send_ok(Method::DELETE, &uri, admin, None).await;
+    let comments = app.send_ok(Method::GET, &comments_uri, None, None).await;
+    assert_eq!(comments["data"][0]["id"], comment["data"]["id"]);
+    assert_eq!(comments["data"][0]["author_id"], serde_json::Value::Null);
+}
@@ -0,0 +1,133 @@
+//! 回归测试：任何接口的响应和 OpenAPI 文档中的响应结构都不能包含密码字段
+
+use axum::http::{Method, StatusCode};
+use serde_json::{Value, json};
+
+mod common;
+
+use common::{PASSWORD, TestApp, assert_no_password, has_password_key};
+
+#[tokio::test]
+async fn create_user_does_not_return_password() {
+    let app = TestApp::new();
+    let body = json!({"username": "alice", "password": PASSWORD});
+    let response = app
+        .send_ok(Method::POST, "/v1/signup", None, Some(body))
+        .await;
+    assert_eq!(response["data"]["username"], "alice");
+    let token = app.admin_token().await;
+    let body = json!({"username": "bob", "password": PASSWORD, "status": "Active"});
+    let response = app
+        .send_ok(Method::POST, "/v1/users", Some(&token), Some(body))
+        .await;
+    assert_eq!(response["data"]["username"], "bob");
+    // 公开的用户信息不包含状态和角色
+    assert!(response["data"].get("role").is_none());
+}
+
+#[tokio::test]
+async fn user_endpoints_do_not_return_password() {
+    let app = TestApp::new();
+    let token = app.admin_token().await;
+    let token = Some(token.as_str());
+    let id = app.create_user("alice").await;
+
+    let response = app.send_ok(Method::GET, "/v1/users", token, None).await;
+    assert_eq!(response["total"], 2);
+    let response = app
+        .send_ok(Method::GET, &format!("/v1/users/{}", id), token, None)
+        .await;
+    assert_eq!(response["data"]["role"], "Reader");
+    app.send_ok(Method::GET, "/v1/users/name/alice", token, None)
+        .await;
+
+    let body = json!({"id": id, "username": "alice", "password": "password2", "status": "Active"});
+    app.send_ok(Method::PUT, &format!("/v1/users/{}", id), token, Some(body))
+        .await;
+    let body = json!({"password": "password3", "status": "Blocked"});
+    app.send_ok(
+        Method::PATCH,
+        &format!("/v1/users/{}", id),
+        token,
+        Some(body),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn trashed_user_endpoints_do_not_return_password() {
+    let app = TestApp::new();
+    let token = app.admin_token().await;
+    let token = Some(token.as_str());
+    let id = app.create_user("alice").await;
+
+    app.send_ok(Method::DELETE, &format!("/v1/users/{}", id), token, None)
+        .await;
+    let response = app
+        .send_ok(Method::GET, "/v1/trash/users", token, None)
+        .await;
+    assert_eq!(response["total"], 1);
+    app.send_ok(
+        Method::POST,
+        &format!("/v1/trash/users/{}/restore", id),
+        token,
+        None,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn me_endpoints_do_not_return_password() {
+    let app = TestApp::new();
+    app.create_user("alice").await;
+    let token = app.login("alice").await;
+    let token = Some(token.as_str());
+
+    let response = app.send_ok(Method::GET, "/v1/me", token, None).await;
+    assert_eq!(response["data"]["username"], "alice");
+    let body = json!({"username": "alice2", "current_password": PASSWORD});
+    app.send_ok(Method::PATCH, "/v1/me", token, Some(body))
+        .await;
+}
+
+#[tokio::test]
+async fn error_responses_do_not_return_password() {
+    let app = TestApp::new();
+    app.create_user("alice").await;
+
+    let body = json!({"username": "alice", "password": "wrong-password"});
+    let (status, text) = app.send(Method::POST, "/v1/login", None, Some(body)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_no_password("POST /v1/login", &text);
+
+    let body = json!({"username": "alice", "password": PASSWORD});
+    let (status, text) = app.send(Method::POST, "/v1/signup", None, Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_no_password("POST /v1/signup", &text);
+}
+
+#[tokio::test]
+async fn openapi_response_schemas_have_no_password() {
+    let app = TestApp::new();
+    let (status, text) = app
+        .send(Method::GET, "/v1/api-docs/openapi.json", None, None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let doc: Value = serde_json::from_str(&text).unwrap();
+    let schemas = doc["components"]["schemas"].as_object().unwrap();
+
+    assert!(schemas.contains_key("PublicUser"));
+    assert!(schemas.contains_key("AdminUser"));
+    assert!(!schemas.contains_key("User"));
+    // 只有请求体可以带密码
+    for (name, schema) in schemas {
+        if name.ends_with("Request") {
+            continue;
+        }
+        assert!(
+            !has_password_key(&schema["properties"]),
+            "schema {} has a password property",
+            name
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! 管理员修改用户：用户名、密码或状态变化后该用户的会话全部失效
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+mod common;
+
+use common::{PASSWORD, TestApp};
+
+#[tokio::test]
+async fn changing_credentials_signs_the_user_out() {
+    let app = TestApp::new();
+    let admin = app.admin_token().await;
+    let admin = Some(admin.as_str());
+    let id = app.create_user("alice").await;
+    let uri = format!("/v1/users/{}", id);
+
+    // 只修改邮箱不影响已登录的会话
+    let token = app.login("alice").await;
+    let body = json!({"email": "alice@example.com"});
+    app.send_ok(Method::PATCH, &uri, admin, Some(body)).await;
+    app.send_ok(Method::GET, "/v1/me", Some(&token), None).await;
+
+    let body = json!({"status": "Blocked"});
+    app.send_ok(Method::PATCH, &uri, admin, Some(body)).await;
+    let (status, _) = app.send(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let body = json!({"status": "Active"});
+    app.send_ok(Method::PATCH, &uri, admin, Some(body)).await;
+    let token = app.login("alice").await;
+    let body = json!({"password": "password2"});
+    app.send_ok(Method::PATCH, &uri, admin, Some(body)).await;
+    let (status, _) = app.send(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let body = json!({"username": "alice", "password": "password2"});
+    let response = app
+        .send_ok(Method::POST, "/v1/login", None, Some(body))
+        .await;
+    let token = response["token"].as_str().unwrap().to_string();
+    let body = json!({"id": id, "username": "alice2", "password": PASSWORD, "status": "Active"});
+    app.send_ok(Method::PUT, &uri, admin, Some(body)).await;
+    let (status, _) = app.send(Method::GET, "/v1/me", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    app.login("alice2").await;
+}